@@ -0,0 +1,10 @@
+CREATE TABLE custom_commands (
+    id INT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    channel VARCHAR(25) NOT NULL,
+    name VARCHAR(25) NOT NULL,
+    response TEXT NOT NULL,
+    level VARCHAR(16) NOT NULL DEFAULT 'everyone',
+    cooldown INT UNSIGNED NOT NULL DEFAULT 5,
+    uses INT UNSIGNED NOT NULL DEFAULT 0,
+    UNIQUE KEY channel_name (channel, name)
+);
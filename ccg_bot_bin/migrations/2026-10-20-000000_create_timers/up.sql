@@ -0,0 +1,10 @@
+CREATE TABLE timers (
+    id INT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    channel VARCHAR(25) NOT NULL,
+    name VARCHAR(25) NOT NULL,
+    message TEXT NOT NULL,
+    interval_minutes INT UNSIGNED NOT NULL,
+    min_lines INT UNSIGNED NOT NULL DEFAULT 5,
+    live_only BOOLEAN NOT NULL DEFAULT FALSE,
+    UNIQUE KEY channel_name (channel, name)
+);
@@ -0,0 +1,12 @@
+CREATE TABLE audit_log (
+    id INT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    platform VARCHAR(16) NOT NULL,
+    channel VARCHAR(100) NOT NULL,
+    moderator VARCHAR(100) NOT NULL,
+    action VARCHAR(16) NOT NULL,
+    target VARCHAR(100) NOT NULL,
+    reason TEXT,
+    duration_seconds INT UNSIGNED,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    KEY channel_created (channel, created_at)
+);
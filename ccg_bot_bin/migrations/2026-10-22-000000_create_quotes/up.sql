@@ -0,0 +1,8 @@
+CREATE TABLE quotes (
+    id INT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    text TEXT NOT NULL,
+    game VARCHAR(100),
+    platform VARCHAR(16) NOT NULL,
+    added_by VARCHAR(100) NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1 @@
+DROP TABLE broadcaster_tokens;
@@ -0,0 +1,9 @@
+CREATE TABLE broadcaster_tokens (
+    broadcaster_id VARCHAR(20) NOT NULL PRIMARY KEY,
+    login VARCHAR(25) NOT NULL UNIQUE,
+    access_token VARCHAR(255) NOT NULL,
+    refresh_token VARCHAR(255) NOT NULL,
+    scopes TEXT NOT NULL,
+    expires_at DATETIME NOT NULL,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+);
@@ -0,0 +1,10 @@
+CREATE TABLE reward_actions (
+    id INT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    channel VARCHAR(25) NOT NULL,
+    reward_title VARCHAR(45) NOT NULL,
+    action VARCHAR(16) NOT NULL,
+    argument TEXT NOT NULL,
+    duration_minutes INT UNSIGNED,
+    auto_settle BOOLEAN NOT NULL DEFAULT FALSE,
+    UNIQUE KEY channel_reward (channel, reward_title)
+);
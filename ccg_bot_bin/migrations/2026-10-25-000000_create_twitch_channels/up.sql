@@ -0,0 +1,6 @@
+CREATE TABLE twitch_channels (
+    name VARCHAR(25) NOT NULL PRIMARY KEY,
+    joined BOOLEAN NOT NULL,
+    updated_by VARCHAR(100) NOT NULL,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+);
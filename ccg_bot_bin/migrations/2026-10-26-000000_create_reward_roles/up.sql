@@ -0,0 +1,6 @@
+CREATE TABLE reward_roles (
+    discord_id BIGINT UNSIGNED NOT NULL,
+    role_id BIGINT UNSIGNED NOT NULL,
+    expires_at DATETIME NOT NULL,
+    PRIMARY KEY (discord_id, role_id)
+);
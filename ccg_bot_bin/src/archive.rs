@@ -0,0 +1,247 @@
+//!Structured chat archive for both platforms.
+//!
+//!Every message the bot sees is appended as a single JSON line to a file named after the UTC day
+//!it was sent on (`chat-YYYY-MM-DD.jsonl`). When the day rolls over a new file is started and any
+//!file older than `retention_days` is removed. A retention of `0` keeps files forever.
+
+//crate
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, debug};
+
+//chrono
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+//serenity
+use serenity::all::Message;
+
+//std
+use std::fs::{self, File, OpenOptions};
+use std::io::{Error as IoError, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+//twitch_irc
+use twitch_irc::message::PrivmsgMessage;
+
+const FILE_PREFIX: &str = "chat-";
+const FILE_SUFFIX: &str = ".jsonl";
+
+lazy_static! {
+    static ref ARCHIVE: Mutex<ChatArchive> = Mutex::new(ChatArchive::new(
+        CONFIG.archive_directory.clone(),
+        CONFIG.archive_retention_days
+    ));
+}
+
+///The platform an [`ArchiveRecord`] was seen on.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    Discord,
+    Twitch,
+}
+
+///A single archived chat message.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ArchiveRecord {
+    pub platform: Platform,
+    pub channel: String,
+    pub user_id: String,
+    pub user_login: String,
+    pub message_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub content: String,
+    ///Twitch badges as `name/version` or Discord role names.
+    pub badges: Vec<String>,
+}
+
+impl ArchiveRecord {
+    ///Builds a record from a Discord message, `roles` being the names of the author's guild roles.
+    pub fn discord(message: &Message, channel: String, roles: Vec<String>) -> Self {
+        Self {
+            platform: Platform::Discord,
+            channel,
+            user_id: message.author.id.to_string(),
+            user_login: message.author.name.clone(),
+            message_id: message.id.to_string(),
+            timestamp: DateTime::from_timestamp(message.timestamp.unix_timestamp(), 0)
+                .unwrap_or_default(),
+            content: message.content.clone(),
+            badges: roles,
+        }
+    }
+}
+
+impl From<&PrivmsgMessage> for ArchiveRecord {
+    fn from(message: &PrivmsgMessage) -> Self {
+        Self {
+            platform: Platform::Twitch,
+            channel: message.channel_login.clone(),
+            user_id: message.sender.id.clone(),
+            user_login: message.sender.login.clone(),
+            message_id: message.message_id.clone(),
+            timestamp: message.server_timestamp,
+            content: message.message_text.clone(),
+            badges: message.badges.iter().map(|b| format!("{}/{}", b.name, b.version)).collect(),
+        }
+    }
+}
+
+///Writes [`ArchiveRecord`]s to a daily rotated JSON lines file.
+#[derive(Debug)]
+pub struct ChatArchive {
+    directory: PathBuf,
+    retention_days: u64,
+    current: Option<(NaiveDate, File)>,
+}
+
+impl ChatArchive {
+    ///Constructs a [`ChatArchive`] writing into `directory`, nothing is opened until the first write.
+    pub fn new(directory: impl Into<PathBuf>, retention_days: u64) -> Self {
+        Self { directory: directory.into(), retention_days, current: None }
+    }
+
+    ///Path of the file holding the records for `date`.
+    pub fn path_for(&self, date: NaiveDate) -> PathBuf {
+        self.directory.join(format!("{FILE_PREFIX}{}{FILE_SUFFIX}", date.format("%Y-%m-%d")))
+    }
+
+    ///Appends `record` to the file for the day it was sent on, rotating if needed.
+    pub fn write(&mut self, record: &ArchiveRecord) -> Result<(), IoError> {
+        let date = record.timestamp.date_naive();
+        let rotate = match &self.current {
+            Some((current, _)) => *current != date,
+            None => true,
+        };
+        if rotate {
+            self.rotate(date)?;
+        }
+        let (_, file) = self.current.as_mut().expect("archive file was just opened");
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        file.write_all(&line)
+    }
+
+    fn rotate(&mut self, date: NaiveDate) -> Result<(), IoError> {
+        fs::create_dir_all(&self.directory)?;
+        let file = OpenOptions::new().create(true).append(true).open(self.path_for(date))?;
+        self.current = Some((date, file));
+        let removed = self.prune(date)?;
+        if removed > 0 {
+            debug!("removed {removed} expired chat archive file(s)");
+        }
+        Ok(())
+    }
+
+    ///Removes archive files older than the retention window relative to `today`.
+    ///
+    ///Returns how many files were removed.
+    pub fn prune(&self, today: NaiveDate) -> Result<usize, IoError> {
+        if self.retention_days == 0 {
+            return Ok(0);
+        }
+        let cutoff = today - Duration::days(self.retention_days as i64);
+        let mut removed = 0;
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            let date = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_prefix(FILE_PREFIX))
+                .and_then(|n| n.strip_suffix(FILE_SUFFIX))
+                .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+            if let Some(date) = date {
+                if date < cutoff {
+                    fs::remove_file(&path)?;
+                    removed += 1;
+                }
+            }
+        }
+        Ok(removed)
+    }
+}
+
+///Archives `record` if the archive is enabled in the config, logging any failure.
+pub fn record(record: ArchiveRecord) {
+    if !CONFIG.archive_enabled {
+        return;
+    }
+    let mut archive = match ARCHIVE.lock() {
+        Ok(archive) => archive,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    if let Err(e) = archive.write(&record) {
+        error!("Unable to archive {:?} message {}: {e}", record.platform, record.message_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use twitch_irc::message::IRCMessage;
+
+    fn record_at(timestamp: DateTime<Utc>) -> ArchiveRecord {
+        ArchiveRecord {
+            platform: Platform::Twitch,
+            channel: "zoes17".to_string(),
+            user_id: "12345678".to_string(),
+            user_login: "testuser".to_string(),
+            message_id: "8da29c58-d182-40cd-8b65-1dc446b45c65".to_string(),
+            timestamp,
+            content: "This is a test".to_string(),
+            badges: vec!["moderator/1".to_string()],
+        }
+    }
+
+    #[test]
+    fn from_privmsg() {
+        let src = "@badge-info=;badges=moderator/1;color=#AA66FF;display-name=TestUser;emotes=;flags=;id=8da29c58-d182-40cd-8b65-1dc446b45c65;mod=1;room-id=78127347;subscriber=0;tmi-sent-ts=1693037683123;turbo=0;user-id=12345678;user-type= :testuser!testuser@testuser.tmi.twitch.tv PRIVMSG #zoes17 :This is a test";
+        let message = PrivmsgMessage::try_from(IRCMessage::parse(src).unwrap()).unwrap();
+        let record = ArchiveRecord::from(&message);
+        assert_eq!(record, record_at(message.server_timestamp));
+    }
+
+    #[test]
+    fn writes_json_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut archive = ChatArchive::new(dir.path(), 30);
+        let now = Utc::now();
+        archive.write(&record_at(now)).unwrap();
+        archive.write(&record_at(now)).unwrap();
+        let file = File::open(archive.path_for(now.date_naive())).unwrap();
+        let lines: Vec<String> = BufReader::new(file).lines().map(|l| l.unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        let parsed: ArchiveRecord = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(parsed, record_at(now));
+    }
+
+    #[test]
+    fn rotates_daily_and_prunes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut archive = ChatArchive::new(dir.path(), 2);
+        let now = Utc::now();
+        let old = now - Duration::days(3);
+        archive.write(&record_at(old)).unwrap();
+        assert!(archive.path_for(old.date_naive()).exists());
+        archive.write(&record_at(now)).unwrap();
+        assert!(archive.path_for(now.date_naive()).exists());
+        assert!(!archive.path_for(old.date_naive()).exists());
+    }
+
+    #[test]
+    fn zero_retention_keeps_everything() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut archive = ChatArchive::new(dir.path(), 0);
+        let now = Utc::now();
+        let old = now - Duration::days(365);
+        archive.write(&record_at(old)).unwrap();
+        archive.write(&record_at(now)).unwrap();
+        assert!(archive.path_for(old.date_naive()).exists());
+    }
+}
@@ -0,0 +1,110 @@
+//!Audit log of moderation actions taken through the bot.
+//!
+//!Entries are kept in the `audit_log` table and posted to the Discord mod-log channel if one is
+//!configured. Failing to record one never stops the action itself, the error is only logged.
+
+//crate
+use crate::db::{self, models::NewAuditEntry};
+use crate::discord::builders::discordembed::DiscordEmbed;
+use crate::discord::HTTP;
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, info};
+
+//serenity
+use serenity::all::{ChannelId, CreateEmbedFooter, CreateMessage};
+
+///Orange, to stand out from announcements.
+const MOD_LOG_COLOR: u32 = 0xE67E22;
+
+///Records `entry` in the background, logging instead of returning any error.
+pub fn record(entry: NewAuditEntry) {
+    info!(
+        "[audit / {} #{}] {} used {} on {}",
+        entry.platform, entry.channel, entry.moderator, entry.action, entry.target
+    );
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+        return save(&entry);
+    };
+    runtime.spawn(async move {
+        let row = entry.clone();
+        if let Err(e) = db::blocking(move || db::create_audit_entry(&row)).await {
+            failed(&entry, e);
+        }
+        if let Some(channel) = mod_log_channel() {
+            post(channel, entry).await;
+        }
+    });
+}
+
+fn save(entry: &NewAuditEntry) {
+    if let Err(e) = db::create_audit_entry(entry) {
+        failed(entry, e);
+    }
+}
+
+fn failed(entry: &NewAuditEntry, e: eyre::Report) {
+    error!("Unable to record {} of {} in the audit log: {e:?}", entry.action, entry.target);
+}
+
+fn mod_log_channel() -> Option<ChannelId> {
+    let id = CONFIG.mod_log_channel_id.as_ref()?;
+    match id.parse::<u64>() {
+        Ok(id) if id != 0 => Some(ChannelId::new(id)),
+        _ => {
+            error!("invalid mod_log channel_id `{id}`");
+            None
+        },
+    }
+}
+
+///The text of the mod-log message for `entry`.
+pub fn describe(entry: &NewAuditEntry) -> String {
+    let mut description =
+        format!("**{}** used **{}** on {}", entry.moderator, entry.action, entry.target);
+    if let Some(secs) = entry.duration_seconds {
+        description.push_str(&format!(" for {secs}s"));
+    }
+    if let Some(reason) = &entry.reason {
+        description.push_str(&format!("\n{reason}"));
+    }
+    description
+}
+
+async fn post(channel: ChannelId, entry: NewAuditEntry) {
+    let footer = CreateEmbedFooter::new(format!("{} #{}", entry.platform, entry.channel));
+    let embed = DiscordEmbed::new()
+        .description(describe(&entry))
+        .color(MOD_LOG_COLOR)
+        .footer(footer)
+        .build();
+    if let Err(e) = channel.send_message(&*HTTP, CreateMessage::new().embed(embed)).await {
+        error!("Unable to post {} of {} to the mod-log: {e}", entry.action, entry.target);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_entries() {
+        let mut entry = NewAuditEntry {
+            platform: "twitch".to_string(),
+            channel: "testchannel".to_string(),
+            moderator: "testmoderator".to_string(),
+            action: "timeout".to_string(),
+            target: "testuser".to_string(),
+            reason: Some("spam".to_string()),
+            duration_seconds: Some(600),
+        };
+        assert_eq!(
+            describe(&entry),
+            "**testmoderator** used **timeout** on testuser for 600s\nspam"
+        );
+        entry.reason = None;
+        entry.duration_seconds = None;
+        assert_eq!(describe(&entry), "**testmoderator** used **timeout** on testuser");
+    }
+}
@@ -1,15 +1,207 @@
 use crate::env;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::io::Error as IoError;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 struct ConfigToml {
+    alerts: Option<Alerts>,
+    announcements: Option<ConfigTomlAnnouncements>,
+    archive: Option<ConfigTomlArchive>,
+    bridge: Option<Vec<Bridge>>,
+    broadcaster_auth: Option<ConfigTomlBroadcasterAuth>,
+    commands: Option<ConfigTomlCommands>,
+    eventsub: Option<EventSub>,
+    filters: Option<Filters>,
+    mirror: Option<Vec<Mirror>>,
+    mod_log: Option<ConfigTomlModLog>,
+    polls: Option<Polls>,
+    role_sync: Option<RoleSync>,
+    shoutouts: Option<Shoutouts>,
     database: Option<ConfigTomlDatabase>,
     discord: Option<ConfigTomlDiscord>,
     twitch: Option<ConfigTomlTwitch>,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct ConfigTomlAnnouncements {
+    channel_id: Option<String>,
+    role_id: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct ConfigTomlBroadcasterAuth {
+    redirect_url: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct ConfigTomlModLog {
+    channel_id: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct ConfigTomlArchive {
+    enabled: Option<bool>,
+    directory: Option<String>,
+    retention_days: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct ConfigTomlCommands {
+    prefix: Option<String>,
+    ///Per channel overrides of `prefix`, keyed by channel name.
+    prefixes: Option<HashMap<String, String>>,
+    ///Per channel overrides of the level a command needs, keyed by channel then command name.
+    permissions: Option<HashMap<String, HashMap<String, String>>>,
+    ///Overrides of a command's cooldowns, keyed by command name.
+    cooldowns: Option<HashMap<String, CommandCooldown>>,
+    ///Whether a command on cooldown replies with the time left instead of staying silent.
+    cooldown_reply: Option<bool>,
+}
+
+///Cooldowns for a command in seconds, used on both Twitch and Discord.
+///
+///Either one left unset keeps the command's own default. Moderators are never held back by them.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct CommandCooldown {
+    ///Time before anyone may use the command again.
+    pub global: Option<u64>,
+    ///Time before the same user may use the command again.
+    pub user: Option<u64>,
+}
+
+///EventSub subscription types, such as `stream.online`, to create for each channel.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct EventSub {
+    ///Subscription types keyed by channel name, channels left out get the bot's defaults.
+    pub channels: Option<HashMap<String, Vec<String>>>,
+    ///Receive events over webhooks instead of the websocket when present.
+    pub webhook: Option<EventSubWebhook>,
+}
+
+///The webhook EventSub transport, served by the bot's Rocket server.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct EventSubWebhook {
+    ///Public https url Twitch posts to, routed to `/eventsub/callback` on the Rocket server.
+    pub callback_url: String,
+    ///Signs every message, between 10 and 100 characters.
+    pub secret: String,
+}
+
+///Twitch chat filters, each filter only runs if its table is present.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Filters {
+    ///Seconds a user is timed out for on each offence in a row, `0` only deletes the message.
+    ///Defaults to `[0, 10, 600]`, the last step repeats.
+    pub escalation: Option<Vec<u32>>,
+    ///Minutes without an offence before a user starts over, defaults to 60.
+    pub reset_minutes: Option<u64>,
+    pub links: Option<LinkFilter>,
+    pub caps: Option<RatioFilter>,
+    pub symbols: Option<RatioFilter>,
+    pub length: Option<LimitFilter>,
+    pub emotes: Option<LimitFilter>,
+}
+
+///Filters links to any domain not in `allowed_domains`, subdomains included.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct LinkFilter {
+    pub allowed_domains: Option<Vec<String>>,
+    ///The lowest permission level the filter doesn't apply to, defaults to subscriber.
+    pub exempt: Option<String>,
+}
+
+///Filters messages where too much of the text is capitals or symbols.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct RatioFilter {
+    ///Highest share of the message allowed, defaults to 70.
+    pub max_percent: Option<u8>,
+    ///Shorter messages are never filtered, defaults to 15 characters.
+    pub min_length: Option<usize>,
+    ///The lowest permission level the filter doesn't apply to, defaults to subscriber.
+    pub exempt: Option<String>,
+}
+
+///Filters messages that are too long or have too many emotes.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct LimitFilter {
+    pub max: Option<usize>,
+    ///The lowest permission level the filter doesn't apply to, defaults to subscriber.
+    pub exempt: Option<String>,
+}
+
+///Relays messages from a Discord channel into a Twitch channel's chat.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Bridge {
+    pub discord_channel_id: String,
+    pub twitch_channel: String,
+    ///Members with this role are never relayed.
+    pub opt_out_role: Option<String>,
+}
+
+///Mirrors a Twitch channel's chat into Discord through a webhook.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Mirror {
+    pub twitch_channel: String,
+    pub webhook_url: String,
+}
+
+///Chat and Discord alerts for follows, subscriptions, gifts, cheers and raids.
+///
+///Each template is an alert, leave one out to stay quiet about that event. Templates take
+///`$(user)`, `$(channel)`, `$(tier)`, `$(months)`, `$(streak)`, `$(amount)`, `$(recipient)` and
+///`$(message)`, which are empty when an event doesn't have them. `$(amount)` counts the bits,
+///gifts or raiders.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Alerts {
+    ///Discord channel every alert is also posted to.
+    pub discord_channel_id: Option<String>,
+    pub follow: Option<String>,
+    pub sub: Option<String>,
+    pub resub: Option<String>,
+    ///A single gifted subscription.
+    pub gift: Option<String>,
+    ///Several subscriptions gifted at once, `$(amount)` is how many.
+    pub gift_bomb: Option<String>,
+    pub cheer: Option<String>,
+    pub raid: Option<String>,
+    ///Seconds gifts from the same user are gathered into one alert, defaults to 5.
+    pub gift_window_seconds: Option<u64>,
+    ///The fewest bits a cheer needs for an alert, defaults to 1.
+    pub min_bits: Option<u64>,
+}
+
+///Where the results of polls and predictions are posted besides chat.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Polls {
+    ///Discord channel for results, usually the staff channel `/poll` and `/predict` are run from.
+    pub discord_channel_id: Option<String>,
+}
+
+///Maps the broadcaster's subscribers, VIPs and moderators to Discord roles for linked users.
+///
+///Any role left unset isn't managed, so it is never granted or removed by the sync.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct RoleSync {
+    ///Minutes between full syncs, defaults to 30 and can't go below 1.
+    pub interval_minutes: Option<u64>,
+    pub tier1_role_id: Option<String>,
+    pub tier2_role_id: Option<String>,
+    pub tier3_role_id: Option<String>,
+    pub vip_role_id: Option<String>,
+    pub moderator_role_id: Option<String>,
+}
+
+///Shoutouts sent with `!so`, and optionally to every raider.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Shoutouts {
+    ///Whether an incoming raid queues a shoutout for the raider, defaults to false.
+    pub raids: Option<bool>,
+    ///The fewest viewers a raid needs to be shouted out, defaults to 1.
+    pub min_raiders: Option<u32>,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 struct ConfigTomlTwitch {
     channels: Option<Vec<String>>,
@@ -32,7 +224,32 @@ struct ConfigTomlDatabase {
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Config {
+    pub alerts: Option<Alerts>,
+    pub announcement_channel_id: Option<String>,
+    pub announcement_role_id: Option<String>,
+    pub archive_enabled: bool,
+    pub archive_directory: String,
+    pub archive_retention_days: u64,
+    pub bridges: Vec<Bridge>,
+    ///Where Twitch sends broadcasters back to after they authorize the bot.
+    pub broadcaster_redirect_url: Option<String>,
+    pub command_prefix: String,
+    ///Per channel command prefixes, keyed by lowercase channel name.
+    pub command_prefixes: HashMap<String, String>,
+    ///Per channel command permission levels, keyed by lowercase channel then command name.
+    pub command_permissions: HashMap<String, HashMap<String, String>>,
+    ///Cooldown overrides, keyed by lowercase command name.
+    pub command_cooldowns: HashMap<String, CommandCooldown>,
+    pub command_cooldown_reply: bool,
     pub database_url: String,
+    pub eventsub: Option<EventSub>,
+    pub filters: Option<Filters>,
+    pub mirrors: Vec<Mirror>,
+    ///Discord channel every audit log entry is posted to.
+    pub mod_log_channel_id: Option<String>,
+    pub polls: Option<Polls>,
+    pub role_sync: Option<RoleSync>,
+    pub shoutouts: Option<Shoutouts>,
     pub discord_guildid: String,
     pub discord_token: String,
     pub twitch_channels: Vec<String>,
@@ -46,7 +263,27 @@ pub struct Config {
 impl Default for Config {
     fn default() -> Self {
         Self {
+            alerts: None,
+            announcement_channel_id: None,
+            announcement_role_id: None,
+            archive_enabled: false,
+            archive_directory: "./archive".to_string(),
+            archive_retention_days: 30,
+            bridges: Default::default(),
+            broadcaster_redirect_url: None,
+            command_prefix: "!".to_string(),
+            command_prefixes: Default::default(),
+            command_permissions: Default::default(),
+            command_cooldowns: Default::default(),
+            command_cooldown_reply: false,
             database_url: Default::default(),
+            eventsub: None,
+            filters: None,
+            mirrors: Default::default(),
+            mod_log_channel_id: None,
+            polls: None,
+            role_sync: None,
+            shoutouts: None,
             discord_guildid: "0".to_string(),
             discord_token: Default::default(),
             twitch_channels: Default::default(),
@@ -79,8 +316,65 @@ impl Config {
         let config_toml_result: Result<ConfigToml, toml::de::Error> = toml::from_str(&content);
         let config_toml: ConfigToml = config_toml_result.unwrap_or_else(|_| {
             eprintln!("Failed to create ConfigToml object out of config file.");
-            ConfigToml { database: None, discord: None, twitch: None }
+            ConfigToml::default()
         });
+        let (announcement_channel_id, announcement_role_id) =
+            match config_toml.announcements.clone() {
+                Some(announcements) => (announcements.channel_id, announcements.role_id),
+                None => (None, None),
+            };
+        let (archive_enabled, archive_directory, archive_retention_days) =
+            match config_toml.archive.clone() {
+                Some(archive) => (
+                    archive.enabled.unwrap_or(false),
+                    archive.directory.unwrap_or_else(|| "./archive".to_string()),
+                    archive.retention_days.unwrap_or(30),
+                ),
+                None => (false, "./archive".to_string(), 30),
+            };
+        let bridges: Vec<Bridge> = config_toml.bridge.clone().unwrap_or_default();
+        let (
+            command_prefix,
+            command_prefixes,
+            command_permissions,
+            command_cooldowns,
+            command_cooldown_reply,
+        ) = match config_toml.commands.clone() {
+            Some(commands) => (
+                commands.prefix.unwrap_or_else(|| "!".to_string()),
+                commands
+                    .prefixes
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(channel, prefix)| (channel.to_lowercase(), prefix))
+                    .collect(),
+                commands
+                    .permissions
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(channel, levels)| (channel.to_lowercase(), levels))
+                    .collect(),
+                commands
+                    .cooldowns
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(name, cooldown)| (name.to_lowercase(), cooldown))
+                    .collect(),
+                commands.cooldown_reply.unwrap_or(false),
+            ),
+            None => ("!".to_string(), HashMap::new(), HashMap::new(), HashMap::new(), false),
+        };
+        let alerts: Option<Alerts> = config_toml.alerts.clone();
+        let mirrors: Vec<Mirror> = config_toml.mirror.clone().unwrap_or_default();
+        let eventsub: Option<EventSub> = config_toml.eventsub.clone();
+        let filters: Option<Filters> = config_toml.filters.clone();
+        let broadcaster_redirect_url: Option<String> =
+            config_toml.broadcaster_auth.clone().and_then(|auth| auth.redirect_url);
+        let mod_log_channel_id: Option<String> =
+            config_toml.mod_log.clone().and_then(|mod_log| mod_log.channel_id);
+        let polls: Option<Polls> = config_toml.polls.clone();
+        let role_sync: Option<RoleSync> = config_toml.role_sync.clone();
+        let shoutouts: Option<Shoutouts> = config_toml.shoutouts.clone();
         let database_url: String = match config_toml.database.clone() {
             Some(db) => db.database_url.unwrap_or_else(|| {
                 eprintln!("Missing field `databaseurl` in table [database]");
@@ -184,7 +478,27 @@ impl Config {
             .map(|i| i.to_string())
             .collect();
         Config {
+            alerts,
+            announcement_channel_id,
+            announcement_role_id,
+            archive_enabled,
+            archive_directory,
+            archive_retention_days,
+            bridges,
+            broadcaster_redirect_url,
+            command_prefix,
+            command_prefixes,
+            command_permissions,
+            command_cooldowns,
+            command_cooldown_reply,
             database_url,
+            eventsub,
+            filters,
+            mirrors,
+            mod_log_channel_id,
+            polls,
+            role_sync,
+            shoutouts,
             discord_guildid,
             discord_token,
             twitch_channels,
@@ -202,6 +516,226 @@ mod test {
     use super::*;
     use crate::utils::json::{from_str, to_string};
 
+    #[test]
+    fn derives_config_toml_announcements() {
+        let all_some = ConfigTomlAnnouncements {
+            channel_id: Some("12345678910111213".to_string()),
+            role_id: Some("12345678910111213".to_string()),
+        };
+        let _all_none = ConfigTomlAnnouncements { channel_id: None, role_id: None };
+        let all_some_string = to_string(&all_some).unwrap(); // derive(Serialize)
+        let _: ConfigTomlAnnouncements = from_str(&all_some_string).unwrap(); // derive(Deserialize)
+        let _ = all_some.clone(); // derive(Clone)
+        let _ = format!("{:?}", all_some); // derive(Debug)
+    }
+
+    #[test]
+    fn derives_config_toml_broadcaster_auth() {
+        let all_some = ConfigTomlBroadcasterAuth {
+            redirect_url: Some("https://example.com/auth/twitch/broadcaster/callback".to_string()),
+        };
+        let _all_none = ConfigTomlBroadcasterAuth { redirect_url: None };
+        let all_some_string = to_string(&all_some).unwrap(); // derive(Serialize)
+        let _: ConfigTomlBroadcasterAuth = from_str(&all_some_string).unwrap(); // derive(Deserialize)
+        let _ = all_some.clone(); // derive(Clone)
+        let _ = format!("{:?}", all_some); // derive(Debug)
+    }
+
+    #[test]
+    fn derives_config_toml_mod_log() {
+        let all_some = ConfigTomlModLog { channel_id: Some("12345678910111213".to_string()) };
+        let _all_none = ConfigTomlModLog { channel_id: None };
+        let all_some_string = to_string(&all_some).unwrap(); // derive(Serialize)
+        let _: ConfigTomlModLog = from_str(&all_some_string).unwrap(); // derive(Deserialize)
+        let _ = all_some.clone(); // derive(Clone)
+        let _ = format!("{:?}", all_some); // derive(Debug)
+    }
+
+    #[test]
+    fn derives_config_toml_archive() {
+        let all_some = ConfigTomlArchive {
+            enabled: Some(true),
+            directory: Some("./archive".to_string()),
+            retention_days: Some(30),
+        };
+        let _all_none = ConfigTomlArchive { enabled: None, directory: None, retention_days: None };
+        let all_some_string = to_string(&all_some).unwrap(); // derive(Serialize)
+        let _: ConfigTomlArchive = from_str(&all_some_string).unwrap(); // derive(Deserialize)
+        let _ = all_some.clone(); // derive(Clone)
+        let _ = format!("{:?}", all_some); // derive(Debug)
+    }
+
+    #[test]
+    fn derives_config_toml_commands() {
+        let all_some = ConfigTomlCommands {
+            prefix: Some("!".to_string()),
+            prefixes: Some(HashMap::from([("twitch".to_string(), "?".to_string())])),
+            permissions: Some(HashMap::from([(
+                "twitch".to_string(),
+                HashMap::from([("ping".to_string(), "vip".to_string())]),
+            )])),
+            cooldowns: Some(HashMap::from([(
+                "ping".to_string(),
+                CommandCooldown { global: Some(10), user: Some(30) },
+            )])),
+            cooldown_reply: Some(true),
+        };
+        let _all_none = ConfigTomlCommands {
+            prefix: None,
+            prefixes: None,
+            permissions: None,
+            cooldowns: None,
+            cooldown_reply: None,
+        };
+        let all_some_string = to_string(&all_some).unwrap(); // derive(Serialize)
+        let _: ConfigTomlCommands = from_str(&all_some_string).unwrap(); // derive(Deserialize)
+        let _ = all_some.clone(); // derive(Clone)
+        let _ = format!("{:?}", all_some); // derive(Debug)
+    }
+
+    #[test]
+    fn derives_command_cooldown() {
+        let all_some = CommandCooldown { global: Some(10), user: Some(30) };
+        let all_some_string = to_string(&all_some).unwrap(); // derive(Serialize)
+        let _: CommandCooldown = from_str(&all_some_string).unwrap(); // derive(Deserialize)
+        let _ = CommandCooldown::default(); // derive(Default)
+        let _ = format!("{:?}", all_some.clone()); // derive(Clone, Debug)
+    }
+
+    #[test]
+    fn derives_alerts() {
+        let all_some = Alerts {
+            discord_channel_id: Some("12345678910111213".to_string()),
+            follow: Some("Welcome $(user)!".to_string()),
+            sub: Some("$(user) subscribed at tier $(tier)!".to_string()),
+            resub: Some("$(user) resubscribed for $(months) months!".to_string()),
+            gift: Some("$(user) gifted $(recipient) a sub!".to_string()),
+            gift_bomb: Some("$(user) gifted $(amount) subs!".to_string()),
+            cheer: Some("$(user) cheered $(amount) bits!".to_string()),
+            raid: Some("$(user) raided with $(amount) viewers!".to_string()),
+            gift_window_seconds: Some(5),
+            min_bits: Some(100),
+        };
+        let all_some_string = to_string(&all_some).unwrap(); // derive(Serialize)
+        let _: Alerts = from_str(&all_some_string).unwrap(); // derive(Deserialize)
+        let _ = Alerts::default(); // derive(Default)
+        let _ = format!("{:?}", all_some.clone()); // derive(Clone, Debug)
+    }
+
+    #[test]
+    fn derives_bridge() {
+        let all_some = Bridge {
+            discord_channel_id: "12345678910111213".to_string(),
+            twitch_channel: "twitch".to_string(),
+            opt_out_role: Some("12345678910111213".to_string()),
+        };
+        let _role_none = Bridge { opt_out_role: None, ..all_some.clone() };
+        let all_some_string = to_string(&all_some).unwrap(); // derive(Serialize)
+        let _: Bridge = from_str(&all_some_string).unwrap(); // derive(Deserialize)
+        let _ = Bridge::default(); // derive(Default)
+        let _ = format!("{:?}", all_some); // derive(Debug)
+    }
+
+    #[test]
+    fn derives_mirror() {
+        let all_some = Mirror {
+            twitch_channel: "twitch".to_string(),
+            webhook_url: "https://discord.com/api/webhooks/1/token".to_string(),
+        };
+        let all_some_string = to_string(&all_some).unwrap(); // derive(Serialize)
+        let _: Mirror = from_str(&all_some_string).unwrap(); // derive(Deserialize)
+        let _ = Mirror::default(); // derive(Default)
+        let _ = format!("{:?}", all_some.clone()); // derive(Clone, Debug)
+    }
+
+    #[test]
+    fn derives_eventsub() {
+        let all_some = EventSub {
+            channels: Some(HashMap::from([(
+                "TwitchRivals".to_string(),
+                vec!["stream.online".to_string()],
+            )])),
+            webhook: Some(EventSubWebhook {
+                callback_url: "https://example.com/eventsub/callback".to_string(),
+                secret: "0123456789".to_string(),
+            }),
+        };
+        let all_some_string = to_string(&all_some).unwrap(); // derive(Serialize)
+        let _: EventSub = from_str(&all_some_string).unwrap(); // derive(Deserialize)
+        let _ = EventSub::default(); // derive(Default)
+        let _ = format!("{:?}", all_some.clone()); // derive(Clone, Debug)
+    }
+
+    #[test]
+    fn derives_eventsub_webhook() {
+        let all_some = EventSubWebhook {
+            callback_url: "https://example.com/eventsub/callback".to_string(),
+            secret: "0123456789".to_string(),
+        };
+        let all_some_string = to_string(&all_some).unwrap(); // derive(Serialize)
+        let _: EventSubWebhook = from_str(&all_some_string).unwrap(); // derive(Deserialize)
+        let _ = EventSubWebhook::default(); // derive(Default)
+        let _ = format!("{:?}", all_some.clone()); // derive(Clone, Debug)
+    }
+
+    #[test]
+    fn derives_filters() {
+        let all_some = Filters {
+            escalation: Some(vec![0, 10, 600]),
+            reset_minutes: Some(60),
+            links: Some(LinkFilter {
+                allowed_domains: Some(vec!["twitch.tv".to_string()]),
+                exempt: Some("subscriber".to_string()),
+            }),
+            caps: Some(RatioFilter {
+                max_percent: Some(70),
+                min_length: Some(15),
+                exempt: Some("vip".to_string()),
+            }),
+            symbols: Some(RatioFilter::default()),
+            length: Some(LimitFilter { max: Some(400), exempt: Some("moderator".to_string()) }),
+            emotes: Some(LimitFilter::default()),
+        };
+        let all_some_string = to_string(&all_some).unwrap(); // derive(Serialize)
+        let _: Filters = from_str(&all_some_string).unwrap(); // derive(Deserialize)
+        let _ = Filters::default(); // derive(Default)
+        let _ = format!("{:?}", all_some.clone()); // derive(Clone, Debug)
+    }
+
+    #[test]
+    fn derives_polls() {
+        let all_some = Polls { discord_channel_id: Some("12345678910111213".to_string()) };
+        let all_some_string = to_string(&all_some).unwrap(); // derive(Serialize)
+        let _: Polls = from_str(&all_some_string).unwrap(); // derive(Deserialize)
+        let _ = Polls::default(); // derive(Default)
+        let _ = format!("{:?}", all_some.clone()); // derive(Clone, Debug)
+    }
+
+    #[test]
+    fn derives_role_sync() {
+        let all_some = RoleSync {
+            interval_minutes: Some(30),
+            tier1_role_id: Some("12345678910111213".to_string()),
+            tier2_role_id: Some("12345678910111213".to_string()),
+            tier3_role_id: Some("12345678910111213".to_string()),
+            vip_role_id: Some("12345678910111213".to_string()),
+            moderator_role_id: Some("12345678910111213".to_string()),
+        };
+        let all_some_string = to_string(&all_some).unwrap(); // derive(Serialize)
+        let _: RoleSync = from_str(&all_some_string).unwrap(); // derive(Deserialize)
+        let _ = RoleSync::default(); // derive(Default)
+        let _ = format!("{:?}", all_some.clone()); // derive(Clone, Debug)
+    }
+
+    #[test]
+    fn derives_shoutouts() {
+        let all_some = Shoutouts { raids: Some(true), min_raiders: Some(5) };
+        let all_some_string = to_string(&all_some).unwrap(); // derive(Serialize)
+        let _: Shoutouts = from_str(&all_some_string).unwrap(); // derive(Deserialize)
+        let _ = Shoutouts::default(); // derive(Default)
+        let _ = format!("{:?}", all_some.clone()); // derive(Clone, Debug)
+    }
+
     #[test]
     fn derives_config_toml_database() {
         let all_some = ConfigTomlDatabase { database_url: Some(Default::default()) };
@@ -341,6 +875,34 @@ mod test {
     #[test]
     fn derives_config_toml() {
         let all_some = ConfigToml {
+            alerts: Some(Alerts::default()),
+            announcements: Some(ConfigTomlAnnouncements {
+                channel_id: Some("".to_string()),
+                role_id: Some("".to_string()),
+            }),
+            archive: Some(ConfigTomlArchive {
+                enabled: Some(true),
+                directory: Some("".to_string()),
+                retention_days: Some(30),
+            }),
+            bridge: Some(vec![Bridge::default()]),
+            broadcaster_auth: Some(ConfigTomlBroadcasterAuth {
+                redirect_url: Some("".to_string()),
+            }),
+            commands: Some(ConfigTomlCommands {
+                prefix: Some("!".to_string()),
+                prefixes: Some(HashMap::new()),
+                permissions: Some(HashMap::new()),
+                cooldowns: Some(HashMap::new()),
+                cooldown_reply: Some(false),
+            }),
+            eventsub: Some(EventSub::default()),
+            filters: Some(Filters::default()),
+            mirror: Some(vec![Mirror::default()]),
+            mod_log: Some(ConfigTomlModLog { channel_id: Some("".to_string()) }),
+            polls: Some(Polls::default()),
+            role_sync: Some(RoleSync::default()),
+            shoutouts: Some(Shoutouts::default()),
             database: Some(ConfigTomlDatabase { database_url: Some("".to_string()) }),
             discord: Some(ConfigTomlDiscord {
                 guildid: Some("".to_string()),
@@ -361,6 +923,7 @@ mod test {
                 token: Some("".to_string()),
             }),
             twitch: None,
+            ..Default::default()
         };
         let _twitch_some = ConfigToml {
             database: None,
@@ -372,11 +935,13 @@ mod test {
                 bot_name: Some("".to_string()),
                 redirect_url: Some("".to_string()),
             }),
+            ..Default::default()
         };
         let _database_some = ConfigToml {
             database: Some(ConfigTomlDatabase { database_url: Some("".to_string()) }),
             discord: None,
             twitch: None,
+            ..Default::default()
         };
         let all_some_string = to_string(&all_some).unwrap(); // derive(Serialize)
         let _: ConfigTomlTwitch = from_str(&all_some_string).unwrap(); // derive(Deserialize)
@@ -0,0 +1,167 @@
+//!In memory command cooldowns shared by Twitch and Discord commands.
+//!
+//!Every command has a global cooldown, how long before anyone may use it again, and a per user
+//!cooldown, how long before the same user may. A use only starts the cooldowns if it gets through
+//!both, so a user spamming a command on cooldown doesn't keep pushing it back. Callers decide who
+//!bypasses cooldowns, and reply with the time left only if `cooldown_reply` is set in `[commands]`.
+
+//crate
+use crate::CONFIG;
+
+use lazy_static::lazy_static;
+
+//std
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+lazy_static! {
+    static ref COOLDOWNS: Mutex<Cooldowns> = Mutex::new(Cooldowns::default());
+}
+
+///How long a command waits between uses.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Cooldown {
+    pub global: Duration,
+    pub user: Duration,
+}
+
+impl Cooldown {
+    ///No cooldown at all.
+    pub const NONE: Cooldown = Cooldown::secs(0, 0);
+
+    ///A cooldown of `global` and `user` seconds.
+    pub const fn secs(global: u64, user: u64) -> Self {
+        Self { global: Duration::from_secs(global), user: Duration::from_secs(user) }
+    }
+
+    ///This cooldown with any overrides configured for the command called `name`.
+    pub fn configured(self, name: &str) -> Self {
+        match CONFIG.command_cooldowns.get(&name.to_lowercase()) {
+            Some(cooldown) => Self {
+                global: cooldown.global.map(Duration::from_secs).unwrap_or(self.global),
+                user: cooldown.user.map(Duration::from_secs).unwrap_or(self.user),
+            },
+            None => self,
+        }
+    }
+}
+
+///When each command was last used, globally and by each user, along with the cooldown it started.
+///Uses are forgotten once their cooldown is over.
+#[derive(Debug, Default)]
+pub struct Cooldowns {
+    global: HashMap<String, (Instant, Duration)>,
+    user: HashMap<(String, String), (Instant, Duration)>,
+}
+
+impl Cooldowns {
+    ///Records a use of `key` by `user` at `now`, or returns how long is left if it is cooling down.
+    pub fn check(
+        &mut self,
+        key: &str,
+        user: &str,
+        cooldown: Cooldown,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let over = |(last, length): &(Instant, Duration)| now.duration_since(*last) >= *length;
+        self.global.retain(|_, used| !over(used));
+        self.user.retain(|_, used| !over(used));
+        let remaining = |used: Option<&(Instant, Duration)>, length: Duration| {
+            used.map(|(last, _)| length.saturating_sub(now.duration_since(*last)))
+                .unwrap_or_default()
+        };
+        let user_key = (key.to_string(), user.to_string());
+        let left = remaining(self.global.get(key), cooldown.global)
+            .max(remaining(self.user.get(&user_key), cooldown.user));
+        if !left.is_zero() {
+            return Err(left);
+        }
+        self.global.insert(key.to_string(), (now, cooldown.global));
+        self.user.insert(user_key, (now, cooldown.user));
+        Ok(())
+    }
+}
+
+///Records a use of `key` by `user`, or returns how long is left if it is cooling down.
+///
+///`key` should include the platform and, for Twitch, the channel, e.g. `twitch:zoes17:ping`.
+pub fn check(key: &str, user: &str, cooldown: Cooldown) -> Result<(), Duration> {
+    let mut cooldowns = match COOLDOWNS.lock() {
+        Ok(cooldowns) => cooldowns,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    cooldowns.check(key, user, cooldown, Instant::now())
+}
+
+///Formats what's left of a cooldown for users, rounding up to the next second.
+pub fn format_remaining(remaining: Duration) -> String {
+    let secs = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+    match secs {
+        0..=59 => format!("{secs}s"),
+        _ if secs % 60 == 0 => format!("{}m", secs / 60),
+        _ => format!("{}m {}s", secs / 60, secs % 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn global_and_user_cooldowns() {
+        let mut cooldowns = Cooldowns::default();
+        let start = Instant::now();
+        let cooldown = Cooldown::secs(10, 30);
+        assert_eq!(cooldowns.check("ping", "a", cooldown, start), Ok(()));
+        assert_eq!(
+            cooldowns.check("ping", "b", cooldown, start + Duration::from_secs(4)),
+            Err(Duration::from_secs(6))
+        );
+        assert_eq!(cooldowns.check("ping", "b", cooldown, start + Duration::from_secs(10)), Ok(()));
+        assert_eq!(
+            cooldowns.check("ping", "a", cooldown, start + Duration::from_secs(25)),
+            Err(Duration::from_secs(5))
+        );
+        assert_eq!(cooldowns.check("ping", "a", cooldown, start + Duration::from_secs(30)), Ok(()));
+        // other commands have their own cooldowns
+        assert_eq!(cooldowns.check("link", "a", cooldown, start), Ok(()));
+    }
+
+    #[test]
+    fn blocked_uses_dont_extend() {
+        let mut cooldowns = Cooldowns::default();
+        let start = Instant::now();
+        let cooldown = Cooldown::secs(10, 0);
+        assert!(cooldowns.check("ping", "a", cooldown, start).is_ok());
+        assert!(cooldowns.check("ping", "a", cooldown, start + Duration::from_secs(9)).is_err());
+        assert!(cooldowns.check("ping", "a", cooldown, start + Duration::from_secs(10)).is_ok());
+        assert!(cooldowns.check("ping", "a", Cooldown::NONE, start).is_ok());
+    }
+
+    #[test]
+    fn forgets_finished_cooldowns() {
+        let mut cooldowns = Cooldowns::default();
+        let start = Instant::now();
+        let cooldown = Cooldown::secs(10, 30);
+        assert!(cooldowns.check("ping", "a", cooldown, start).is_ok());
+        assert!(cooldowns.check("link", "b", cooldown, start + Duration::from_secs(20)).is_ok());
+        assert_eq!((cooldowns.global.len(), cooldowns.user.len()), (1, 2));
+        assert!(cooldowns.check("link", "c", cooldown, start + Duration::from_secs(40)).is_ok());
+        assert_eq!((cooldowns.global.len(), cooldowns.user.len()), (1, 2));
+    }
+
+    #[test]
+    fn overrides_from_config() {
+        // config.toml.example sets ping to 5s globally and 30s per user
+        assert_eq!(Cooldown::NONE.configured("Ping"), Cooldown::secs(5, 30));
+        assert_eq!(Cooldown::secs(1, 2).configured("link"), Cooldown::secs(1, 2));
+    }
+
+    #[test]
+    fn formats_remaining() {
+        assert_eq!(format_remaining(Duration::from_millis(4200)), "5s");
+        assert_eq!(format_remaining(Duration::from_secs(120)), "2m");
+        assert_eq!(format_remaining(Duration::from_secs(95)), "1m 35s");
+    }
+}
@@ -16,6 +16,16 @@ fn establish_connection() -> eyre::Result<MysqlConnection> {
     MysqlConnection::establish(&database_url).map_err(|error| error_to_eyre(error))
 }
 
+/// Run database work on tokio's blocking pool so a slow database can't stall the async task,
+/// and with it chat, that needs the result
+pub async fn blocking<T, F>(work: F) -> eyre::Result<T>
+where
+    F: FnOnce() -> eyre::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(work).await.context("Database task panicked")?
+}
+
 /// Pull a [TwitchUser] from the database by its username
 pub fn find_twitch_user(un: String) -> eyre::Result<TwitchUser> {
     use self::schema::twitchuser::dsl::*;
@@ -31,20 +41,17 @@ pub fn find_twitch_user(un: String) -> eyre::Result<TwitchUser> {
         .ok_or(eyre::eyre!("Error selecting twitchuser"))
 }
 
-/// Pull a [TwitchUser] from the database by its Twitch user id
-pub fn find_twitch_user_by_id(uid: u32) -> eyre::Result<TwitchUser> {
+/// Pull a [TwitchUser] from the database by its Twitch user id, [None] if they were never stored
+pub fn find_twitch_user_by_id(uid: u32) -> eyre::Result<Option<TwitchUser>> {
     use self::schema::twitchuser::dsl::*;
 
     let connection = &mut establish_connection()?;
     twitchuser
         .filter(tid.eq(uid))
         .select(TwitchUser::as_select())
-        .limit(1)
-        .load(connection)
-        .context("Error loading twitchuser")?
-        .first().cloned()
-        // .map(|tu| tu.clone())
-        .ok_or(eyre::eyre!("Error finding twitch user by id"))
+        .first(connection)
+        .optional()
+        .context("Error loading twitchuser")
 }
 
 /// Pull [DiscordUser] from database by a Discord username
@@ -110,6 +117,386 @@ pub fn find_discord_user_by_twitch_id(tid: u32) -> eyre::Result<Vec<DiscordUser>
     Ok(result)
 }
 
+/// Pull every row of the users table, i.e. each linked Discord and Twitch account pair
+pub fn find_all_linked_users() -> eyre::Result<Vec<Users>> {
+    use self::schema::users::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    users.select(Users::as_select()).load(connection).context("Error selecting linked users")
+}
+
+/// Pull a [CustomCommand] from the database by the channel it belongs to and its name
+pub fn find_custom_command(chan: &str, command: &str) -> eyre::Result<Option<CustomCommand>> {
+    use self::schema::custom_commands::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    Ok(custom_commands
+        .filter(channel.eq(chan))
+        .filter(name.eq(command))
+        .select(CustomCommand::as_select())
+        .limit(1)
+        .load(connection)
+        .context("Error selecting custom command")?
+        .first()
+        .cloned())
+}
+
+/// Pull every [CustomCommand] of a channel, ordered by name
+pub fn find_custom_commands(chan: &str) -> eyre::Result<Vec<CustomCommand>> {
+    use self::schema::custom_commands::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    custom_commands
+        .filter(channel.eq(chan))
+        .order(name.asc())
+        .select(CustomCommand::as_select())
+        .load(connection)
+        .context("Error selecting custom commands")
+}
+
+/// Insert a new [CustomCommand]
+pub fn create_custom_command(command: &NewCustomCommand) -> eyre::Result<()> {
+    use self::schema::custom_commands::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    diesel::insert_into(custom_commands)
+        .values(command)
+        .execute(connection)
+        .context("Error inserting custom command")?;
+    Ok(())
+}
+
+/// Apply [CustomCommandChanges] to a channel's command, returns how many rows changed
+pub fn update_custom_command(
+    chan: &str,
+    command: &str,
+    changes: &CustomCommandChanges,
+) -> eyre::Result<usize> {
+    use self::schema::custom_commands::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    diesel::update(custom_commands.filter(channel.eq(chan)).filter(name.eq(command)))
+        .set(changes)
+        .execute(connection)
+        .context("Error updating custom command")
+}
+
+/// Delete a channel's command, returns how many rows were removed
+pub fn delete_custom_command(chan: &str, command: &str) -> eyre::Result<usize> {
+    use self::schema::custom_commands::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    diesel::delete(custom_commands.filter(channel.eq(chan)).filter(name.eq(command)))
+        .execute(connection)
+        .context("Error deleting custom command")
+}
+
+/// Count one more use of a [CustomCommand], returning the new total
+pub fn increment_custom_command_uses(command_id: u32) -> eyre::Result<u32> {
+    use self::schema::custom_commands::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    diesel::update(custom_commands.find(command_id))
+        .set(uses.eq(uses + 1))
+        .execute(connection)
+        .context("Error counting custom command use")?;
+    custom_commands
+        .find(command_id)
+        .select(uses)
+        .first(connection)
+        .context("Error selecting custom command uses")
+}
+
+/// Pull every [Timer] of a channel, ordered by name
+pub fn find_timers(chan: &str) -> eyre::Result<Vec<Timer>> {
+    use self::schema::timers::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    timers
+        .filter(channel.eq(chan))
+        .order(name.asc())
+        .select(Timer::as_select())
+        .load(connection)
+        .context("Error selecting timers")
+}
+
+/// Pull every [Timer] of every channel
+pub fn find_all_timers() -> eyre::Result<Vec<Timer>> {
+    use self::schema::timers::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    timers.select(Timer::as_select()).load(connection).context("Error selecting all timers")
+}
+
+/// Insert a new [Timer]
+pub fn create_timer(timer: &NewTimer) -> eyre::Result<()> {
+    use self::schema::timers::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    diesel::insert_into(timers)
+        .values(timer)
+        .execute(connection)
+        .context("Error inserting timer")?;
+    Ok(())
+}
+
+/// Delete a channel's timer, returns how many rows were removed
+pub fn delete_timer(chan: &str, timer: &str) -> eyre::Result<usize> {
+    use self::schema::timers::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    diesel::delete(timers.filter(channel.eq(chan)).filter(name.eq(timer)))
+        .execute(connection)
+        .context("Error deleting timer")
+}
+
+/// Insert a new [NewAuditEntry] into the `audit_log`
+pub fn create_audit_entry(entry: &NewAuditEntry) -> eyre::Result<()> {
+    use self::schema::audit_log::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    diesel::insert_into(audit_log)
+        .values(entry)
+        .execute(connection)
+        .context("Error inserting audit entry")?;
+    Ok(())
+}
+
+/// Pull a [Quote] from the database by its id
+pub fn find_quote(quote: u32) -> eyre::Result<Option<Quote>> {
+    use self::schema::quotes::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    quotes
+        .find(quote)
+        .select(Quote::as_select())
+        .first(connection)
+        .optional()
+        .context("Error selecting quote")
+}
+
+/// Pull a [Quote] at random, MySQL does the picking
+pub fn find_random_quote() -> eyre::Result<Option<Quote>> {
+    use self::schema::quotes::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    quotes
+        .order(diesel::dsl::sql::<diesel::sql_types::Double>("RAND()"))
+        .select(Quote::as_select())
+        .first(connection)
+        .optional()
+        .context("Error selecting a random quote")
+}
+
+/// Pull every [Quote] containing `term`, oldest first
+pub fn search_quotes(term: &str) -> eyre::Result<Vec<Quote>> {
+    use self::schema::quotes::dsl::*;
+
+    // `%` and `_` in the term are matched literally
+    let escaped = term.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    let pattern = format!("%{escaped}%");
+    let connection = &mut establish_connection()?;
+    quotes
+        .filter(text.like(pattern))
+        .order(id.asc())
+        .select(Quote::as_select())
+        .load(connection)
+        .context("Error searching quotes")
+}
+
+/// Insert a new [Quote], returning it as stored
+pub fn create_quote(quote: &NewQuote) -> eyre::Result<Quote> {
+    use self::schema::quotes::dsl::*;
+    use diesel::sql_types::{BigInt, Unsigned};
+
+    let connection = &mut establish_connection()?;
+    connection
+        .transaction(|connection| {
+            diesel::insert_into(quotes).values(quote).execute(connection)?;
+            // the id of this connection's insert, not whichever quote happens to be newest
+            let inserted: u64 =
+                diesel::select(diesel::dsl::sql::<Unsigned<BigInt>>("LAST_INSERT_ID()"))
+                    .get_result(connection)?;
+            quotes.find(inserted as u32).select(Quote::as_select()).first(connection)
+        })
+        .context("Error inserting quote")
+}
+
+/// Change the text of a [Quote], returns how many rows changed
+pub fn update_quote(quote: u32, new_text: &str) -> eyre::Result<usize> {
+    use self::schema::quotes::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    diesel::update(quotes.find(quote))
+        .set(text.eq(new_text))
+        .execute(connection)
+        .context("Error updating quote")
+}
+
+/// Delete a [Quote], returns how many rows were removed
+pub fn delete_quote(quote: u32) -> eyre::Result<usize> {
+    use self::schema::quotes::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    diesel::delete(quotes.find(quote)).execute(connection).context("Error deleting quote")
+}
+
+/// Pull the [BroadcasterToken] of a channel by its login
+pub fn find_broadcaster_token(name: &str) -> eyre::Result<Option<BroadcasterToken>> {
+    use self::schema::broadcaster_tokens::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    broadcaster_tokens
+        .filter(login.eq(name))
+        .select(BroadcasterToken::as_select())
+        .first(connection)
+        .optional()
+        .context("Error selecting broadcaster token")
+}
+
+/// Pull every [BroadcasterToken], by login
+pub fn find_broadcaster_tokens() -> eyre::Result<Vec<BroadcasterToken>> {
+    use self::schema::broadcaster_tokens::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    broadcaster_tokens
+        .order(login.asc())
+        .select(BroadcasterToken::as_select())
+        .load(connection)
+        .context("Error selecting broadcaster tokens")
+}
+
+/// Insert a [BroadcasterToken], replacing the broadcaster's previous one
+pub fn save_broadcaster_token(token: &NewBroadcasterToken) -> eyre::Result<()> {
+    use self::schema::broadcaster_tokens::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    diesel::replace_into(broadcaster_tokens)
+        .values(token)
+        .execute(connection)
+        .context("Error saving broadcaster token")?;
+    Ok(())
+}
+
+/// Pull the [RewardAction] of a channel's reward by its title
+pub fn find_reward_action(chan: &str, title: &str) -> eyre::Result<Option<RewardAction>> {
+    use self::schema::reward_actions::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    reward_actions
+        .filter(channel.eq(chan))
+        .filter(reward_title.eq(title))
+        .select(RewardAction::as_select())
+        .first(connection)
+        .optional()
+        .context("Error selecting reward action")
+}
+
+/// Pull every [RewardAction] of a channel, ordered by reward title
+pub fn find_reward_actions(chan: &str) -> eyre::Result<Vec<RewardAction>> {
+    use self::schema::reward_actions::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    reward_actions
+        .filter(channel.eq(chan))
+        .order(reward_title.asc())
+        .select(RewardAction::as_select())
+        .load(connection)
+        .context("Error selecting reward actions")
+}
+
+/// Insert a [RewardAction], replacing the reward's previous one
+pub fn save_reward_action(reward: &NewRewardAction) -> eyre::Result<()> {
+    use self::schema::reward_actions::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    diesel::replace_into(reward_actions)
+        .values(reward)
+        .execute(connection)
+        .context("Error saving reward action")?;
+    Ok(())
+}
+
+/// Delete the action of a channel's reward, returns how many rows were removed
+pub fn delete_reward_action(chan: &str, title: &str) -> eyre::Result<usize> {
+    use self::schema::reward_actions::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    diesel::delete(reward_actions.filter(channel.eq(chan)).filter(reward_title.eq(title)))
+        .execute(connection)
+        .context("Error deleting reward action")
+}
+
+/// Insert a [RewardRole], replacing the expiry of a role the user already has
+pub fn save_reward_role(role: &RewardRole) -> eyre::Result<()> {
+    use self::schema::reward_roles::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    diesel::replace_into(reward_roles)
+        .values(role)
+        .execute(connection)
+        .context("Error saving reward role")?;
+    Ok(())
+}
+
+/// Pull the [RewardRole] a Discord user was granted, if they still have it
+pub fn find_reward_role(user: u64, role: u64) -> eyre::Result<Option<RewardRole>> {
+    use self::schema::reward_roles::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    reward_roles
+        .find((user, role))
+        .select(RewardRole::as_select())
+        .first(connection)
+        .optional()
+        .context("Error selecting reward role")
+}
+
+/// Pull every [RewardRole] that hasn't been removed yet
+pub fn find_reward_roles() -> eyre::Result<Vec<RewardRole>> {
+    use self::schema::reward_roles::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    reward_roles
+        .select(RewardRole::as_select())
+        .load(connection)
+        .context("Error selecting reward roles")
+}
+
+/// Delete a [RewardRole] once it was removed, returns how many rows were removed
+pub fn delete_reward_role(user: u64, role: u64) -> eyre::Result<usize> {
+    use self::schema::reward_roles::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    diesel::delete(reward_roles.find((user, role)))
+        .execute(connection)
+        .context("Error deleting reward role")
+}
+
+/// Pull every [TwitchChannel] joined or parted at runtime, ordered by name
+pub fn find_twitch_channels() -> eyre::Result<Vec<TwitchChannel>> {
+    use self::schema::twitch_channels::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    twitch_channels
+        .order(name.asc())
+        .select(TwitchChannel::as_select())
+        .load(connection)
+        .context("Error selecting twitch channels")
+}
+
+/// Insert a [TwitchChannel], replacing the channel's previous join or part
+pub fn save_twitch_channel(channel: &NewTwitchChannel) -> eyre::Result<()> {
+    use self::schema::twitch_channels::dsl::*;
+
+    let connection = &mut establish_connection()?;
+    diesel::replace_into(twitch_channels)
+        .values(channel)
+        .execute(connection)
+        .context("Error saving twitch channel")?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -128,7 +515,8 @@ mod tests {
         let expected = TwitchUser { tid: 12345678_u32, username: String::from("testuser") };
         let user = 12345678_u32;
         let needle = find_twitch_user_by_id(user);
-        assert_eq!(needle.ok().unwrap(), expected);
+        assert_eq!(needle.ok().flatten().unwrap(), expected);
+        assert_eq!(find_twitch_user_by_id(1).unwrap(), None);
     }
 
     #[test]
@@ -154,4 +542,179 @@ mod tests {
         let needle = find_discord_user_by_twitch_id(user).ok().unwrap();
         assert_eq!(needle.first().unwrap(), &expected);
     }
+
+    #[test]
+    fn custom_command_lifecycle() {
+        let (chan, command) = ("testchannel", "testcommand");
+        let _ = delete_custom_command(chan, command);
+        let new = NewCustomCommand {
+            channel: chan.to_string(),
+            name: command.to_string(),
+            response: "Hello $(user)".to_string(),
+            level: "everyone".to_string(),
+            cooldown: 5,
+        };
+        create_custom_command(&new).unwrap();
+        let found = find_custom_command(chan, command).unwrap().unwrap();
+        assert_eq!(found.response, "Hello $(user)");
+        assert_eq!(increment_custom_command_uses(found.id).unwrap(), 1);
+        let changes =
+            CustomCommandChanges { response: Some("Bye".to_string()), ..Default::default() };
+        assert_eq!(update_custom_command(chan, command, &changes).unwrap(), 1);
+        assert_eq!(find_custom_commands(chan).unwrap().first().unwrap().response, "Bye");
+        assert_eq!(delete_custom_command(chan, command).unwrap(), 1);
+        assert!(find_custom_command(chan, command).unwrap().is_none());
+    }
+
+    #[test]
+    fn timer_lifecycle() {
+        let (chan, timer) = ("testchannel", "testtimer");
+        let _ = delete_timer(chan, timer);
+        let new = NewTimer {
+            channel: chan.to_string(),
+            name: timer.to_string(),
+            message: "Follow us on Discord".to_string(),
+            interval_minutes: 15,
+            min_lines: 5,
+            live_only: true,
+        };
+        create_timer(&new).unwrap();
+        let found = find_timers(chan).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].message, "Follow us on Discord");
+        assert!(find_all_timers().unwrap().iter().any(|t| t.id == found[0].id));
+        assert!(create_timer(&new).is_err());
+        assert_eq!(delete_timer(chan, timer).unwrap(), 1);
+        assert!(find_timers(chan).unwrap().is_empty());
+    }
+
+    #[test]
+    fn audit_entries() {
+        use super::schema::audit_log::dsl::*;
+
+        let entry = NewAuditEntry {
+            platform: "twitch".to_string(),
+            channel: "testchannel".to_string(),
+            moderator: "testuser".to_string(),
+            action: "timeout".to_string(),
+            target: "testbanuser".to_string(),
+            reason: Some("spam".to_string()),
+            duration_seconds: Some(600),
+        };
+        create_audit_entry(&entry).unwrap();
+        let latest: (String, Option<u32>) = audit_log
+            .filter(channel.eq("testchannel"))
+            .order(id.desc())
+            .select((action, duration_seconds))
+            .first(&mut establish_connection().unwrap())
+            .unwrap();
+        assert_eq!(latest, ("timeout".to_string(), Some(600)));
+    }
+
+    #[test]
+    fn quote_lifecycle() {
+        let new = NewQuote {
+            text: "I meant to do that 100% of the time".to_string(),
+            game: Some("Just Chatting".to_string()),
+            platform: "twitch".to_string(),
+            added_by: "testuser".to_string(),
+        };
+        let quote = create_quote(&new).unwrap();
+        assert_eq!(quote.text, new.text);
+        assert_eq!(find_quote(quote.id).unwrap(), Some(quote.clone()));
+        assert!(find_random_quote().unwrap().is_some());
+        assert!(search_quotes("100% of").unwrap().iter().any(|q| q.id == quote.id));
+        assert!(search_quotes("100%%").unwrap().iter().all(|q| q.id != quote.id));
+        assert_eq!(update_quote(quote.id, "Edited").unwrap(), 1);
+        assert_eq!(find_quote(quote.id).unwrap().unwrap().text, "Edited");
+        assert_eq!(delete_quote(quote.id).unwrap(), 1);
+        assert!(find_quote(quote.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn broadcaster_token_lifecycle() {
+        let mut token = NewBroadcasterToken {
+            broadcaster_id: "87654321".to_string(),
+            login: "testbroadcaster".to_string(),
+            access_token: "TestAccessToken".to_string(),
+            refresh_token: "TestRefreshToken".to_string(),
+            scopes: "channel:moderate moderation:read".to_string(),
+            expires_at: chrono::NaiveDate::from_ymd_opt(2030, 1, 1)
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                .unwrap(),
+        };
+        save_broadcaster_token(&token).unwrap();
+        token.access_token = "RefreshedAccessToken".to_string();
+        save_broadcaster_token(&token).unwrap();
+        let saved = find_broadcaster_token("testbroadcaster").unwrap().unwrap();
+        assert_eq!(saved.access_token, "RefreshedAccessToken");
+        assert!(find_broadcaster_tokens().unwrap().iter().any(|t| t.login == "testbroadcaster"));
+        assert!(find_broadcaster_token("nobody").unwrap().is_none());
+    }
+
+    #[test]
+    fn reward_action_lifecycle() {
+        let (chan, title) = ("testchannel", "Hydrate");
+        let _ = delete_reward_action(chan, title);
+        let mut reward = NewRewardAction {
+            channel: chan.to_string(),
+            reward_title: title.to_string(),
+            action: "say".to_string(),
+            argument: "$(user) says drink some water!".to_string(),
+            duration_minutes: None,
+            auto_settle: false,
+        };
+        save_reward_action(&reward).unwrap();
+        reward.auto_settle = true;
+        save_reward_action(&reward).unwrap();
+        let found = find_reward_actions(chan).unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(found[0].auto_settle);
+        assert_eq!(find_reward_action(chan, title).unwrap(), found.first().cloned());
+        assert_eq!(delete_reward_action(chan, title).unwrap(), 1);
+        assert!(find_reward_action(chan, title).unwrap().is_none());
+    }
+
+    #[test]
+    fn reward_role_lifecycle() {
+        use chrono::Timelike;
+
+        let (user, role) = (123456789012345_u64, 1234_u64);
+        let _ = delete_reward_role(user, role);
+        let expires_at = chrono::Utc::now().naive_utc().with_nanosecond(0).unwrap();
+        let mut granted = RewardRole { discord_id: user, role_id: role, expires_at };
+        save_reward_role(&granted).unwrap();
+        granted.expires_at += chrono::TimeDelta::minutes(30);
+        save_reward_role(&granted).unwrap();
+        assert_eq!(find_reward_role(user, role).unwrap(), Some(granted.clone()));
+        assert!(find_reward_roles().unwrap().contains(&granted));
+        assert_eq!(delete_reward_role(user, role).unwrap(), 1);
+        assert!(find_reward_role(user, role).unwrap().is_none());
+    }
+
+    #[test]
+    fn twitch_channel_lifecycle() {
+        let mut channel = NewTwitchChannel {
+            name: "testjoinchannel".to_string(),
+            joined: true,
+            updated_by: "testuser".to_string(),
+        };
+        save_twitch_channel(&channel).unwrap();
+        channel.joined = false;
+        save_twitch_channel(&channel).unwrap();
+        let saved: Vec<TwitchChannel> = find_twitch_channels()
+            .unwrap()
+            .into_iter()
+            .filter(|c| c.name == "testjoinchannel")
+            .collect();
+        assert_eq!(saved.len(), 1);
+        assert!(!saved[0].joined);
+    }
+
+    #[test]
+    fn select_all_linked_users() {
+        let needle = find_all_linked_users().unwrap();
+        let linked = needle.iter().find(|u| u.twitch_id == 12345678_u32).unwrap();
+        assert_eq!(linked.discord_id, 123456789012345_u64);
+    }
 }
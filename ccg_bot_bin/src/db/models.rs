@@ -25,3 +25,172 @@ pub struct TwitchUser {
     pub tid: u32,
     pub username: String,
 }
+
+#[derive(Clone, Debug, PartialEq, Queryable, Selectable)]
+#[diesel(table_name = crate::db::schema::custom_commands)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub struct CustomCommand {
+    pub id: u32,
+    pub channel: String,
+    pub name: String,
+    pub response: String,
+    pub level: String,
+    pub cooldown: u32,
+    pub uses: u32,
+}
+
+#[derive(Clone, Debug, Insertable)]
+#[diesel(table_name = crate::db::schema::custom_commands)]
+pub struct NewCustomCommand {
+    pub channel: String,
+    pub name: String,
+    pub response: String,
+    pub level: String,
+    pub cooldown: u32,
+}
+
+/// Fields of a [CustomCommand] to change, [None] leaves the field as it is
+#[derive(Clone, Debug, Default, AsChangeset)]
+#[diesel(table_name = crate::db::schema::custom_commands)]
+pub struct CustomCommandChanges {
+    pub response: Option<String>,
+    pub level: Option<String>,
+    pub cooldown: Option<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Queryable, Selectable)]
+#[diesel(table_name = crate::db::schema::timers)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub struct Timer {
+    pub id: u32,
+    pub channel: String,
+    pub name: String,
+    pub message: String,
+    pub interval_minutes: u32,
+    pub min_lines: u32,
+    pub live_only: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Insertable)]
+#[diesel(table_name = crate::db::schema::timers)]
+pub struct NewTimer {
+    pub channel: String,
+    pub name: String,
+    pub message: String,
+    pub interval_minutes: u32,
+    pub min_lines: u32,
+    pub live_only: bool,
+}
+
+/// A moderation action, `id` and `created_at` are left to the database
+#[derive(Clone, Debug, PartialEq, Insertable)]
+#[diesel(table_name = crate::db::schema::audit_log)]
+pub struct NewAuditEntry {
+    pub platform: String,
+    pub channel: String,
+    pub moderator: String,
+    pub action: String,
+    pub target: String,
+    pub reason: Option<String>,
+    pub duration_seconds: Option<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Queryable, Selectable)]
+#[diesel(table_name = crate::db::schema::quotes)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub struct Quote {
+    pub id: u32,
+    pub text: String,
+    pub game: Option<String>,
+    pub platform: String,
+    pub added_by: String,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Clone, Debug, PartialEq, Insertable)]
+#[diesel(table_name = crate::db::schema::quotes)]
+pub struct NewQuote {
+    pub text: String,
+    pub game: Option<String>,
+    pub platform: String,
+    pub added_by: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Queryable, Selectable)]
+#[diesel(table_name = crate::db::schema::broadcaster_tokens)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub struct BroadcasterToken {
+    pub broadcaster_id: String,
+    pub login: String,
+    pub access_token: String,
+    pub refresh_token: String,
+    ///Space separated, as Twitch lists them.
+    pub scopes: String,
+    pub expires_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Clone, Debug, PartialEq, Insertable)]
+#[diesel(table_name = crate::db::schema::broadcaster_tokens)]
+pub struct NewBroadcasterToken {
+    pub broadcaster_id: String,
+    pub login: String,
+    pub access_token: String,
+    pub refresh_token: String,
+    pub scopes: String,
+    pub expires_at: chrono::NaiveDateTime,
+}
+
+#[derive(Clone, Debug, PartialEq, Queryable, Selectable)]
+#[diesel(table_name = crate::db::schema::reward_actions)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub struct RewardAction {
+    pub id: u32,
+    pub channel: String,
+    pub reward_title: String,
+    ///One of `say`, `role`, `command` or `queue`.
+    pub action: String,
+    pub argument: String,
+    pub duration_minutes: Option<u32>,
+    pub auto_settle: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Insertable)]
+#[diesel(table_name = crate::db::schema::reward_actions)]
+pub struct NewRewardAction {
+    pub channel: String,
+    pub reward_title: String,
+    pub action: String,
+    pub argument: String,
+    pub duration_minutes: Option<u32>,
+    pub auto_settle: bool,
+}
+
+/// A Discord role granted by a channel point reward, removed once `expires_at` passes
+#[derive(Clone, Debug, PartialEq, Queryable, Selectable, Insertable)]
+#[diesel(table_name = crate::db::schema::reward_roles)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub struct RewardRole {
+    pub discord_id: u64,
+    pub role_id: u64,
+    pub expires_at: chrono::NaiveDateTime,
+}
+
+/// A channel joined or parted at runtime, parting a configured channel is kept as `joined: false`
+#[derive(Clone, Debug, PartialEq, Queryable, Selectable)]
+#[diesel(table_name = crate::db::schema::twitch_channels)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub struct TwitchChannel {
+    pub name: String,
+    pub joined: bool,
+    pub updated_by: String,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+#[derive(Clone, Debug, PartialEq, Insertable)]
+#[diesel(table_name = crate::db::schema::twitch_channels)]
+pub struct NewTwitchChannel {
+    pub name: String,
+    pub joined: bool,
+    pub updated_by: String,
+}
@@ -1,5 +1,55 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    audit_log (id) {
+        id -> Unsigned<Integer>,
+        #[max_length = 16]
+        platform -> Varchar,
+        #[max_length = 100]
+        channel -> Varchar,
+        #[max_length = 100]
+        moderator -> Varchar,
+        #[max_length = 16]
+        action -> Varchar,
+        #[max_length = 100]
+        target -> Varchar,
+        reason -> Nullable<Text>,
+        duration_seconds -> Nullable<Unsigned<Integer>>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    broadcaster_tokens (broadcaster_id) {
+        #[max_length = 20]
+        broadcaster_id -> Varchar,
+        #[max_length = 25]
+        login -> Varchar,
+        #[max_length = 255]
+        access_token -> Varchar,
+        #[max_length = 255]
+        refresh_token -> Varchar,
+        scopes -> Text,
+        expires_at -> Datetime,
+        updated_at -> Datetime,
+    }
+}
+
+diesel::table! {
+    custom_commands (id) {
+        id -> Unsigned<Integer>,
+        #[max_length = 25]
+        channel -> Varchar,
+        #[max_length = 25]
+        name -> Varchar,
+        response -> Text,
+        #[max_length = 16]
+        level -> Varchar,
+        cooldown -> Unsigned<Integer>,
+        uses -> Unsigned<Integer>,
+    }
+}
+
 diesel::table! {
     discorduser (did) {
         did -> Unsigned<Bigint>,
@@ -8,6 +58,68 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    quotes (id) {
+        id -> Unsigned<Integer>,
+        text -> Text,
+        #[max_length = 100]
+        game -> Nullable<Varchar>,
+        #[max_length = 16]
+        platform -> Varchar,
+        #[max_length = 100]
+        added_by -> Varchar,
+        created_at -> Datetime,
+    }
+}
+
+diesel::table! {
+    reward_actions (id) {
+        id -> Unsigned<Integer>,
+        #[max_length = 25]
+        channel -> Varchar,
+        #[max_length = 45]
+        reward_title -> Varchar,
+        #[max_length = 16]
+        action -> Varchar,
+        argument -> Text,
+        duration_minutes -> Nullable<Unsigned<Integer>>,
+        auto_settle -> Bool,
+    }
+}
+
+diesel::table! {
+    reward_roles (discord_id, role_id) {
+        discord_id -> Unsigned<Bigint>,
+        role_id -> Unsigned<Bigint>,
+        expires_at -> Datetime,
+    }
+}
+
+diesel::table! {
+    timers (id) {
+        id -> Unsigned<Integer>,
+        #[max_length = 25]
+        channel -> Varchar,
+        #[max_length = 25]
+        name -> Varchar,
+        message -> Text,
+        interval_minutes -> Unsigned<Integer>,
+        min_lines -> Unsigned<Integer>,
+        live_only -> Bool,
+    }
+}
+
+diesel::table! {
+    twitch_channels (name) {
+        #[max_length = 25]
+        name -> Varchar,
+        joined -> Bool,
+        #[max_length = 100]
+        updated_by -> Varchar,
+        updated_at -> Datetime,
+    }
+}
+
 diesel::table! {
     twitchuser (tid) {
         tid -> Unsigned<Integer>,
@@ -28,4 +140,16 @@ diesel::table! {
 diesel::joinable!(users -> discorduser (discord_id));
 diesel::joinable!(users -> twitchuser (twitch_id));
 
-diesel::allow_tables_to_appear_in_same_query!(discorduser, twitchuser, users,);
+diesel::allow_tables_to_appear_in_same_query!(
+    audit_log,
+    broadcaster_tokens,
+    custom_commands,
+    discorduser,
+    quotes,
+    reward_actions,
+    reward_roles,
+    timers,
+    twitch_channels,
+    twitchuser,
+    users,
+);
@@ -0,0 +1,237 @@
+//!Relays messages from configured Discord channels into Twitch chat.
+//!
+//!Each [`Bridge`] pairs a Discord channel with a Twitch channel. Messages are sent as
+//!`[Discord] user: message` through the running [`TwitchClient`](crate::twitch::TwitchClient),
+//!after Discord specific markup has been flattened to plain text.
+
+//crate
+use crate::config::Bridge;
+use crate::twitch::IRC_CLIENT;
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, warn, debug};
+
+//governor
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
+
+use lazy_static::lazy_static;
+
+//serenity
+use serenity::all::{ChannelId, Context, Message, RoleId, User};
+
+//std
+use std::num::NonZeroU32;
+use std::time::Duration;
+
+///Twitch drops anything longer than this.
+pub const TWITCH_MESSAGE_LIMIT: usize = 500;
+
+///Non-moderators may send 20 messages every 30 seconds, so one every 1.5 seconds keeps a bridge
+///inside that limit no matter how busy the Discord channel is.
+const RELAY_PERIOD: Duration = Duration::from_millis(1500);
+
+lazy_static! {
+    static ref RELAYS: Vec<Relay> = CONFIG.bridges.iter().filter_map(Relay::new).collect();
+}
+
+struct Relay {
+    channel_id: ChannelId,
+    twitch_channel: String,
+    opt_out_role: Option<RoleId>,
+    limiter: DefaultDirectRateLimiter,
+}
+
+impl Relay {
+    fn new(bridge: &Bridge) -> Option<Self> {
+        let channel_id = match bridge.discord_channel_id.parse::<u64>() {
+            Ok(id) => ChannelId::new(id),
+            Err(e) => {
+                error!("Ignoring bridge with invalid discord_channel_id: {e}");
+                return None;
+            },
+        };
+        let opt_out_role = match &bridge.opt_out_role {
+            Some(role) => match role.parse::<u64>() {
+                Ok(id) => Some(RoleId::new(id)),
+                Err(e) => {
+                    error!("Ignoring bridge with invalid opt_out_role: {e}");
+                    return None;
+                },
+            },
+            None => None,
+        };
+        let quota = Quota::with_period(RELAY_PERIOD)
+            .expect("relay period is non-zero")
+            .allow_burst(NonZeroU32::MIN);
+        Some(Self {
+            channel_id,
+            twitch_channel: bridge.twitch_channel.to_lowercase(),
+            opt_out_role,
+            limiter: RateLimiter::direct(quota),
+        })
+    }
+}
+
+///Forwards `msg` to Twitch if its channel is bridged.
+pub async fn relay(ctx: &Context, msg: &Message) {
+    if msg.author.bot || msg.content.trim().is_empty() {
+        return;
+    }
+    let Some(relay) = RELAYS.iter().find(|r| r.channel_id == msg.channel_id) else {
+        return;
+    };
+    if let (Some(role), Some(member)) = (relay.opt_out_role, msg.member.as_ref()) {
+        if member.roles.contains(&role) {
+            return;
+        }
+    }
+    let content = sanitise(
+        &msg.content,
+        &msg.mentions,
+        |id| ctx.cache.role(msg.guild_id?, id).map(|r| r.name.clone()),
+        |id| ctx.cache.channel(id).map(|c| c.name.clone()),
+    );
+    if content.is_empty() {
+        return;
+    }
+    let author = msg.member.as_ref().and_then(|m| m.nick.clone()).unwrap_or_else(|| {
+        msg.author.global_name.clone().unwrap_or_else(|| msg.author.name.clone())
+    });
+    if relay.limiter.check().is_err() {
+        debug!("bridge to #{} is rate limited, dropping message {}", relay.twitch_channel, msg.id);
+        return;
+    }
+    let Some(client) = IRC_CLIENT.get() else {
+        warn!("Twitch chat isn't connected yet, dropping bridged message {}", msg.id);
+        return;
+    };
+    if let Err(e) = client.say(relay.twitch_channel.clone(), format_relay(&author, &content)).await
+    {
+        error!("Unable to relay message {} to #{}: {e}", msg.id, relay.twitch_channel);
+    }
+}
+
+///Formats a relayed line, truncating it to fit in a single Twitch message.
+pub fn format_relay(author: &str, content: &str) -> String {
+    truncate(&format!("[Discord] {author}: {content}"), TWITCH_MESSAGE_LIMIT)
+}
+
+///Truncates `text` to at most `limit` characters, marking the cut with an ellipsis.
+pub fn truncate(text: &str, limit: usize) -> String {
+    if text.chars().count() <= limit {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().take(limit.saturating_sub(1)).collect();
+    out.push('…');
+    out
+}
+
+///Flattens Discord markup into plain text that is safe to post in Twitch chat.
+///
+///User, role and channel mentions are replaced with their names, custom emoji become `:name:`,
+///newlines are collapsed, and leading `@`s are dropped so Twitch users aren't pinged.
+pub fn sanitise<R, C>(content: &str, mentions: &[User], role_name: R, channel_name: C) -> String
+where
+    R: Fn(RoleId) -> Option<String>,
+    C: Fn(ChannelId) -> Option<String>,
+{
+    let mut out = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        match tail.find('>').and_then(|end| {
+            resolve(&tail[1..end], mentions, &role_name, &channel_name).map(|r| (end, r))
+        }) {
+            Some((end, replacement)) => {
+                out.push_str(&replacement);
+                rest = &tail[end + 1..];
+            },
+            None => {
+                out.push('<');
+                rest = &tail[1..];
+            },
+        }
+    }
+    out.push_str(rest);
+    out.split_whitespace()
+        .map(|word| word.trim_start_matches('@'))
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn resolve<R, C>(inner: &str, mentions: &[User], role_name: &R, channel_name: &C) -> Option<String>
+where
+    R: Fn(RoleId) -> Option<String>,
+    C: Fn(ChannelId) -> Option<String>,
+{
+    if let Some(id) = inner.strip_prefix("@&") {
+        let id = RoleId::new(id.parse().ok().filter(|i| *i != 0)?);
+        return Some(role_name(id).unwrap_or_else(|| "role".to_string()));
+    }
+    if let Some(id) = inner.strip_prefix('@') {
+        let id: u64 = id.trim_start_matches('!').parse().ok()?;
+        let name = mentions
+            .iter()
+            .find(|u| u.id.get() == id)
+            .map(|u| u.global_name.clone().unwrap_or_else(|| u.name.clone()));
+        return Some(name.unwrap_or_else(|| "someone".to_string()));
+    }
+    if let Some(id) = inner.strip_prefix('#') {
+        let id = ChannelId::new(id.parse().ok().filter(|i| *i != 0)?);
+        return Some(format!("#{}", channel_name(id).unwrap_or_else(|| "channel".to_string())));
+    }
+    // custom emoji look like `<:name:id>` or `<a:name:id>` when animated
+    let emoji = inner.strip_prefix('a').unwrap_or(inner).strip_prefix(':')?;
+    let (name, id) = emoji.split_once(':')?;
+    id.parse::<u64>().ok()?;
+    Some(format!(":{name}:"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_role(_: RoleId) -> Option<String> {
+        None
+    }
+
+    fn no_channel(_: ChannelId) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn sanitise_emoji() {
+        let out = sanitise("gg <:pog:938514423155400804> <a:dance:1234>", &[], no_role, no_channel);
+        assert_eq!(out, "gg :pog: :dance:");
+    }
+
+    #[test]
+    fn sanitise_mentions() {
+        let out = sanitise(
+            "hi <@1234> and <@&5678> see <#91011>",
+            &[],
+            |_| Some("Mods".to_string()),
+            |_| Some("general".to_string()),
+        );
+        assert_eq!(out, "hi someone and Mods see #general");
+    }
+
+    #[test]
+    fn sanitise_pings_and_newlines() {
+        let out = sanitise("@everyone look\nat @testuser <3", &[], no_role, no_channel);
+        assert_eq!(out, "everyone look at testuser <3");
+    }
+
+    #[test]
+    fn truncates_long_messages() {
+        let long = "a".repeat(600);
+        let out = format_relay("TestUser", &long);
+        assert_eq!(out.chars().count(), TWITCH_MESSAGE_LIMIT);
+        assert!(out.starts_with("[Discord] TestUser: "));
+        assert!(out.ends_with('…'));
+        assert_eq!(format_relay("TestUser", "hi"), "[Discord] TestUser: hi");
+    }
+}
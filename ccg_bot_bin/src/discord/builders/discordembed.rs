@@ -40,6 +40,12 @@ impl DiscordEmbed {
         Self(self.0.color(color))
     }
 
+    ///Sets the description of the embed.
+    #[inline]
+    pub fn description(self, description: impl Into<String>) -> Self {
+        Self(self.0.description(description))
+    }
+
     ///Takes a name and value that impl ToString and a boolean as to whether to inline this field in the Embed.
     #[inline]
     pub fn field(self, name: impl Into<String>, value: impl Into<String>, inline: bool) -> Self {
@@ -58,9 +64,21 @@ impl DiscordEmbed {
         }
     }
 
-    #[allow(unused)]
-    #[doc(hidden)]
-    fn url_object(self, url: impl Into<String>) -> Self {
+    /// Set the footer of the embed.
+    #[inline]
+    pub fn footer(self, footer: serenity::all::CreateEmbedFooter) -> Self {
+        Self(self.0.footer(footer))
+    }
+
+    /// Set the image of the embed. This only supports HTTP(S).
+    #[inline]
+    pub fn image(self, url: impl Into<String>) -> Self {
+        Self(self.0.image(url))
+    }
+
+    /// Set the url the title of the embed links to.
+    #[inline]
+    pub fn url_object(self, url: impl Into<String>) -> Self {
         Self(self.0.url(url.into()))
     }
 
@@ -70,6 +88,12 @@ impl DiscordEmbed {
         Self(self.0.thumbnail(url))
     }
 
+    /// Set the timestamp shown at the bottom of the embed.
+    #[inline]
+    pub fn timestamp<T: Into<serenity::all::Timestamp>>(self, timestamp: T) -> Self {
+        Self(self.0.timestamp(timestamp))
+    }
+
     /// Set the title of the embed.
     #[inline]
     pub fn title(self, title: impl Into<String>) -> Self {
@@ -102,8 +126,13 @@ mod tests {
             colorful_embed_auth_replaced_fields.url_object("http:://localhost/url_object");
         let colorful_embed_auth_with_field_url_and_thumbnail =
             colorful_embed_auth_with_field_and_url.thumbnail("test_thumb.png");
+        let described_embed = colorful_embed_auth_with_field_url_and_thumbnail
+            .description("Test description")
+            .footer(serenity::all::CreateEmbedFooter::new("Test footer"))
+            .image("test_image.png")
+            .timestamp(serenity::all::Timestamp::now());
         let mut titled_colorful_embed_auth_with_field_url_and_thumbnail =
-            colorful_embed_auth_with_field_url_and_thumbnail.title("Test Title");
+            described_embed.title("Test Title");
         dbg!(&titled_colorful_embed_auth_with_field_url_and_thumbnail);
         let _built = titled_colorful_embed_auth_with_field_url_and_thumbnail.build();
     }
@@ -0,0 +1,82 @@
+//!`/broadcasters` shows which Twitch channels' broadcasters authorized the bot, see
+//![`broadcasters`](crate::twitch::broadcasters). Only administrators can see it.
+
+//crate
+use crate::cooldown::Cooldown;
+use crate::discord::commands::reply_embed;
+use crate::discord::is_admin;
+use crate::twitch::{broadcasters, channels};
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::debug;
+use crate::utils::commandinteraction::CommandInteraction;
+
+//serenity
+use serenity::all::{Context, Permissions};
+use serenity::builder::{CreateCommand, CreateEmbed};
+
+///Default cooldowns, which `[commands.cooldowns]` may override.
+pub const COOLDOWN: Cooldown = Cooldown::NONE;
+
+///One line per channel, the bot's own channel never needs authorizing.
+pub fn summary(channels: &[String], authorized: &[String], bot_name: &str) -> String {
+    channels
+        .iter()
+        .map(|channel| {
+            let status = if channel.eq_ignore_ascii_case(bot_name) {
+                "the bot's channel"
+            } else if authorized.iter().any(|a| a.eq_ignore_ascii_case(channel)) {
+                "authorized"
+            } else {
+                "not authorized"
+            };
+            format!("**{channel}**: {status}")
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+///Called when the command is run in a guild.
+pub async fn run(options: &CommandInteraction, context: &Context) -> CreateEmbed {
+    debug!("{:?}", options.data.options);
+    let description = match is_admin(options) {
+        false => "Only administrators can see this".to_string(),
+        true => {
+            let authorized = broadcasters::authorized().await;
+            let mut description =
+                summary(&channels::current().await, &authorized, &CONFIG.twitch_bot_name);
+            match broadcasters::authorize_url() {
+                Some(url) => {
+                    description.push_str(&format!("\n\nBroadcasters can authorize at {url}"))
+                },
+                None => description
+                    .push_str("\n\nSet `[broadcaster_auth]` to let broadcasters authorize"),
+            }
+            description
+        },
+    };
+    reply_embed(context, "Twitch broadcasters", description)
+}
+
+///Register the command to be used in the guild.
+pub fn register() -> CreateCommand {
+    CreateCommand::new("broadcasters")
+        .description("Show which Twitch channels authorized the bot")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarises_channels() {
+        let channels = ["TestUser".to_string(), "Twitch".to_string(), "TwitchRivals".to_string()];
+        let authorized = ["twitch".to_string()];
+        assert_eq!(
+            summary(&channels, &authorized, "testuser"),
+            "**TestUser**: the bot's channel\n**Twitch**: authorized\n**TwitchRivals**: not authorized"
+        );
+    }
+}
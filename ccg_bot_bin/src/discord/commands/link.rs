@@ -1,6 +1,7 @@
 //!Link Discord and Twitch accounts from a discord command interaction
 
 //crate imports
+use crate::cooldown::Cooldown;
 use crate::discord::builders::discordembed::*;
 //skip reordering to allow easy reference to verbosity(from least to most)
 #[rustfmt::skip]
@@ -18,6 +19,9 @@ use serenity::prelude::Context;
 //std imports
 // use std::sync::Arc;
 
+///Default cooldowns, which `[commands.cooldowns]` may override.
+pub const COOLDOWN: Cooldown = Cooldown::secs(0, 60);
+
 ///Called when the command is run in a guild.
 pub async fn run(options: &CommandInteraction, context: &Context) -> CreateEmbed {
     debug!("{:?}", options.clone());
@@ -1,3 +1,32 @@
+pub mod broadcasters;
 pub mod id;
 pub mod link;
 pub mod ping;
+pub mod poll;
+pub mod predict;
+pub mod quote;
+pub mod rewards;
+pub mod stream;
+pub mod twitch;
+
+//crate
+use crate::discord::builders::discordembed::*;
+
+//serenity
+use serenity::all::{Color, Context};
+use serenity::builder::{CreateEmbed, CreateEmbedAuthor};
+
+///The embed commands reply with, `description` under `title` and signed by the bot.
+pub(crate) fn reply_embed(
+    context: &Context,
+    title: impl Into<String>,
+    description: impl Into<String>,
+) -> CreateEmbed {
+    let current_user = context.cache.current_user().clone();
+    DiscordEmbed::new()
+        .description(description)
+        .color(Color::new(0x500060_u32))
+        .title(title)
+        .author(CreateEmbedAuthor::new(current_user.name.to_string()).url(current_user.face()))
+        .build()
+}
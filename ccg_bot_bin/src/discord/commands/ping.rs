@@ -1,6 +1,7 @@
 //!Returns an embed with the message, "Program" in the Greetings field.
 
 //crate
+use crate::cooldown::Cooldown;
 use crate::discord::builders::discordembed::*;
 //skip reordering to allow easy reference to verbosity(from least to most)
 #[rustfmt::skip]
@@ -12,6 +13,9 @@ use crate::utils::commandinteraction::CommandInteraction;
 use serenity::all::{Color, Context};
 use serenity::builder::{CreateCommand, CreateEmbed, CreateEmbedAuthor};
 
+///Default cooldowns, which `[commands.cooldowns]` may override.
+pub const COOLDOWN: Cooldown = Cooldown::secs(5, 30);
+
 ///Called when the command is run in a guild.
 pub async fn run(_options: &CommandInteraction, context: &Context) -> CreateEmbed {
     let current_user = context.cache.current_user().clone();
@@ -0,0 +1,72 @@
+//!Starts or ends a poll in the bot's Twitch channel, see [`polls`](crate::twitch::polls).
+
+//crate
+use crate::cooldown::Cooldown;
+use crate::discord::commands::reply_embed;
+use crate::discord::is_moderator;
+use crate::twitch::{bot_channel, polls};
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, debug};
+use crate::utils::commandinteraction::CommandInteraction;
+
+//serenity
+use serenity::all::{CommandOptionType, Context};
+use serenity::builder::{CreateCommand, CreateCommandOption, CreateEmbed};
+
+///Default cooldowns, which `[commands.cooldowns]` may override.
+pub const COOLDOWN: Cooldown = Cooldown::NONE;
+
+///Called when the command is run in a guild.
+pub async fn run(options: &CommandInteraction, context: &Context) -> CreateEmbed {
+    debug!("{:?}", options.data.options);
+    let option = |name: &str| {
+        options.data.options.iter().find(|o| o.name == name).and_then(|o| o.value.as_str())
+    };
+    let reply = if !is_moderator(options.member.as_deref()) {
+        "Only moderators can run polls".to_string()
+    } else if option("action") == Some("end") {
+        polls::end_poll().await.unwrap_or_else(|e| {
+            error!("/poll end failed: {e:?}");
+            "Twitch didn't accept that, check the bot's logs".to_string()
+        })
+    } else {
+        let question = option("question").unwrap_or_default();
+        let choices = option("choices").unwrap_or_default();
+        match polls::build(question, choices, option("duration"), polls::POLL) {
+            Ok(spec) => polls::start_poll(&spec).await.unwrap_or_else(|e| {
+                error!("/poll start failed: {e:?}");
+                "Twitch didn't accept that, check the bot's logs".to_string()
+            }),
+            Err(problem) => problem,
+        }
+    };
+    reply_embed(context, format!("Poll in #{}", bot_channel()), reply)
+}
+
+///Register the command to be used in the guild.
+pub fn register() -> CreateCommand {
+    CreateCommand::new("poll")
+        .description("Start or end a poll on Twitch")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "action", "What to do")
+                .add_string_choice("start", "start")
+                .add_string_choice("end", "end")
+                .required(true),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "question",
+            "The question to ask",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "choices",
+            "2 to 5 choices separated by |",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "duration",
+            "How long voting lasts, e.g. 90s or 5m",
+        ))
+}
@@ -0,0 +1,82 @@
+//!Runs a prediction in the bot's Twitch channel, see [`polls`](crate::twitch::polls).
+
+//crate
+use crate::cooldown::Cooldown;
+use crate::discord::commands::reply_embed;
+use crate::discord::is_moderator;
+use crate::twitch::{bot_channel, polls};
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, debug};
+use crate::utils::commandinteraction::CommandInteraction;
+
+//serenity
+use serenity::all::{CommandOptionType, Context};
+use serenity::builder::{CreateCommand, CreateCommandOption, CreateEmbed};
+
+///Default cooldowns, which `[commands.cooldowns]` may override.
+pub const COOLDOWN: Cooldown = Cooldown::NONE;
+
+///Called when the command is run in a guild.
+pub async fn run(options: &CommandInteraction, context: &Context) -> CreateEmbed {
+    debug!("{:?}", options.data.options);
+    let option = |name: &str| {
+        options.data.options.iter().find(|o| o.name == name).and_then(|o| o.value.as_str())
+    };
+    let action = option("action").unwrap_or_default();
+    let result = match (action, option("question"), option("outcomes"), option("winner")) {
+        _ if !is_moderator(options.member.as_deref()) => {
+            Ok("Only moderators can run predictions".to_string())
+        },
+        ("start", Some(question), Some(outcomes), _) => {
+            match polls::build(question, outcomes, option("window"), polls::PREDICTION) {
+                Ok(spec) => polls::start_prediction(&spec).await,
+                Err(problem) => Ok(problem),
+            }
+        },
+        ("start", ..) => Ok("A prediction needs a question and outcomes".to_string()),
+        ("lock", ..) => polls::lock_prediction().await,
+        ("resolve", _, _, Some(winner)) => polls::resolve_prediction(winner).await,
+        ("resolve", ..) => Ok("Pick the winning outcome by number or title".to_string()),
+        _ => polls::cancel_prediction().await,
+    };
+    let reply = result.unwrap_or_else(|e| {
+        error!("/predict {action} failed: {e:?}");
+        "Twitch didn't accept that, check the bot's logs".to_string()
+    });
+    reply_embed(context, format!("Prediction in #{}", bot_channel()), reply)
+}
+
+///Register the command to be used in the guild.
+pub fn register() -> CreateCommand {
+    CreateCommand::new("predict")
+        .description("Start, lock, resolve or cancel a prediction on Twitch")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "action", "What to do")
+                .add_string_choice("start", "start")
+                .add_string_choice("lock", "lock")
+                .add_string_choice("resolve", "resolve")
+                .add_string_choice("cancel", "cancel")
+                .required(true),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "question",
+            "The question to predict",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "outcomes",
+            "2 to 10 outcomes separated by |",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "window",
+            "How long predictions are open, e.g. 2m",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "winner",
+            "The winning outcome's number or title",
+        ))
+}
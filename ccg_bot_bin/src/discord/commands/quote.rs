@@ -0,0 +1,119 @@
+//!Shows, adds, searches, edits and deletes quotes in the shared [quote database](crate::quotes).
+
+//crate
+use crate::audit;
+use crate::cooldown::Cooldown;
+use crate::db::models::NewAuditEntry;
+use crate::discord::commands::reply_embed;
+use crate::discord::is_moderator;
+use crate::quotes;
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{warn, debug};
+use crate::utils::commandinteraction::CommandInteraction;
+
+//serenity
+use serenity::all::{CommandOptionType, Context};
+use serenity::builder::{CreateCommand, CreateCommandOption, CreateEmbed};
+
+///Default cooldowns, which `[commands.cooldowns]` may override.
+pub const COOLDOWN: Cooldown = Cooldown::secs(5, 30);
+
+///Called when the command is run in a guild.
+pub async fn run(options: &CommandInteraction, context: &Context) -> CreateEmbed {
+    debug!("{:?}", options.data.options);
+    let option = |name: &str| options.data.options.iter().find(|o| o.name == name);
+    let action = option("action").and_then(|o| o.value.as_str()).unwrap_or("random");
+    let id = option("id").and_then(|o| o.value.as_i64()).and_then(|i| u32::try_from(i).ok());
+    let text = option("text").and_then(|o| o.value.as_str()).map(str::trim);
+    let moderator = is_moderator(options.member.as_deref());
+    let reply = match respond(action, id, text, moderator, &options.user.name).await {
+        Ok(reply) => reply,
+        Err(e) => {
+            warn!("/quote {action} failed: {e}");
+            "Unable to reach the quote database".to_string()
+        },
+    };
+    reply_embed(context, "Quotes", reply)
+}
+
+async fn respond(
+    action: &str,
+    id: Option<u32>,
+    text: Option<&str>,
+    moderator: bool,
+    user: &str,
+) -> eyre::Result<String> {
+    let reply = match (action, id, text) {
+        ("random", None, _) => match quotes::random().await? {
+            Some(quote) => quotes::format(&quote),
+            None => "No quotes yet, add one with /quote add".to_string(),
+        },
+        ("random" | "show", Some(id), _) => match quotes::find(id).await? {
+            Some(quote) => quotes::format(&quote),
+            None => format!("There is no quote #{id}"),
+        },
+        ("add", _, Some(text)) if !text.is_empty() => {
+            let quote = quotes::add(text, None, "discord", user).await?;
+            format!("Added quote #{}", quote.id)
+        },
+        ("search", _, Some(term)) if !term.is_empty() => {
+            quotes::format_search(term, &quotes::search(term).await?)
+        },
+        ("edit" | "delete", ..) if !moderator => "Only moderators can change quotes".to_string(),
+        ("edit", Some(id), Some(text)) if !text.is_empty() => match quotes::edit(id, text).await? {
+            0 => format!("There is no quote #{id}"),
+            _ => {
+                record(user, "quote edit", id);
+                format!("Updated quote #{id}")
+            },
+        },
+        ("delete", Some(id), _) => match quotes::delete(id).await? {
+            0 => format!("There is no quote #{id}"),
+            _ => {
+                record(user, "quote delete", id);
+                format!("Deleted quote #{id}")
+            },
+        },
+        ("show" | "edit" | "delete", None, _) => format!("/quote {action} needs an id"),
+        _ => format!("/quote {action} needs some text"),
+    };
+    Ok(reply)
+}
+
+fn record(user: &str, action: &str, id: u32) {
+    audit::record(NewAuditEntry {
+        platform: "discord".to_string(),
+        channel: CONFIG.discord_guildid.clone(),
+        moderator: user.to_string(),
+        action: action.to_string(),
+        target: format!("#{id}"),
+        reason: None,
+        duration_seconds: None,
+    });
+}
+
+///Register the command to be used in the guild.
+pub fn register() -> CreateCommand {
+    CreateCommand::new("quote")
+        .description("Show, add or search quotes")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::String, "action", "What to do")
+                .add_string_choice("random", "random")
+                .add_string_choice("show", "show")
+                .add_string_choice("add", "add")
+                .add_string_choice("search", "search")
+                .add_string_choice("edit", "edit")
+                .add_string_choice("delete", "delete"),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::Integer, "id", "Quote number")
+                .min_int_value(1),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "text",
+            "Quote text or search term",
+        ))
+}
@@ -0,0 +1,225 @@
+//!`/rewards` maps channel point rewards to the actions run when they're redeemed and shows each
+//!channel's queue, see [`rewards`](crate::twitch::rewards). Only administrators can use it.
+
+//crate
+use crate::cooldown::Cooldown;
+use crate::db::{self, models::NewRewardAction, models::RewardAction};
+use crate::discord::commands::reply_embed;
+use crate::discord::is_admin;
+use crate::twitch::{bot_channel, rewards};
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, debug};
+use crate::utils::commandinteraction::CommandInteraction;
+
+//serenity
+use serenity::all::{
+    CommandDataOption, CommandDataOptionValue, CommandOptionType, Context, Permissions,
+};
+use serenity::builder::{CreateCommand, CreateCommandOption, CreateEmbed};
+
+///Default cooldowns, which `[commands.cooldowns]` may override.
+pub const COOLDOWN: Cooldown = Cooldown::NONE;
+
+///One line describing what redeeming `reward` does.
+pub fn describe(reward: &RewardAction) -> String {
+    let what = match reward.action.as_str() {
+        "say" => format!("says `{}`", reward.argument),
+        "role" => format!(
+            "grants <@&{}> for {} minute(s)",
+            reward.argument.trim_start_matches("<@&").trim_end_matches('>'),
+            reward.duration_minutes.unwrap_or_default()
+        ),
+        "command" => format!("runs `{}`", reward.argument),
+        "queue" => "joins the queue".to_string(),
+        other => format!("has an unknown action `{other}`"),
+    };
+    let settle = if reward.auto_settle { ", then settles it" } else { "" };
+    format!("**{}** {what}{settle}", reward.reward_title)
+}
+
+async fn respond(subcommand: &str, values: &[CommandDataOption]) -> eyre::Result<String> {
+    let value = |name: &str| values.iter().find(|v| v.name == name).map(|v| &v.value);
+    let text = |name: &str| value(name).and_then(|v| v.as_str()).map(str::trim).unwrap_or("");
+    let channel = match text("channel") {
+        "" => bot_channel(),
+        channel => channel.trim_start_matches('#').to_lowercase(),
+    };
+    let title = text("title");
+    let reply = match subcommand {
+        "list" => {
+            let chan = channel.clone();
+            let rewards = db::blocking(move || db::find_reward_actions(&chan)).await?;
+            match rewards.is_empty() {
+                true => format!("#{channel} has no reward actions, add one with /rewards set"),
+                false => rewards.iter().map(describe).collect::<Vec<String>>().join("\n"),
+            }
+        },
+        "set" => {
+            let minutes =
+                value("minutes").and_then(|v| v.as_i64()).and_then(|m| u32::try_from(m).ok());
+            let action = match rewards::Action::new(text("action"), text("value"), minutes) {
+                Ok(action) => action,
+                Err(e) => return Ok(format!("Unable to set **{title}**: {e}")),
+            };
+            let reward = NewRewardAction {
+                channel: channel.clone(),
+                reward_title: title.to_string(),
+                action: action.kind().to_string(),
+                argument: text("value").to_string(),
+                duration_minutes: minutes,
+                auto_settle: value("auto_settle").and_then(|v| v.as_bool()).unwrap_or(false),
+            };
+            let saved = db::blocking(move || {
+                db::save_reward_action(&reward)?;
+                db::find_reward_action(&reward.channel, &reward.reward_title)
+            })
+            .await?;
+            match saved {
+                Some(saved) => format!("In #{channel}, {}", describe(&saved)),
+                None => format!("Saved **{title}** in #{channel}"),
+            }
+        },
+        "remove" => {
+            let (chan, reward) = (channel.clone(), title.to_string());
+            match db::blocking(move || db::delete_reward_action(&chan, &reward)).await? {
+                0 => format!("**{title}** has no action in #{channel}"),
+                _ => format!("Removed the action of **{title}** in #{channel}"),
+            }
+        },
+        "queue" => {
+            let queue = rewards::queue(&channel);
+            match queue.is_empty() {
+                true => format!("#{channel}'s queue is empty"),
+                false => queue
+                    .iter()
+                    .enumerate()
+                    .map(|(i, e)| format!("{}. **{}** {}", i + 1, e.user_name, e.input))
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            }
+        },
+        "next" => match rewards::next(&channel) {
+            Some(entry) => format!("Up next: **{}** {}", entry.user_name, entry.input),
+            None => format!("#{channel}'s queue is empty"),
+        },
+        _ => "Pick list, set, remove, queue or next".to_string(),
+    };
+    Ok(reply)
+}
+
+///Called when the command is run in a guild.
+pub async fn run(options: &CommandInteraction, context: &Context) -> CreateEmbed {
+    debug!("{:?}", options.data.options);
+    let subcommand = options.data.options.first();
+    let values = match subcommand.map(|s| &s.value) {
+        Some(CommandDataOptionValue::SubCommand(values)) => values.as_slice(),
+        _ => &[],
+    };
+    let name = subcommand.map(|s| s.name.as_str()).unwrap_or_default();
+    let reply = match is_admin(options) {
+        false => "Only administrators can manage rewards".to_string(),
+        true => respond(name, values).await.unwrap_or_else(|e| {
+            error!("/rewards {name} failed: {e:?}");
+            "Unable to reach the reward database".to_string()
+        }),
+    };
+    reply_embed(context, "Channel point rewards", reply)
+}
+
+fn channel_option() -> CreateCommandOption {
+    CreateCommandOption::new(
+        CommandOptionType::String,
+        "channel",
+        "The Twitch channel, the bot's own when left out",
+    )
+}
+
+fn title_option() -> CreateCommandOption {
+    CreateCommandOption::new(CommandOptionType::String, "title", "The reward's title")
+        .required(true)
+        .max_length(rewards::TITLE_LIMIT as u16)
+}
+
+///Register the command to be used in the guild.
+pub fn register() -> CreateCommand {
+    let action = rewards::Action::KINDS.iter().fold(
+        CreateCommandOption::new(CommandOptionType::String, "action", "What redeeming it does")
+            .required(true),
+        |option, kind| option.add_string_choice(*kind, *kind),
+    );
+    CreateCommand::new("rewards")
+        .description("Manage what channel point rewards do")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "list", "List reward actions")
+                .add_sub_option(channel_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "set", "Set a reward's action")
+                .add_sub_option(title_option())
+                .add_sub_option(action)
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "value",
+                    "The message, role or command name",
+                ))
+                .add_sub_option(
+                    CreateCommandOption::new(
+                        CommandOptionType::Integer,
+                        "minutes",
+                        "How long a role is kept",
+                    )
+                    .min_int_value(1),
+                )
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::Boolean,
+                    "auto_settle",
+                    "Fulfil the redemption when the action works, refund it when it doesn't",
+                ))
+                .add_sub_option(channel_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "remove",
+                "Remove a reward's action",
+            )
+            .add_sub_option(title_option())
+            .add_sub_option(channel_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "queue", "Show the queue")
+                .add_sub_option(channel_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "next",
+                "Take the next viewer out of the queue",
+            )
+            .add_sub_option(channel_option()),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_rewards() {
+        let reward = RewardAction {
+            id: 1,
+            channel: "testchannel".to_string(),
+            reward_title: "VIP for a day".to_string(),
+            action: "role".to_string(),
+            argument: "1234".to_string(),
+            duration_minutes: Some(1440),
+            auto_settle: true,
+        };
+        assert_eq!(
+            describe(&reward),
+            "**VIP for a day** grants <@&1234> for 1440 minute(s), then settles it"
+        );
+    }
+}
@@ -0,0 +1,67 @@
+//!`/stream title` and `/stream game` show or change the bot channel's title and category, see
+//![`broadcast`](crate::twitch::broadcast).
+
+//crate
+use crate::cooldown::Cooldown;
+use crate::discord::commands::reply_embed;
+use crate::discord::is_moderator;
+use crate::twitch::{bot_channel, broadcast};
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, debug};
+use crate::utils::commandinteraction::CommandInteraction;
+
+//serenity
+use serenity::all::{CommandDataOptionValue, CommandOptionType, Context};
+use serenity::builder::{CreateCommand, CreateCommandOption, CreateEmbed};
+
+///Default cooldowns, which `[commands.cooldowns]` may override.
+pub const COOLDOWN: Cooldown = Cooldown::NONE;
+
+///Called when the command is run in a guild.
+pub async fn run(options: &CommandInteraction, context: &Context) -> CreateEmbed {
+    debug!("{:?}", options.data.options);
+    let subcommand = options.data.options.first();
+    let value = match subcommand.map(|s| &s.value) {
+        Some(CommandDataOptionValue::SubCommand(values)) => {
+            values.first().and_then(|v| v.value.as_str())
+        },
+        _ => None,
+    };
+    let moderator = options.user.name.as_str();
+    let result = match subcommand.map(|s| s.name.as_str()) {
+        _ if value.is_some() && !is_moderator(options.member.as_deref()) => {
+            Ok("Only moderators can change the stream".to_string())
+        },
+        Some("title") => broadcast::title(value, "discord", moderator).await,
+        Some("game") => broadcast::game(value, "discord", moderator).await,
+        _ => Ok("Pick title or game".to_string()),
+    };
+    let reply = result.unwrap_or_else(|e| {
+        error!("/stream failed: {e:?}");
+        "Twitch didn't accept that, check the bot's logs".to_string()
+    });
+    reply_embed(context, format!("Stream in #{}", bot_channel()), reply)
+}
+
+///Register the command to be used in the guild.
+pub fn register() -> CreateCommand {
+    CreateCommand::new("stream")
+        .description("Show or change the stream's title and category")
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "title", "The stream title")
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "text",
+                    "The new title, leave out to see the current one",
+                )),
+        )
+        .add_option(
+            CreateCommandOption::new(CommandOptionType::SubCommand, "game", "The stream category")
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "name",
+                    "The new category, leave out to see the current one",
+                )),
+        )
+}
@@ -0,0 +1,94 @@
+//!`/twitch channels` lists the Twitch channels the bot is in and who put it there, see
+//![`channels`](crate::twitch::channels). Only administrators can use it.
+
+//crate
+use crate::cooldown::Cooldown;
+use crate::db::{self, models::TwitchChannel};
+use crate::discord::commands::reply_embed;
+use crate::discord::is_admin;
+use crate::twitch::channels;
+use crate::utils::commandinteraction::CommandInteraction;
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, debug};
+
+//serenity
+use serenity::all::{CommandOptionType, Context, Permissions};
+use serenity::builder::{CreateCommand, CreateCommandOption, CreateEmbed};
+
+///Default cooldowns, which `[commands.cooldowns]` may override.
+pub const COOLDOWN: Cooldown = Cooldown::NONE;
+
+///One line per channel the bot is in saying where it comes from, then the configured channels
+///that were parted.
+pub fn summary(configured: &[String], stored: &[TwitchChannel]) -> String {
+    let current = channels::merge(configured, stored);
+    let stored_by = |name: &str| stored.iter().find(|c| c.name.eq_ignore_ascii_case(name));
+    let mut lines: Vec<String> = current
+        .iter()
+        .map(|name| match stored_by(name).filter(|c| c.joined) {
+            Some(c) => format!("#{name}, joined by {} on {}", c.updated_by, c.updated_at.date()),
+            None => format!("#{name}, from the config"),
+        })
+        .collect();
+    lines.extend(
+        stored
+            .iter()
+            .filter(|c| !c.joined && configured.iter().any(|n| n.eq_ignore_ascii_case(&c.name)))
+            .map(|c| {
+                format!("~~#{}~~, parted by {} on {}", c.name, c.updated_by, c.updated_at.date())
+            }),
+    );
+    match lines.is_empty() {
+        true => "Not in any channel, add one with !join".to_string(),
+        false => lines.join("\n"),
+    }
+}
+
+///Called when the command is run in a guild.
+pub async fn run(options: &CommandInteraction, context: &Context) -> CreateEmbed {
+    debug!("{:?}", options.data.options);
+    let reply = match is_admin(options) {
+        false => "Only administrators can see the bot's channels".to_string(),
+        true => match db::blocking(db::find_twitch_channels).await {
+            Ok(stored) => summary(&CONFIG.twitch_channels, &stored),
+            Err(e) => {
+                error!("/twitch channels failed: {e:?}");
+                "Unable to reach the channel database".to_string()
+            },
+        },
+    };
+    reply_embed(context, "Twitch channels", reply)
+}
+
+///Register the command to be used in the guild.
+pub fn register() -> CreateCommand {
+    CreateCommand::new("twitch")
+        .description("The bot's Twitch presence")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "channels",
+            "List the channels the bot is in",
+        ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::twitch::channels::stored;
+
+    #[test]
+    fn summarises_channels() {
+        let configured = ["testuser".to_string(), "twitch".to_string()];
+        let stored = [stored("twitch", false), stored("twitchrivals", true)];
+        assert_eq!(
+            summary(&configured, &stored),
+            "#testuser, from the config\n\
+             #twitchrivals, joined by testuser on 1970-01-01\n\
+             ~~#twitch~~, parted by testuser on 1970-01-01"
+        );
+        assert_eq!(summary(&[], &[]), "Not in any channel, add one with !join");
+    }
+}
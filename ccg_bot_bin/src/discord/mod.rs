@@ -1,7 +1,9 @@
 //!This way be Discord
 
 //crate
+use crate::archive::{self, ArchiveRecord};
 use crate::config::Config;
+use crate::cooldown::{self, Cooldown};
 #[cfg(test)]
 use crate::env;
 //skip reordering to allow easy reference to verbosity(from least to most)
@@ -17,7 +19,7 @@ use serde::ser::SerializeSeq;
 use serenity::all::ShardId;
 use serenity::all::{
     Client, Context, CreateInteractionResponse, CreateInteractionResponseMessage, EventHandler,
-    GatewayIntents, GuildId, Interaction, Message, Ready,
+    GatewayIntents, GuildId, Http, Interaction, Member, Message, Permissions, Ready,
 };
 use serenity::async_trait;
 //use serenity::model::prelude::*;
@@ -25,14 +27,16 @@ use serenity::async_trait;
 //std
 use std::error;
 use std::fmt;
+use std::sync::Arc;
 
 //re-exports
 #[cfg(not(test))]
-mod builders;
+pub(crate) mod builders;
 #[cfg(test)]
 pub mod builders;
 use self::builders::discordembed::DiscordEmbed;
 
+pub(crate) mod bridge;
 #[doc(hidden)]
 mod cache;
 #[cfg(not(test))]
@@ -47,6 +51,28 @@ lazy_static! {
         | GatewayIntents::MESSAGE_CONTENT
         | GatewayIntents::GUILD_MEMBERS
         | GatewayIntents::GUILD_PRESENCES;
+    ///An HTTP client for talking to Discord from outside of an event handler.
+    pub static ref HTTP: Arc<Http> = Arc::new(Http::new(&crate::CONFIG.discord_token));
+}
+
+///Whether `member` may moderate the guild, used to skip cooldowns and to change quotes.
+pub(crate) fn is_moderator(member: Option<&Member>) -> bool {
+    member.and_then(|m| m.permissions).is_some_and(|p| {
+        p.intersects(
+            Permissions::ADMINISTRATOR
+                | Permissions::MANAGE_MESSAGES
+                | Permissions::MODERATE_MEMBERS,
+        )
+    })
+}
+
+///Whether whoever ran `options` administers the guild, which the bot's setup commands need.
+pub(crate) fn is_admin(options: &CommandInteraction) -> bool {
+    options
+        .member
+        .as_deref()
+        .and_then(|m| m.permissions)
+        .is_some_and(|p| p.contains(Permissions::ADMINISTRATOR))
 }
 
 #[derive(Debug)]
@@ -57,11 +83,58 @@ impl EventHandler for Handler {
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
         if let Interaction::Command(command) = interaction.clone() {
             debug!("[mod#L58] {:?}", &command.data);
+            let name = command.data.name.as_str();
+            let cooldown = match name {
+                "broadcasters" => commands::broadcasters::COOLDOWN,
+                "id" => commands::id::COOLDOWN,
+                "link" => commands::link::COOLDOWN,
+                "ping" => commands::ping::COOLDOWN,
+                "poll" => commands::poll::COOLDOWN,
+                "predict" => commands::predict::COOLDOWN,
+                "quote" => commands::quote::COOLDOWN,
+                "rewards" => commands::rewards::COOLDOWN,
+                "stream" => commands::stream::COOLDOWN,
+                "twitch" => commands::twitch::COOLDOWN,
+                _ => Cooldown::NONE,
+            };
+            // moderators aren't held back by cooldowns
+            let checked = match is_moderator(command.member.as_deref()) {
+                true => Ok(()),
+                false => cooldown::check(
+                    &format!("discord:{name}"),
+                    &command.user.id.to_string(),
+                    cooldown.configured(name),
+                ),
+            };
+            if let Err(remaining) = checked {
+                debug!("/{name} is on cooldown for {}", command.user.name);
+                if self.0.command_cooldown_reply {
+                    let data =
+                        CreateInteractionResponseMessage::new().ephemeral(true).content(format!(
+                            "/{name} is on cooldown, try again in {}",
+                            cooldown::format_remaining(remaining)
+                        ));
+                    let builder = CreateInteractionResponse::Message(data);
+                    if let Err(why) = command.create_response(&ctx.http, builder).await {
+                        println!("Cannot respond to slash command: {why}");
+                    }
+                }
+                return;
+            }
             let command_interaction = CommandInteraction::from(interaction);
             let content = match command.data.name.as_str() {
+                "broadcasters" => {
+                    Some(commands::broadcasters::run(&command_interaction, &ctx).await)
+                },
                 "id" => Some(commands::id::run(&command_interaction, &ctx).await),
                 "link" => Some(commands::link::run(&command_interaction, &ctx).await),
                 "ping" => Some(commands::ping::run(&command_interaction, &ctx).await),
+                "poll" => Some(commands::poll::run(&command_interaction, &ctx).await),
+                "predict" => Some(commands::predict::run(&command_interaction, &ctx).await),
+                "quote" => Some(commands::quote::run(&command_interaction, &ctx).await),
+                "rewards" => Some(commands::rewards::run(&command_interaction, &ctx).await),
+                "stream" => Some(commands::stream::run(&command_interaction, &ctx).await),
+                "twitch" => Some(commands::twitch::run(&command_interaction, &ctx).await),
                 _ => Some(DiscordEmbed::not_implemented()),
             };
 
@@ -85,9 +158,16 @@ impl EventHandler for Handler {
             .set_commands(
                 &ctx.http,
                 vec![
+                    commands::broadcasters::register(),
                     commands::id::register(),
                     commands::link::register(),
                     commands::ping::register(),
+                    commands::poll::register(),
+                    commands::predict::register(),
+                    commands::quote::register(),
+                    commands::rewards::register(),
+                    commands::stream::register(),
+                    commands::twitch::register(),
                 ],
             )
             .await;
@@ -98,6 +178,7 @@ impl EventHandler for Handler {
 
     ///This prints every message the bot can see, in the format:
     ///<pre>[Channel] Author: Message</pre>
+    ///and hands it to the [chat archive](crate::archive) and any [bridge](bridge) into Twitch.
     async fn message<'a>(&'a self, ctx: Context, msg: Message) {
         // let channel_name: String = match ctx.cache.guild_channel(msg.channel_id) {
         let channel_name: String = match ctx.cache.channel(msg.channel_id) {
@@ -105,6 +186,19 @@ impl EventHandler for Handler {
             None => return,
         };
         println!("[Discord / #{}] {}: {}", channel_name, msg.author.name, msg.content);
+        let roles: Vec<String> = match (msg.guild_id, msg.member.as_ref()) {
+            (Some(gid), Some(member)) => match ctx.cache.guild(gid) {
+                Some(guild) => member
+                    .roles
+                    .iter()
+                    .filter_map(|r| guild.roles.get(r).map(|role| role.name.clone()))
+                    .collect(),
+                None => member.roles.iter().map(|r| r.to_string()).collect(),
+            },
+            _ => vec![],
+        };
+        archive::record(ArchiveRecord::discord(&msg, channel_name, roles));
+        bridge::relay(&ctx, &msg).await;
     }
 }
 
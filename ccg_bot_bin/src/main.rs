@@ -29,12 +29,16 @@ use std::result::Result as StdResult;
 #[cfg(test)]
 mod tests;
 
+mod archive;
+mod audit;
 mod config;
+mod cooldown;
 mod db;
 mod discord;
 #[macro_use]
 mod internals;
 
+mod quotes;
 mod twitch;
 mod utils;
 
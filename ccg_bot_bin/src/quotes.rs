@@ -0,0 +1,132 @@
+//!Community quotes shared by Twitch and Discord.
+//!
+//!Quotes live in one `quotes` table no matter where they were added, along with the category the
+//!bot's Twitch channel was streaming at the time, looked up through Helix. Everything here runs
+//!its queries on the [blocking pool](db::blocking) so chat and Discord aren't held up by them.
+
+//crate
+use crate::db::{self, models::NewQuote, models::Quote};
+use crate::twitch::helix;
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::debug;
+
+///The category `channel` is streaming or last streamed, [`None`] if it can't be looked up.
+pub async fn current_game(channel: &str) -> Option<String> {
+    let helix = helix::get()?;
+    let lookup = async {
+        let token = helix.token().await?;
+        Ok::<_, eyre::Report>(helix.client.get_channel_from_login(channel, &token).await?)
+    };
+    match lookup.await {
+        Ok(info) => info.map(|i| i.game_name.to_string()).filter(|g| !g.is_empty()),
+        Err(e) => {
+            debug!("Unable to look up the category of #{channel}: {e}");
+            None
+        },
+    }
+}
+
+///Adds a quote, capturing the category of `channel` or the bot's own channel.
+pub async fn add(
+    text: &str,
+    channel: Option<&str>,
+    platform: &str,
+    added_by: &str,
+) -> eyre::Result<Quote> {
+    let channel = channel.unwrap_or(&CONFIG.twitch_bot_name);
+    let quote = NewQuote {
+        text: text.trim().to_string(),
+        game: current_game(channel).await,
+        platform: platform.to_string(),
+        added_by: added_by.to_string(),
+    };
+    db::blocking(move || db::create_quote(&quote)).await
+}
+
+///A quote picked at random, [`None`] if there are none yet.
+pub async fn random() -> eyre::Result<Option<Quote>> {
+    db::blocking(db::find_random_quote).await
+}
+
+///Quote `#id`, [`None`] if there is no such quote.
+pub async fn find(id: u32) -> eyre::Result<Option<Quote>> {
+    db::blocking(move || db::find_quote(id)).await
+}
+
+///Every quote mentioning `term`, oldest first.
+pub async fn search(term: &str) -> eyre::Result<Vec<Quote>> {
+    let term = term.to_string();
+    db::blocking(move || db::search_quotes(&term)).await
+}
+
+///Replaces the text of quote `#id`, returning how many quotes changed.
+pub async fn edit(id: u32, text: &str) -> eyre::Result<usize> {
+    let text = text.trim().to_string();
+    db::blocking(move || db::update_quote(id, &text)).await
+}
+
+///Deletes quote `#id`, returning how many quotes were removed.
+pub async fn delete(id: u32) -> eyre::Result<usize> {
+    db::blocking(move || db::delete_quote(id)).await
+}
+
+///Formats `quote` for chat, e.g. `#4: "Hello" [Just Chatting, 2026-10-19]`.
+pub fn format(quote: &Quote) -> String {
+    let date = quote.created_at.format("%Y-%m-%d");
+    match &quote.game {
+        Some(game) => format!("#{}: \"{}\" [{game}, {date}]", quote.id, quote.text),
+        None => format!("#{}: \"{}\" [{date}]", quote.id, quote.text),
+    }
+}
+
+///Formats the result of a search for `term`, showing the first match and the ids of the rest.
+pub fn format_search(term: &str, found: &[Quote]) -> String {
+    match found {
+        [] => format!("No quotes mention \"{term}\""),
+        [quote] => format(quote),
+        [first, rest @ ..] => {
+            let ids: Vec<String> = rest.iter().map(|q| format!("#{}", q.id)).collect();
+            format!("{} (also {})", format(first), ids.join(", "))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn quote(id: u32, game: Option<&str>) -> Quote {
+        Quote {
+            id,
+            text: "I meant to do that".to_string(),
+            game: game.map(str::to_string),
+            platform: "twitch".to_string(),
+            added_by: "testuser".to_string(),
+            created_at: NaiveDate::from_ymd_opt(2026, 10, 19)
+                .unwrap()
+                .and_hms_opt(20, 15, 0)
+                .unwrap(),
+        }
+    }
+
+    #[test]
+    fn formats_quotes() {
+        assert_eq!(
+            format(&quote(4, Some("Just Chatting"))),
+            "#4: \"I meant to do that\" [Just Chatting, 2026-10-19]"
+        );
+        assert_eq!(format(&quote(5, None)), "#5: \"I meant to do that\" [2026-10-19]");
+    }
+
+    #[test]
+    fn formats_searches() {
+        assert_eq!(format_search("nope", &[]), "No quotes mention \"nope\"");
+        assert_eq!(
+            format_search("meant", &[quote(4, None), quote(7, None), quote(9, None)]),
+            "#4: \"I meant to do that\" [2026-10-19] (also #7, #9)"
+        );
+    }
+}
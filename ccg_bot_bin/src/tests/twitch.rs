@@ -42,6 +42,7 @@ async fn it_works() {
         twitch_client_secret: "".to_string(),
         twitch_redirect_url: "http://localhost/".to_string(),
         bot_admins: vec!["test_admin".to_string()],
+        ..Default::default()
     })
     .await;
     let twitch_bool = twitch.is_ok();
@@ -101,6 +102,7 @@ fn token_derives() {
         uid: uid.clone(),
         name: name.clone(),
         client_secret: client_secret.clone(),
+        scopes: vec![],
     };
     let _clone = token.clone();
     let debug = format!("{:?}", &token);
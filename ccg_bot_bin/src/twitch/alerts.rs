@@ -0,0 +1,397 @@
+//!Follow, subscription, gift, cheer and raid alerts, posted in chat and to
+//!`[alerts] discord_channel_id`.
+//!
+//!Subscriptions, gifts and raids arrive in chat as USERNOTICEs, cheers as messages carrying bits
+//!and follows through EventSub. Twitch announces a gift bomb with one notice and then sends a
+//!notice for every gift in it, so [`Gifts`] drops the gifts that were announced and gathers the
+//!ones that weren't for `gift_window_seconds`, leaving one alert per bomb.
+
+//crate
+use crate::config::Alerts;
+use crate::discord::bridge::{truncate, TWITCH_MESSAGE_LIMIT};
+use crate::discord::builders::discordembed::DiscordEmbed;
+use crate::discord::HTTP;
+use crate::twitch::commands::variables;
+use crate::twitch::IRC_CLIENT;
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, info, debug, trace};
+
+use lazy_static::lazy_static;
+
+//serenity
+use serenity::all::{ChannelId, CreateMessage};
+
+//std
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+//twitch_api
+use twitch_api::eventsub::channel::ChannelFollowV2Payload;
+
+//twitch_irc
+use twitch_irc::message::{PrivmsgMessage, UserNoticeEvent, UserNoticeMessage};
+
+///Twitch purple.
+const ALERT_COLOR: u32 = 0x9146FF;
+///How long the gifts of an announced bomb are waited for.
+const ANNOUNCED_FOR: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    static ref GIFTS: Mutex<Gifts> = Mutex::new(Gifts::default());
+}
+
+fn gifts() -> std::sync::MutexGuard<'static, Gifts> {
+    match GIFTS.lock() {
+        Ok(gifts) => gifts,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+///The event an alert is for, each has its own template.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Kind {
+    Follow,
+    Sub,
+    Resub,
+    Gift,
+    GiftBomb,
+    Cheer,
+    Raid,
+}
+
+impl Kind {
+    ///The configured template, [`None`] if this alert is turned off.
+    pub fn template(self, alerts: &Alerts) -> Option<&String> {
+        match self {
+            Kind::Follow => alerts.follow.as_ref(),
+            Kind::Sub => alerts.sub.as_ref(),
+            Kind::Resub => alerts.resub.as_ref(),
+            Kind::Gift => alerts.gift.as_ref(),
+            Kind::GiftBomb => alerts.gift_bomb.as_ref(),
+            Kind::Cheer => alerts.cheer.as_ref(),
+            Kind::Raid => alerts.raid.as_ref(),
+        }
+    }
+}
+
+///Something worth an alert, fields an event doesn't have are left empty.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Alert {
+    pub kind: Kind,
+    ///Who followed, subscribed, gifted, cheered or raided.
+    pub user: String,
+    pub tier: String,
+    pub months: Option<u64>,
+    pub streak: Option<u64>,
+    ///Bits cheered, subscriptions gifted or viewers brought along.
+    pub amount: Option<u64>,
+    pub recipient: String,
+    pub message: String,
+}
+
+impl Alert {
+    ///An alert of `kind` for `user` with nothing else filled in.
+    pub fn new(kind: Kind, user: &str) -> Self {
+        Alert {
+            kind,
+            user: user.to_string(),
+            tier: String::new(),
+            months: None,
+            streak: None,
+            amount: None,
+            recipient: String::new(),
+            message: String::new(),
+        }
+    }
+}
+
+///`1`, `2` or `3` for paid plans, `Prime` for Prime Gaming.
+pub fn tier(sub_plan: &str) -> String {
+    match sub_plan {
+        "1000" => "1".to_string(),
+        "2000" => "2".to_string(),
+        "3000" => "3".to_string(),
+        other => other.to_string(),
+    }
+}
+
+///Fills in `template` with the variables of `alert` in `channel`.
+pub fn render(template: &str, channel: &str, alert: &Alert) -> String {
+    let number = |n: Option<u64>| n.map(|n| n.to_string()).unwrap_or_default();
+    variables::expand(template, |variable| match variable {
+        "user" => Some(alert.user.clone()),
+        "channel" => Some(channel.to_string()),
+        "tier" => Some(alert.tier.clone()),
+        "months" => Some(number(alert.months)),
+        "streak" => Some(number(alert.streak)),
+        "amount" => Some(number(alert.amount)),
+        "recipient" => Some(alert.recipient.clone()),
+        "message" => Some(alert.message.clone()),
+        _ => None,
+    })
+}
+
+///What happened to a gift given to [`Gifts::add`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Gathered {
+    ///It belongs to a bomb that already had its alert.
+    Announced,
+    ///It started a new batch, which should be taken once the window passes.
+    First,
+    ///It joined a batch that is already waiting.
+    Added,
+}
+
+///Gifted subscriptions waiting to be alerted, keyed by channel and gifter.
+#[derive(Debug, Default)]
+pub struct Gifts {
+    announced: HashMap<(String, String), (Instant, u64)>,
+    gathering: HashMap<(String, String), Vec<Alert>>,
+}
+
+impl Gifts {
+    ///Expects `amount` gifts from `gifter`, which were announced as a bomb.
+    pub fn announce(&mut self, channel: &str, gifter: &str, amount: u64) {
+        let key = (channel.to_string(), gifter.to_string());
+        let left = match self.announced.get(&key) {
+            Some((at, left)) if at.elapsed() < ANNOUNCED_FOR => *left,
+            _ => 0,
+        };
+        self.announced.insert(key, (Instant::now(), left + amount));
+    }
+
+    ///Adds a single gift, see [`Gathered`].
+    pub fn add(&mut self, channel: &str, gift: Alert) -> Gathered {
+        let key = (channel.to_string(), gift.user.clone());
+        if let Some((at, left)) = self.announced.get_mut(&key) {
+            let expected = at.elapsed() < ANNOUNCED_FOR && *left > 0;
+            *left = left.saturating_sub(1);
+            if !expected || *left == 0 {
+                self.announced.remove(&key);
+            }
+            if expected {
+                return Gathered::Announced;
+            }
+        }
+        let batch = self.gathering.entry(key).or_default();
+        batch.push(gift);
+        match batch.len() {
+            1 => Gathered::First,
+            _ => Gathered::Added,
+        }
+    }
+
+    ///Takes `gifter`'s batch, as a single gift or as a bomb of all of them.
+    pub fn take(&mut self, channel: &str, gifter: &str) -> Option<Alert> {
+        let mut batch = self.gathering.remove(&(channel.to_string(), gifter.to_string()))?;
+        let amount = batch.len() as u64;
+        let mut alert = batch.swap_remove(0);
+        if amount > 1 {
+            alert.kind = Kind::GiftBomb;
+            alert.amount = Some(amount);
+            alert.recipient.clear();
+        }
+        Some(alert)
+    }
+}
+
+///The alert for a USERNOTICE, [`None`] for notices that don't get one.
+pub fn from_notice(message: &UserNoticeMessage) -> Option<Alert> {
+    let user = message.sender.name.as_str();
+    let text = message.message_text.clone().unwrap_or_default();
+    let alert = match &message.event {
+        UserNoticeEvent::SubOrResub {
+            is_resub,
+            cumulative_months,
+            streak_months,
+            sub_plan,
+            ..
+        } => Alert {
+            tier: tier(sub_plan),
+            months: Some(*cumulative_months),
+            streak: *streak_months,
+            message: text,
+            ..Alert::new(if *is_resub { Kind::Resub } else { Kind::Sub }, user)
+        },
+        UserNoticeEvent::SubGift { recipient, sub_plan, .. } => Alert {
+            tier: tier(sub_plan),
+            amount: Some(1),
+            recipient: recipient.name.clone(),
+            ..Alert::new(Kind::Gift, user)
+        },
+        UserNoticeEvent::SubMysteryGift { mass_gift_count, sub_plan, .. }
+        | UserNoticeEvent::AnonSubMysteryGift { mass_gift_count, sub_plan, .. } => Alert {
+            tier: tier(sub_plan),
+            amount: Some(*mass_gift_count),
+            ..Alert::new(Kind::GiftBomb, user)
+        },
+        UserNoticeEvent::Raid { viewer_count, .. } => {
+            Alert { amount: Some(*viewer_count), ..Alert::new(Kind::Raid, user) }
+        },
+        _ => return None,
+    };
+    Some(alert)
+}
+
+///Sends `alert` to chat and Discord in its own task, so the chat loop never waits on either.
+fn post(channel: &str, alert: &Alert) {
+    tokio::spawn(send(channel.to_string(), alert.clone()));
+}
+
+async fn send(channel: String, alert: Alert) {
+    let Some(alerts) = CONFIG.alerts.as_ref() else {
+        return;
+    };
+    let Some(template) = alert.kind.template(alerts) else {
+        return debug!("no {:?} alert configured", alert.kind);
+    };
+    let text = render(template, &channel, &alert);
+    info!("[twitch / #{channel}] alert: {text}");
+    match IRC_CLIENT.get() {
+        Some(client) => {
+            if let Err(e) = client.say(channel.clone(), truncate(&text, TWITCH_MESSAGE_LIMIT)).await
+            {
+                error!("Unable to send the {:?} alert to #{channel}: {e}", alert.kind);
+            }
+        },
+        None => error!("chat isn't connected, dropping the {:?} alert", alert.kind),
+    }
+    let Some(id) = alerts.discord_channel_id.as_ref() else {
+        return;
+    };
+    let Ok(id) = id.parse::<u64>() else {
+        return error!("invalid alerts discord_channel_id `{id}`");
+    };
+    let embed =
+        DiscordEmbed::new().title(format!("#{channel}")).description(text).color(ALERT_COLOR);
+    let message = CreateMessage::new().embed(embed.build());
+    if let Err(e) = ChannelId::new(id).send_message(&*HTTP, message).await {
+        error!("Unable to post the {:?} alert to Discord: {e}", alert.kind);
+    }
+}
+
+///Posts the alert for a subscription, gift or raid notice, gathering gifts into bombs.
+#[allow(unused)]
+pub fn user_notice(message: &UserNoticeMessage) {
+    if CONFIG.alerts.is_none() {
+        return;
+    }
+    let Some(alert) = from_notice(message) else {
+        return trace!("no alert for {:?}", message.event);
+    };
+    let channel = message.channel_login.clone();
+    match alert.kind {
+        Kind::GiftBomb => {
+            gifts().announce(&channel, &alert.user, alert.amount.unwrap_or_default());
+            post(&channel, &alert)
+        },
+        Kind::Gift => {
+            let gifter = alert.user.clone();
+            let gathered = gifts().add(&channel, alert);
+            if gathered != Gathered::First {
+                return debug!("gathered a gift from {gifter} in #{channel}: {gathered:?}");
+            }
+            let window = CONFIG.alerts.as_ref().and_then(|a| a.gift_window_seconds).unwrap_or(5);
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_secs(window)).await;
+                let batch = gifts().take(&channel, &gifter);
+                if let Some(alert) = batch {
+                    post(&channel, &alert);
+                }
+            });
+        },
+        _ => post(&channel, &alert),
+    }
+}
+
+///Posts the alert for a message carrying at least `min_bits` bits.
+#[allow(unused)]
+pub fn cheered(message: &PrivmsgMessage) {
+    let Some(bits) = message.bits else {
+        return;
+    };
+    let min_bits = CONFIG.alerts.as_ref().and_then(|a| a.min_bits).unwrap_or(1);
+    if bits < min_bits {
+        return;
+    }
+    let alert = Alert {
+        amount: Some(bits),
+        message: message.message_text.clone(),
+        ..Alert::new(Kind::Cheer, &message.sender.name)
+    };
+    post(&message.channel_login, &alert)
+}
+
+///Posts the alert for a new follower.
+pub async fn followed(payload: &ChannelFollowV2Payload) -> eyre::Result<()> {
+    let alert = Alert::new(Kind::Follow, payload.user_name.as_str());
+    post(payload.broadcaster_user_login.as_str(), &alert);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gift(gifter: &str, recipient: &str) -> Alert {
+        Alert {
+            tier: "1".to_string(),
+            amount: Some(1),
+            recipient: recipient.to_string(),
+            ..Alert::new(Kind::Gift, gifter)
+        }
+    }
+
+    #[test]
+    fn renders_templates() {
+        let alert = Alert {
+            tier: tier("2000"),
+            months: Some(12),
+            streak: Some(3),
+            message: "hi chat".to_string(),
+            ..Alert::new(Kind::Resub, "TestUser")
+        };
+        let template = "$(user) resubscribed to $(channel) at tier $(tier) for $(months) months \
+                        ($(streak) in a row, $(amount)): $(message)";
+        assert_eq!(
+            render(template, "testchannel", &alert),
+            "TestUser resubscribed to testchannel at tier 2 for 12 months (3 in a row, ): hi chat"
+        );
+        assert_eq!(tier("Prime"), "Prime");
+    }
+
+    #[test]
+    fn picks_templates() {
+        let alerts = Alerts { cheer: Some("$(amount) bits".to_string()), ..Default::default() };
+        assert_eq!(Kind::Cheer.template(&alerts), Some(&"$(amount) bits".to_string()));
+        assert_eq!(Kind::Follow.template(&alerts), None);
+    }
+
+    #[test]
+    fn gathers_gifts() {
+        let mut gifts = Gifts::default();
+        assert_eq!(gifts.add("testchannel", gift("TestUser", "First")), Gathered::First);
+        assert_eq!(gifts.add("testchannel", gift("TestUser", "Second")), Gathered::Added);
+        assert_eq!(gifts.add("testchannel", gift("OtherUser", "Third")), Gathered::First);
+        let bomb = gifts.take("testchannel", "TestUser").unwrap();
+        assert_eq!(
+            (bomb.kind, bomb.amount, bomb.recipient.as_str()),
+            (Kind::GiftBomb, Some(2), "")
+        );
+        let single = gifts.take("testchannel", "OtherUser").unwrap();
+        assert_eq!((single.kind, single.recipient.as_str()), (Kind::Gift, "Third"));
+        assert!(gifts.take("testchannel", "TestUser").is_none());
+    }
+
+    #[test]
+    fn drops_announced_gifts() {
+        let mut gifts = Gifts::default();
+        gifts.announce("testchannel", "TestUser", 2);
+        assert_eq!(gifts.add("testchannel", gift("TestUser", "First")), Gathered::Announced);
+        assert_eq!(gifts.add("testchannel", gift("TestUser", "Second")), Gathered::Announced);
+        assert_eq!(gifts.add("testchannel", gift("TestUser", "Third")), Gathered::First);
+    }
+}
@@ -0,0 +1,114 @@
+//!Lets broadcasters authorize the bot for their own channel.
+//!
+//!`/auth/twitch/broadcaster` sends the broadcaster to Twitch with [`SCOPE`], Twitch sends them
+//!back to `[broadcaster_auth] redirect_url` and the token is kept by
+//![`broadcasters::store`](crate::twitch::broadcasters::store).
+
+//crate
+use crate::twitch::broadcasters::{self, SCOPE};
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, warn, info};
+
+use lazy_static::lazy_static;
+
+//rocket
+use rocket::http::Status;
+use rocket::response::Redirect;
+use rocket::{get, routes, Route};
+
+//std
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+//twitch_api
+use twitch_api::twitch_oauth2::tokens::UserTokenBuilder;
+
+///How long a broadcaster has to finish authorizing.
+const PENDING_FOR: Duration = Duration::from_secs(10 * 60);
+
+lazy_static! {
+    ///Authorizations that were started, keyed by their CSRF state.
+    static ref PENDING: Mutex<HashMap<String, (Instant, UserTokenBuilder)>> =
+        Mutex::new(HashMap::new());
+}
+
+fn pending() -> std::sync::MutexGuard<'static, HashMap<String, (Instant, UserTokenBuilder)>> {
+    match PENDING.lock() {
+        Ok(pending) => pending,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+///The routes to mount, they answer 404 unless `[broadcaster_auth]` is set.
+pub fn routes() -> Vec<Route> {
+    routes![authorize, callback, denied]
+}
+
+#[get("/auth/twitch/broadcaster")]
+fn authorize() -> Result<Redirect, Status> {
+    let redirect = CONFIG.broadcaster_redirect_url.as_ref().ok_or(Status::NotFound)?;
+    let redirect = url::Url::parse(redirect).map_err(|e| {
+        error!("invalid broadcaster_auth redirect_url `{redirect}`: {e}");
+        Status::InternalServerError
+    })?;
+    let mut builder = UserTokenBuilder::new(
+        CONFIG.twitch_client_id.clone(),
+        CONFIG.twitch_client_secret.clone(),
+        redirect,
+    )
+    .set_scopes(SCOPE.to_vec())
+    .force_verify(true);
+    let (url, state) = builder.generate_url();
+    let mut pending = pending();
+    pending.retain(|_, (started, _)| started.elapsed() < PENDING_FOR);
+    pending.insert(state.secret().to_string(), (Instant::now(), builder));
+    Ok(Redirect::to(url.to_string()))
+}
+
+#[get("/auth/twitch/broadcaster/callback?<code>&<state>")]
+async fn callback(code: String, state: String) -> (Status, String) {
+    let builder = pending().remove(&state).map(|(_, builder)| builder);
+    let Some(builder) = builder else {
+        return (Status::BadRequest, "This link expired, please start over".to_string());
+    };
+    let client = reqwest::Client::new();
+    let token = match builder.get_user_token(&client, &state, &code).await {
+        Ok(token) => token,
+        Err(e) => {
+            error!("Unable to exchange a broadcaster's code: {e}");
+            return (Status::BadGateway, "Twitch didn't accept the authorization".to_string());
+        },
+    };
+    if let Err(e) = broadcasters::store(&token).await {
+        error!("Unable to store the token of {}: {e:?}", token.login);
+        return (Status::InternalServerError, "Unable to save the authorization".to_string());
+    }
+    info!("{} authorized the bot", token.login);
+    let bot_name = &CONFIG.twitch_bot_name;
+    (Status::Ok, format!("{bot_name} is authorized for {}! you can close this tab", token.login))
+}
+
+#[get("/auth/twitch/broadcaster/callback?<error>&<error_description>", rank = 2)]
+fn denied(error: String, error_description: String) -> String {
+    warn!("a broadcaster didn't authorize the bot: {error_description} ({error})");
+    let bot_name = &CONFIG.twitch_bot_name;
+    match error.as_str() {
+        "access_denied" => format!("{bot_name} was denied access to your channel"),
+        _ => format!("{bot_name} could not be authorized: {error_description} ({error})"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explains_denials() {
+        let bot_name = &CONFIG.twitch_bot_name;
+        let res = denied("access_denied".to_string(), "The user denied you access".to_string());
+        assert_eq!(res, format!("{bot_name} was denied access to your channel"));
+    }
+}
@@ -1,3 +1,9 @@
+//!The bot's Rocket server, for the bot's own OAuth flow and the routes that stay up.
+
+pub(crate) mod broadcaster;
+
+use crate::twitch::{tokens::AppToken, webhook};
+use crate::CONFIG;
 use reqwest::Url;
 use rocket::{
     get, http::ContentType, response::Responder, routes, Ignite, Response, Rocket, Shutdown, State,
@@ -15,9 +21,10 @@ use tokio::time;
 use twitch_api::twitch_oauth2::{tokens::UserTokenBuilder, ClientId, ClientSecret, Scope};
 use twitch_irc::login::{GetAccessTokenResponse, UserAccessToken};
 
-pub const SCOPE: [Scope; 27] = [
+pub const SCOPE: [Scope; 31] = [
     Scope::ChannelModerate,
     Scope::ChannelReadRedemptions,
+    Scope::ChannelManageRedemptions,
     Scope::ChatRead,
     Scope::ChatEdit,
     Scope::WhispersRead,
@@ -43,6 +50,9 @@ pub const SCOPE: [Scope; 27] = [
     Scope::ModeratorReadChatters,
     Scope::ModeratorManageShieldMode,
     Scope::ModeratorManageShoutouts,
+    Scope::ChannelReadSubscriptions,
+    Scope::ModerationRead,
+    Scope::ModeratorReadFollowers,
 ];
 
 #[allow(unused)]
@@ -103,6 +113,20 @@ pub async fn new(
     Ok((token, rocket_handle))
 }
 
+///Serves broadcaster authorization and EventSub webhooks, whichever are configured.
+#[allow(unused)]
+pub async fn serve(app_token: AppToken) -> eyre::Result<()> {
+    let mut rocket = rocket::build();
+    if CONFIG.broadcaster_redirect_url.is_some() {
+        rocket = rocket.mount("/", broadcaster::routes());
+    }
+    if webhook::config().is_some() {
+        rocket = rocket.mount("/", webhook::routes()).attach(webhook::liftoff(app_token));
+    }
+    rocket.launch().await?;
+    Ok(())
+}
+
 async fn get_token(
     auth_rx: Receiver<UserAccessToken>,
     shutdown_handle: Shutdown,
@@ -0,0 +1,115 @@
+//!Changes the title and category of the bot's channel from chat or Discord.
+//!
+//!Like polls this needs the broadcaster's own token, so it only works for the bot's channel. The
+//!category is looked up with Helix's category search, preferring an exact name, then one starting
+//!with what was typed, then one containing it, before falling back to Twitch's best match. Every
+//!change is recorded in the [audit log](crate::audit), which posts it to the mod-log.
+
+//crate
+use crate::audit;
+use crate::db::models::NewAuditEntry;
+use crate::twitch::bot_channel;
+use crate::twitch::helix::{self, Helix};
+use crate::twitch::tokens::Token;
+
+//twitch_api
+use twitch_api::helix::{
+    channels::{ChannelInformation, ModifyChannelInformationBody, ModifyChannelInformationRequest},
+    search::SearchCategoriesRequest,
+};
+
+///Picks the closest of `names` to `query`, [`None`] if there are no names.
+pub fn best_category(query: &str, names: &[&str]) -> Option<usize> {
+    let query = query.trim().to_lowercase();
+    let names: Vec<String> = names.iter().map(|n| n.to_lowercase()).collect();
+    names
+        .iter()
+        .position(|n| *n == query)
+        .or_else(|| names.iter().position(|n| n.starts_with(&query)))
+        .or_else(|| names.iter().position(|n| n.contains(&query)))
+        .or((!names.is_empty()).then_some(0))
+}
+
+///How a change is shown in chat and the mod-log.
+pub fn change(what: &str, old: &str, new: &str) -> String {
+    format!("Changed the {what} from \"{old}\" to \"{new}\"")
+}
+
+async fn current(helix: &Helix, token: &Token) -> eyre::Result<ChannelInformation> {
+    helix
+        .client
+        .get_channel_from_login(bot_channel().as_str(), token)
+        .await?
+        .ok_or_else(|| eyre::eyre!("#{} doesn't exist", bot_channel()))
+}
+
+fn record(platform: &str, moderator: &str, what: &str, old: &str, new: &str) {
+    audit::record(NewAuditEntry {
+        platform: platform.to_string(),
+        channel: bot_channel(),
+        moderator: moderator.to_string(),
+        action: what.to_string(),
+        target: new.to_string(),
+        reason: Some(format!("was \"{old}\"")),
+        duration_seconds: None,
+    });
+}
+
+///The current title, or sets a new one when `title` is given.
+pub async fn title(title: Option<&str>, platform: &str, moderator: &str) -> eyre::Result<String> {
+    let helix = helix::get().ok_or_else(|| eyre::eyre!("Helix client isn't initialised yet"))?;
+    let token = helix.token().await?;
+    let old = current(helix, &token).await?.title;
+    let Some(title) = title.map(str::trim).filter(|t| !t.is_empty()) else {
+        return Ok(format!("The title is \"{old}\""));
+    };
+    let mut body = ModifyChannelInformationBody::new();
+    body.title(title);
+    let request = ModifyChannelInformationRequest::broadcaster_id(&token.uid);
+    helix.client.req_patch(request, body, &token).await?;
+    record(platform, moderator, "title", &old, title);
+    Ok(change("title", &old, title))
+}
+
+///The current category, or switches to the closest match of `query` when given.
+pub async fn game(query: Option<&str>, platform: &str, moderator: &str) -> eyre::Result<String> {
+    let helix = helix::get().ok_or_else(|| eyre::eyre!("Helix client isn't initialised yet"))?;
+    let token = helix.token().await?;
+    let old = current(helix, &token).await?.game_name.to_string();
+    let Some(query) = query.map(str::trim).filter(|q| !q.is_empty()) else {
+        return Ok(format!("The category is \"{old}\""));
+    };
+    let request = SearchCategoriesRequest::query(query);
+    let categories = helix.client.req_get(request, &token).await?.data;
+    let names: Vec<&str> = categories.iter().map(|c| c.name.as_str()).collect();
+    let Some(index) = best_category(query, &names) else {
+        return Ok(format!("There is no category like \"{query}\""));
+    };
+    let category = &categories[index];
+    let mut body = ModifyChannelInformationBody::new();
+    body.game_id(&category.id);
+    let request = ModifyChannelInformationRequest::broadcaster_id(&token.uid);
+    helix.client.req_patch(request, body, &token).await?;
+    record(platform, moderator, "category", &old, &category.name);
+    Ok(change("category", &old, &category.name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_categories() {
+        let names = ["Halo Infinite", "Halo: The Master Chief Collection", "Halo"];
+        assert_eq!(best_category("halo", &names), Some(2));
+        assert_eq!(best_category("halo: the", &names), Some(1));
+        assert_eq!(best_category("infinite", &names), Some(0));
+        assert_eq!(best_category("mcc", &names), Some(0));
+        assert_eq!(best_category("halo", &[]), None);
+    }
+
+    #[test]
+    fn formats_changes() {
+        assert_eq!(change("title", "Old", "New"), "Changed the title from \"Old\" to \"New\"");
+    }
+}
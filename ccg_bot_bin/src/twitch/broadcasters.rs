@@ -0,0 +1,171 @@
+//!Tokens of broadcasters who authorized the bot for their own channel.
+//!
+//!Ban, role sync, poll, prediction and channel point events need the broadcaster's permission, so without this
+//!they only work in the bot's channel. A broadcaster authorizes the bot through the routes in
+//![`api::broadcaster`](super::api::broadcaster), their token is kept in the `broadcaster_tokens`
+//!table and refreshed there whenever it is used after expiring.
+//!
+//!The access and refresh tokens are stored as plain text, anyone who can read the database can act
+//!for these broadcasters with the [`SCOPE`] they granted until they disconnect the bot on Twitch.
+//!Keep the database user and its backups as private as `client_secret`.
+
+//crate
+use crate::db::{self, models::BroadcasterToken, models::NewBroadcasterToken};
+use crate::twitch::tokens::Token;
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, debug};
+
+//chrono
+use chrono::{DateTime, TimeDelta, Utc};
+
+//std
+use std::time::{Duration, Instant};
+
+//twitch_api
+use twitch_api::twitch_oauth2::{
+    scopes::Scope,
+    tokens::UserToken,
+    types::{AccessToken, ClientId, ClientSecret, RefreshToken},
+    TwitchToken,
+};
+use twitch_api::types::{UserId, UserName};
+use twitch_api::HelixClient;
+
+///Everything the channel-specific EventSub subscriptions need.
+pub const SCOPE: [Scope; 9] = [
+    Scope::ChannelManageRedemptions,
+    Scope::ChannelModerate,
+    Scope::ChannelReadRedemptions,
+    Scope::ChannelReadPolls,
+    Scope::ChannelReadPredictions,
+    Scope::ChannelReadSubscriptions,
+    Scope::ChannelReadVips,
+    Scope::ModerationRead,
+    Scope::ModeratorReadFollowers,
+];
+
+///The page broadcasters open to authorize the bot, [`None`] unless `[broadcaster_auth]` is set.
+pub fn authorize_url() -> Option<String> {
+    let redirect = CONFIG.broadcaster_redirect_url.as_ref()?;
+    let mut url = url::Url::parse(redirect).ok()?;
+    url.set_path("/auth/twitch/broadcaster");
+    url.set_query(None);
+    Some(url.to_string())
+}
+
+async fn rows() -> Vec<BroadcasterToken> {
+    match db::blocking(db::find_broadcaster_tokens).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            error!("Unable to load broadcaster tokens: {e:?}");
+            Vec::new()
+        },
+    }
+}
+
+///Logins of every broadcaster who authorized the bot.
+pub async fn authorized() -> Vec<String> {
+    rows().await.into_iter().map(|t| t.login).collect()
+}
+
+///Whether the broadcaster of `channel` authorized the bot.
+pub async fn is_authorized(channel: &str) -> bool {
+    let login = channel.to_lowercase();
+    matches!(db::blocking(move || db::find_broadcaster_token(&login)).await, Ok(Some(_)))
+}
+
+fn from_now(duration: Duration) -> DateTime<Utc> {
+    Utc::now() + TimeDelta::from_std(duration).unwrap_or_else(|_| TimeDelta::zero())
+}
+
+async fn save(token: &Token) -> eyre::Result<()> {
+    let scopes: Vec<String> = token.scopes.iter().map(|s| s.to_string()).collect();
+    let expires_at = from_now(TwitchToken::expires_in(token));
+    let row = NewBroadcasterToken {
+        broadcaster_id: token.uid.to_string(),
+        login: token.name.to_string(),
+        access_token: token.access_token.secret().to_string(),
+        refresh_token: token.refresh_token.secret().to_string(),
+        scopes: scopes.join(" "),
+        expires_at: expires_at.naive_utc(),
+    };
+    db::blocking(move || db::save_broadcaster_token(&row)).await
+}
+
+///Keeps the token a broadcaster just authorized the bot with.
+pub async fn store(token: &UserToken) -> eyre::Result<()> {
+    let refresh_token = token
+        .refresh_token
+        .clone()
+        .ok_or_else(|| eyre::eyre!("Twitch didn't send {} a refresh token", token.login))?;
+    let token = Token {
+        access_token: token.access_token.clone(),
+        refresh_token,
+        created_at: Utc::now(),
+        expires_at: from_now(token.expires_in()),
+        struct_created: Instant::now(),
+        expires_in: token.expires_in(),
+        clientid: token.client_id().clone(),
+        uid: token.user_id.clone(),
+        name: token.login.clone(),
+        client_secret: ClientSecret::new(CONFIG.twitch_client_secret.clone()),
+        scopes: token.scopes().to_vec(),
+    };
+    save(&token).await
+}
+
+fn to_token(row: &BroadcasterToken) -> Token {
+    let expires_at: DateTime<Utc> = row.expires_at.and_utc();
+    Token {
+        access_token: AccessToken::new(row.access_token.clone()),
+        refresh_token: RefreshToken::new(row.refresh_token.clone()),
+        created_at: row.updated_at.and_utc(),
+        expires_at,
+        struct_created: Instant::now(),
+        expires_in: (expires_at - Utc::now()).to_std().unwrap_or_default(),
+        clientid: ClientId::new(CONFIG.twitch_client_id.clone()),
+        uid: UserId::new(row.broadcaster_id.clone()),
+        name: UserName::new(row.login.clone()),
+        client_secret: ClientSecret::new(CONFIG.twitch_client_secret.clone()),
+        scopes: row.scopes.split_whitespace().map(|s| Scope::parse(s.to_string())).collect(),
+    }
+}
+
+async fn refreshed(
+    client: &HelixClient<'static, reqwest::Client>,
+    row: &BroadcasterToken,
+) -> eyre::Result<Token> {
+    let mut token = to_token(row);
+    if token.is_elapsed() {
+        debug!("refreshing the token of {}", row.login);
+        token.refresh_token(client).await?;
+        save(&token).await?;
+    }
+    Ok(token)
+}
+
+///The token of `channel`'s broadcaster, refreshed if it expired, [`None`] if they never authorized.
+pub async fn token(
+    client: &HelixClient<'static, reqwest::Client>,
+    channel: &str,
+) -> eyre::Result<Option<Token>> {
+    let login = channel.to_lowercase();
+    let Some(row) = db::blocking(move || db::find_broadcaster_token(&login)).await? else {
+        return Ok(None);
+    };
+    refreshed(client, &row).await.map(Some)
+}
+
+///The tokens of every broadcaster who authorized the bot, skipping any that can't be refreshed.
+pub async fn tokens(client: &HelixClient<'static, reqwest::Client>) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    for row in rows().await {
+        match refreshed(client, &row).await {
+            Ok(token) => tokens.push(token),
+            Err(e) => error!("Unable to refresh the token of {}: {e:?}", row.login),
+        }
+    }
+    tokens
+}
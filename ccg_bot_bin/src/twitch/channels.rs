@@ -0,0 +1,202 @@
+//!The Twitch channels the bot is in, `[twitch] channels` merged with the ones bot admins joined or
+//!parted since.
+//!
+//!`!join` and `!part` store the change in the `twitch_channels` table so it outlives a restart,
+//!then join or leave the channel's chat and set up or tear down its EventSub subscriptions on
+//!whichever transport is running.
+
+//crate
+use crate::db::{self, models::NewTwitchChannel, models::TwitchChannel};
+use crate::twitch::tokens::AppToken;
+use crate::twitch::{eventsub, helix, webhook, IRC_CLIENT};
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, info, debug};
+
+//std
+use std::collections::HashSet;
+
+//twitch_api
+use twitch_api::eventsub::Transport;
+use twitch_api::types::UserName;
+
+///Longest login Twitch allows.
+const NAME_LIMIT: usize = 25;
+
+///Whether `name` could be a Twitch login.
+pub fn valid(name: &str) -> bool {
+    (3..=NAME_LIMIT).contains(&name.len())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+///`configured` in order followed by the channels joined at runtime, minus any that were parted.
+pub fn merge(configured: &[String], stored: &[TwitchChannel]) -> Vec<String> {
+    let parted: HashSet<&str> =
+        stored.iter().filter(|c| !c.joined).map(|c| c.name.as_str()).collect();
+    let mut channels: Vec<String> = vec![];
+    let joined = stored.iter().filter(|c| c.joined).map(|c| &c.name);
+    for channel in configured.iter().chain(joined).map(|c| c.to_lowercase()) {
+        if !parted.contains(channel.as_str()) && !channels.contains(&channel) {
+            channels.push(channel);
+        }
+    }
+    channels
+}
+
+///Every channel the bot should be in, only the configured ones if the database can't be reached.
+pub async fn current() -> Vec<String> {
+    let stored = db::blocking(db::find_twitch_channels).await.unwrap_or_else(|e| {
+        error!("Unable to load joined channels, using the configured ones: {e:?}");
+        vec![]
+    });
+    merge(&CONFIG.twitch_channels, &stored)
+}
+
+///Whether `channel` comes from `[twitch] channels` rather than `!join`.
+pub fn configured(channel: &str) -> bool {
+    CONFIG.twitch_channels.iter().any(|c| c.eq_ignore_ascii_case(channel))
+}
+
+fn normalize(channel: &str) -> String {
+    channel.trim().trim_start_matches(['#', '@']).to_lowercase()
+}
+
+async fn save(channel: &str, joined: bool, by: &str) -> eyre::Result<()> {
+    let row = NewTwitchChannel { name: channel.to_string(), joined, updated_by: by.to_string() };
+    db::blocking(move || db::save_twitch_channel(&row)).await
+}
+
+///Joins `channel` for good on behalf of `by`, returning what to tell them.
+pub async fn join(channel: &str, by: &str) -> eyre::Result<String> {
+    let channel = normalize(channel);
+    if !valid(&channel) {
+        return Ok(format!("{channel} isn't a Twitch channel"));
+    }
+    if current().await.contains(&channel) {
+        return Ok(format!("Already in #{channel}"));
+    }
+    let client = IRC_CLIENT.get().ok_or_else(|| eyre::eyre!("chat isn't connected"))?;
+    // only channels Twitch knows about are kept
+    let helix = helix::get().ok_or_else(|| eyre::eyre!("Helix client isn't initialised yet"))?;
+    let token = helix.token().await?;
+    let login = UserName::new(channel.clone());
+    if helix.client.get_user_from_login(&login, &token).await?.is_none() {
+        return Ok(format!("{channel} isn't a Twitch channel"));
+    }
+    save(&channel, true, by).await?;
+    client.join(channel.clone()).map_err(|e| eyre::eyre!("{e}"))?;
+    info!("{by} joined #{channel}");
+    match subscribe(&channel).await {
+        Ok(()) => Ok(format!("Joined #{channel}")),
+        Err(e) => {
+            error!("[{channel}] Unable to subscribe after joining: {e:?}");
+            Ok(format!("Joined #{channel}, but its EventSub subscriptions failed"))
+        },
+    }
+}
+
+///Leaves `channel` for good on behalf of `by`, returning what to tell them.
+pub async fn part(channel: &str, by: &str) -> eyre::Result<String> {
+    let channel = normalize(channel);
+    if channel.eq_ignore_ascii_case(&CONFIG.twitch_bot_name) {
+        return Ok("The bot can't leave its own channel".to_string());
+    }
+    if !current().await.contains(&channel) {
+        return Ok(format!("Not in #{channel}"));
+    }
+    let client = IRC_CLIENT.get().ok_or_else(|| eyre::eyre!("chat isn't connected"))?;
+    save(&channel, false, by).await?;
+    client.part(channel.clone());
+    info!("{by} parted #{channel}");
+    match unsubscribe(&channel).await {
+        Ok(deleted) => Ok(format!("Left #{channel}, removed {deleted} EventSub subscription(s)")),
+        Err(e) => {
+            error!("[{channel}] Unable to unsubscribe after parting: {e:?}");
+            Ok(format!("Left #{channel}, but its EventSub subscriptions couldn't be removed"))
+        },
+    }
+}
+
+async fn subscribe(channel: &str) -> eyre::Result<()> {
+    let helix = helix::get().ok_or_else(|| eyre::eyre!("Helix client isn't initialised yet"))?;
+    match webhook::config() {
+        Some(config) => {
+            let transport = Transport::webhook(config.callback_url, config.secret);
+            let app_token = AppToken::new().await;
+            let created = webhook::subscribe_channel(
+                &helix.client,
+                &app_token,
+                channel,
+                &transport,
+                &HashSet::new(),
+            )
+            .await?;
+            Ok(debug!("[{channel}] {created} webhook subscription(s) created"))
+        },
+        None => {
+            let Some(session_id) = eventsub::session_id() else {
+                return Ok(debug!("[{channel}] no eventsub session yet, it subscribes on welcome"));
+            };
+            let token = helix.token().await?;
+            let transport = Transport::websocket(session_id);
+            let mut active = HashSet::new();
+            eventsub::subscribe_channel(&helix.client, &token, channel, &transport, &mut active)
+                .await?;
+            Ok(debug!("[{channel}] {} websocket subscription(s) created", active.len()))
+        },
+    }
+}
+
+async fn unsubscribe(channel: &str) -> eyre::Result<usize> {
+    let helix = helix::get().ok_or_else(|| eyre::eyre!("Helix client isn't initialised yet"))?;
+    let token = helix.token().await?;
+    let user = helix
+        .client
+        .get_user_from_login(&UserName::new(channel.to_string()), &token)
+        .await?
+        .ok_or_else(|| eyre::eyre!("Unable to retrieve user from: {channel}"))?;
+    let id = user.id.as_str();
+    if webhook::config().is_some() {
+        let app_token = AppToken::new().await;
+        return eventsub::unsubscribe_channel(&helix.client, &app_token, id).await;
+    }
+    // websocket subscriptions belong to whichever token created them
+    let mut deleted = eventsub::unsubscribe_channel(&helix.client, &token, id).await?;
+    if let Some(broadcaster) = super::broadcasters::token(&helix.client, channel).await? {
+        deleted += eventsub::unsubscribe_channel(&helix.client, &broadcaster, id).await?;
+    }
+    Ok(deleted)
+}
+
+///A stored channel changed by `testuser` at the epoch, for tests here and on Discord.
+#[cfg(test)]
+pub(crate) fn stored(name: &str, joined: bool) -> TwitchChannel {
+    TwitchChannel {
+        name: name.to_string(),
+        joined,
+        updated_by: "testuser".to_string(),
+        updated_at: chrono::NaiveDateTime::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_with_config() {
+        let configured = ["TestUser".to_string(), "Twitch".to_string()];
+        let stored =
+            [stored("twitch", false), stored("twitchrivals", true), stored("testuser", true)];
+        assert_eq!(merge(&configured, &stored), ["testuser", "twitchrivals"]);
+    }
+
+    #[test]
+    fn validates_names() {
+        assert!(valid("twitch_rivals"));
+        assert!(!valid("tw"));
+        assert!(!valid("not a channel"));
+        assert_eq!(normalize(" #TwitchRivals"), "twitchrivals");
+    }
+}
@@ -1,24 +1,58 @@
 use twitch_irc::login::RefreshingLoginCredentials;
-use twitch_irc::message::{Badge, IRCMessage, PrivmsgMessage, ServerMessage, WhisperMessage};
+use twitch_irc::message::{IRCMessage, PrivmsgMessage, ServerMessage, WhisperMessage};
 use twitch_irc::{transport::tcp::SecureTCPTransport, TwitchIRCClient};
 
 use super::tokens::BotTokenStorage;
 
 //command each in a module
+mod broadcast;
+pub(crate) mod custom;
+mod join;
 mod link;
+mod moderation;
 mod ping;
+mod poll;
+mod quote;
+mod shoutout;
+mod timer;
+
+//framework
+pub(crate) mod parser;
+pub(crate) mod permissions;
+pub(crate) mod registry;
+pub(crate) mod variables;
+
+use registry::{Command, Origin};
 
 // use crate::debug;
 
+///Every built in command, see [`registry::Command`] for how they are declared.
+pub(crate) static COMMANDS: &[Command] = &[
+    broadcast::GAME,
+    broadcast::TITLE,
+    custom::ADDCOM,
+    custom::DELCOM,
+    custom::EDITCOM,
+    custom::LIST,
+    join::JOIN,
+    join::PART,
+    link::COMMAND,
+    moderation::BAN,
+    moderation::PERMIT,
+    moderation::PURGE,
+    moderation::TIMEOUT,
+    moderation::UNBAN,
+    ping::COMMAND,
+    poll::POLL,
+    poll::PREDICT,
+    quote::COMMAND,
+    shoutout::COMMAND,
+    timer::COMMAND,
+];
+
+#[allow(unused)]
 pub fn has_mod_rights(message: PrivmsgMessage) -> bool {
-    if message.badges.contains(&Badge { name: "moderator".to_string(), version: "1".to_string() })
-        || message
-            .badges
-            .contains(&Badge { name: "broadcaster".to_string(), version: "1".to_string() })
-    {
-        return true;
-    }
-    false
+    permissions::badge_level(&message.badges) >= permissions::Level::Moderator
 }
 
 pub fn has_bot_admin_rights(user_login: String, config: &crate::Config) -> bool {
@@ -35,35 +69,22 @@ pub async fn parse_command(
         // pseudo-default case
         ServerMessage::Privmsg { .. } => {
             let m = PrivmsgMessage::try_from(Into::<IRCMessage>::into(message.clone())).unwrap();
-            #[allow(clippy::collapsible_if)]
-            if has_mod_rights(m.to_owned())
-                | has_bot_admin_rights(m.to_owned().sender.login, &crate::CONFIG)
+            let prefix = registry::prefix_for(Some(&m.channel_login));
+            if !registry::dispatch(COMMANDS, Origin::Chat(m.clone()), prefix, irc_client.clone())
+                .await
             {
-                if m.message_text.starts_with("!ping") {
-                    tokio::spawn(async move { ping::handle(m, irc_client).await });
-                }
-                /*else if m.message_text.starts_with("!") {
-                    tokio::spawn(async move { ::handle(m, irc_client).await });
-                }*/
-                /* else if m.message_text.starts_with("!") {
-                    tokio::spawn(async move { ::handle(m, irc_client).await });
-                }*/
-            };
+                custom::run(&m, prefix, irc_client).await;
+            }
         },
         ServerMessage::Whisper { .. } => {
             let m = WhisperMessage::try_from(Into::<IRCMessage>::into(message.clone())).unwrap();
             // debug!("{:?}", &m);
-            if has_bot_admin_rights(m.to_owned().sender.login, &crate::CONFIG) {
-                #[allow(clippy::suspicious_else_formatting)]
-                if m.message_text.starts_with("!link") {
-                    tokio::spawn(async move { link::handle(m, irc_client).await });
-                }
-                /* else if m.message_text.starts_with("!") {
-                    tokio::spawn(async move { ::handle(m, irc_client).await });
-                }*/
-                else {
-                    super::parse_message("debug", format!("{:?}", message));
-                }
+            let admin = has_bot_admin_rights(m.to_owned().sender.login, &crate::CONFIG);
+            let prefix = registry::prefix_for(None);
+            // only bot admins' whispers are logged, everyone else's stay private
+            if !registry::dispatch(COMMANDS, Origin::Whisper(m), prefix, irc_client).await && admin
+            {
+                super::parse_message("debug", format!("{:?}", message));
             }
         },
         // All other cases are bunk
@@ -0,0 +1,70 @@
+//!`!title` and `!game` show or change the bot channel's title and category, see
+//![`broadcast`](crate::twitch::broadcast).
+
+//crate
+use super::parser::{Arg, ArgKind};
+use super::permissions::Level;
+use super::registry::{Availability, Command, Invocation};
+use crate::cooldown::Cooldown;
+use crate::twitch::broadcast;
+
+//futures
+use futures::future::BoxFuture;
+
+pub(super) const TITLE: Command = Command {
+    name: "title",
+    aliases: &[],
+    args: &[Arg::optional("title", ArgKind::Rest)],
+    availability: Availability::Chat,
+    level: Level::Moderator,
+    cooldown: Cooldown::NONE,
+    handler: title,
+};
+
+pub(super) const GAME: Command = Command {
+    name: "game",
+    aliases: &["category"],
+    args: &[Arg::optional("name", ArgKind::Rest)],
+    availability: Availability::Chat,
+    level: Level::Moderator,
+    cooldown: Cooldown::NONE,
+    handler: game,
+};
+
+///Why the command did nothing outside `channel`.
+fn elsewhere(channel: &str) -> String {
+    format!("Only #{channel}'s title and category can be changed")
+}
+
+fn title(invocation: Invocation) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        if invocation.outside_bot_channel(elsewhere).await? {
+            return Ok(());
+        }
+        let moderator = invocation.origin.sender_login();
+        let result = broadcast::title(invocation.args.str("title"), "twitch", moderator).await;
+        invocation.reply_result(result).await
+    })
+}
+
+fn game(invocation: Invocation) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        if invocation.outside_bot_channel(elsewhere).await? {
+            return Ok(());
+        }
+        let moderator = invocation.origin.sender_login();
+        let result = broadcast::game(invocation.args.str("name"), "twitch", moderator).await;
+        invocation.reply_result(result).await
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_lines() {
+        assert_eq!(TITLE.usage("!"), "!title [title...]");
+        assert_eq!(GAME.usage("!"), "!game [name...]");
+    }
+}
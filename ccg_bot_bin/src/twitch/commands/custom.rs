@@ -0,0 +1,402 @@
+//!Per channel text commands stored in the database.
+//!
+//!Moderators manage them with `!addcom`, `!editcom` and `!delcom`, and `!commands` lists what the
+//!caller can run. Options go before the response, `-ul=<level>` sets the permission level and
+//!`-cd=<seconds>` the cooldown, e.g. `!addcom hello -ul=subscriber -cd=30 Hi $(touser)!`.
+//![`run`] is the fallback used by [`parse_command`](super::parse_command) when no built in
+//!command matches.
+
+//crate
+use super::parser::{self, Arg, ArgKind};
+use super::permissions::{self, Level};
+use super::registry::{self, Availability, Command, Invocation, Origin};
+use super::variables;
+use crate::cooldown::{self, Cooldown};
+use crate::db::{self, models::CustomCommandChanges, models::NewCustomCommand};
+use crate::discord::bridge::{truncate, TWITCH_MESSAGE_LIMIT};
+use crate::twitch::{golive::format_duration, helix, TwitchClient};
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, warn, debug};
+
+//chrono
+use chrono::{DateTime, Utc};
+
+//futures
+use futures::future::BoxFuture;
+
+//nom
+use nom::{
+    bytes::complete::take_while1,
+    character::complete::{alpha1, char, multispace0},
+    sequence::{preceded, separated_pair},
+    IResult,
+};
+
+//twitch_api
+use twitch_api::{helix::streams::GetStreamsRequest, types::UserId};
+
+//twitch_irc
+use twitch_irc::message::PrivmsgMessage;
+
+///Cooldown given to new commands when `-cd` isn't set.
+const DEFAULT_COOLDOWN: u32 = 5;
+///Longest name the `custom_commands` table can hold.
+const NAME_LIMIT: usize = 25;
+
+pub(super) const ADDCOM: Command = Command {
+    name: "addcom",
+    aliases: &[],
+    args: &[Arg::new("name", ArgKind::Word), Arg::new("response", ArgKind::Rest)],
+    availability: Availability::Chat,
+    level: Level::Moderator,
+    cooldown: Cooldown::NONE,
+    handler: addcom,
+};
+
+pub(super) const EDITCOM: Command = Command {
+    name: "editcom",
+    aliases: &[],
+    args: &[Arg::new("name", ArgKind::Word), Arg::new("response", ArgKind::Rest)],
+    availability: Availability::Chat,
+    level: Level::Moderator,
+    cooldown: Cooldown::NONE,
+    handler: editcom,
+};
+
+pub(super) const DELCOM: Command = Command {
+    name: "delcom",
+    aliases: &[],
+    args: &[Arg::new("name", ArgKind::Word)],
+    availability: Availability::Chat,
+    level: Level::Moderator,
+    cooldown: Cooldown::NONE,
+    handler: delcom,
+};
+
+pub(super) const LIST: Command = Command {
+    name: "commands",
+    aliases: &["cmds"],
+    args: &[],
+    availability: Availability::Chat,
+    level: Level::Everyone,
+    cooldown: Cooldown::secs(10, 30),
+    handler: commands,
+};
+
+///Options given before a response.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Options {
+    pub level: Option<Level>,
+    pub cooldown: Option<u32>,
+}
+
+fn option(input: &str) -> IResult<&str, (&str, &str)> {
+    preceded(
+        multispace0,
+        preceded(
+            char('-'),
+            separated_pair(alpha1, char('='), take_while1(|c: char| !c.is_whitespace())),
+        ),
+    )(input)
+}
+
+///Splits leading `-ul=` and `-cd=` options from the response that follows them.
+pub fn options(input: &str) -> Result<(Options, &str), String> {
+    let mut options = Options::default();
+    let mut rest = input;
+    while let Ok((next, (key, value))) = option(rest) {
+        match key.to_lowercase().as_str() {
+            "ul" | "level" => options.level = Some(value.parse()?),
+            "cd" | "cooldown" => {
+                options.cooldown = Some(
+                    value.parse().map_err(|_| format!("`{value}` isn't a number of seconds"))?,
+                )
+            },
+            other => return Err(format!("unknown option `-{other}`, use -ul or -cd")),
+        }
+        rest = next;
+    }
+    Ok((options, rest.trim()))
+}
+
+///Lowercases `name` and drops the channel's prefix if it was typed out.
+fn command_name(channel: &str, name: &str) -> String {
+    let prefix = registry::prefix_for(Some(channel));
+    name.strip_prefix(prefix).unwrap_or(name).to_lowercase()
+}
+
+fn addcom(invocation: Invocation) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        let Some(channel) = invocation.origin.channel().map(str::to_string) else {
+            return Ok(());
+        };
+        let name = command_name(&channel, invocation.args.str("name").unwrap_or_default());
+        if name.chars().count() > NAME_LIMIT {
+            return invocation.reply(format!("Names can be at most {NAME_LIMIT} characters")).await;
+        }
+        let prefix = registry::prefix_for(Some(&channel));
+        if registry::find(super::COMMANDS, &name).is_some() {
+            return invocation.reply(format!("{prefix}{name} is a built in command")).await;
+        }
+        let (options, response) = match options(invocation.args.str("response").unwrap_or_default())
+        {
+            Ok(parsed) => parsed,
+            Err(e) => return invocation.reply(e).await,
+        };
+        if response.is_empty() {
+            return invocation.reply(format!("[Usage] {}", ADDCOM.usage(prefix))).await;
+        }
+        let command = NewCustomCommand {
+            channel: channel.clone(),
+            name: name.clone(),
+            response: response.to_string(),
+            level: options.level.unwrap_or(Level::Everyone).to_string(),
+            cooldown: options.cooldown.unwrap_or(DEFAULT_COOLDOWN),
+        };
+        let added = db::blocking(move || {
+            if db::find_custom_command(&command.channel, &command.name)?.is_some() {
+                return Ok(false);
+            }
+            db::create_custom_command(&command).map(|_| true)
+        })
+        .await?;
+        match added {
+            true => invocation.reply(format!("Added {prefix}{name}")).await,
+            false => {
+                let reply =
+                    format!("{prefix}{name} already exists, use {prefix}editcom to change it");
+                invocation.reply(reply).await
+            },
+        }
+    })
+}
+
+fn editcom(invocation: Invocation) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        let Some(channel) = invocation.origin.channel().map(str::to_string) else {
+            return Ok(());
+        };
+        let name = command_name(&channel, invocation.args.str("name").unwrap_or_default());
+        let prefix = registry::prefix_for(Some(&channel));
+        let (options, response) = match options(invocation.args.str("response").unwrap_or_default())
+        {
+            Ok(parsed) => parsed,
+            Err(e) => return invocation.reply(e).await,
+        };
+        let changes = CustomCommandChanges {
+            response: Some(response.to_string()).filter(|r| !r.is_empty()),
+            level: options.level.map(|l| l.to_string()),
+            cooldown: options.cooldown,
+        };
+        let (chan, command) = (channel.clone(), name.clone());
+        let updated =
+            db::blocking(move || match db::update_custom_command(&chan, &command, &changes)? {
+                0 => db::find_custom_command(&chan, &command).map(|c| c.map(|_| false)),
+                _ => Ok(Some(true)),
+            })
+            .await?;
+        let reply = match updated {
+            Some(true) => format!("Updated {prefix}{name}"),
+            Some(false) => format!("{prefix}{name} is unchanged"),
+            None => format!("{prefix}{name} doesn't exist, use {prefix}addcom to create it"),
+        };
+        invocation.reply(reply).await
+    })
+}
+
+fn delcom(invocation: Invocation) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        let Some(channel) = invocation.origin.channel().map(str::to_string) else {
+            return Ok(());
+        };
+        let name = command_name(&channel, invocation.args.str("name").unwrap_or_default());
+        let prefix = registry::prefix_for(Some(&channel));
+        let (chan, command) = (channel.clone(), name.clone());
+        let reply = match db::blocking(move || db::delete_custom_command(&chan, &command)).await? {
+            0 => format!("{prefix}{name} doesn't exist"),
+            _ => format!("Removed {prefix}{name}"),
+        };
+        invocation.reply(reply).await
+    })
+}
+
+fn commands(invocation: Invocation) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        let Some(channel) = invocation.origin.channel().map(str::to_string) else {
+            return Ok(());
+        };
+        let prefix = registry::prefix_for(Some(&channel));
+        let chan = channel.clone();
+        let custom = db::blocking(move || db::find_custom_commands(&chan)).await?;
+        let mut commands: Vec<(String, Level)> = super::COMMANDS
+            .iter()
+            .filter(|c| c.availability != Availability::Whisper)
+            .map(|c| (c.name.to_string(), permissions::required(c, Some(&channel))))
+            .collect();
+        commands.extend(custom.into_iter().map(|c| (c.name, level(&c.level))));
+        // one look at the caller's level, Helix only gets asked if a command needs followers
+        let needs_follow = commands.iter().any(|(_, l)| *l == Level::Follower);
+        let caller = permissions::level(&invocation.origin, needs_follow).await;
+        let mut names: Vec<String> =
+            commands.into_iter().filter(|(_, l)| *l <= caller).map(|(n, _)| n).collect();
+        names.sort();
+        let list = names.iter().map(|n| format!("{prefix}{n}")).collect::<Vec<_>>().join(", ");
+        invocation.reply(truncate(&format!("Commands: {list}"), TWITCH_MESSAGE_LIMIT)).await
+    })
+}
+
+fn level(level: &str) -> Level {
+    level.parse().unwrap_or_else(|e| {
+        warn!("{e}, treating custom command as moderator only");
+        Level::Moderator
+    })
+}
+
+///Fills in the `$(user)`, `$(touser)`, `$(count)` and `$(channel)` of a custom command's
+///`response`, and `$(uptime)` when it is given. `$(touser)` is the first word of `args` or `user`.
+pub fn response(
+    response: &str,
+    user: &str,
+    args: &str,
+    uses: u32,
+    channel: &str,
+    uptime: Option<&str>,
+) -> String {
+    let touser = args
+        .split_whitespace()
+        .next()
+        .map(|u| u.trim_start_matches('@').to_string())
+        .unwrap_or_else(|| user.to_string());
+    variables::expand(response, |variable| match variable {
+        "user" => Some(user.to_string()),
+        "touser" => Some(touser.clone()),
+        "count" => Some(uses.to_string()),
+        "channel" => Some(channel.to_string()),
+        "uptime" => uptime.map(str::to_string),
+        _ => None,
+    })
+}
+
+async fn uptime(message: &PrivmsgMessage) -> String {
+    let Some(helix) = helix::get() else {
+        return "unknown".to_string();
+    };
+    let stream = async {
+        let token = helix.token().await?;
+        let ids = [UserId::from(message.channel_id.clone())];
+        let request = GetStreamsRequest::user_ids(&ids[..]);
+        Ok::<_, eyre::Report>(helix.client.req_get(request, &token).await?.data.into_iter().next())
+    };
+    match stream.await {
+        Ok(Some(stream)) => DateTime::parse_from_rfc3339(stream.started_at.as_str())
+            .map(|started| format_duration(Utc::now() - started.with_timezone(&Utc)))
+            .unwrap_or_else(|_| "unknown".to_string()),
+        Ok(None) => "offline".to_string(),
+        Err(e) => {
+            debug!("Unable to look up uptime for #{}: {e}", message.channel_login);
+            "unknown".to_string()
+        },
+    }
+}
+
+///Runs the custom command in `message` if there is one, returning whether it was handled.
+pub async fn run(message: &PrivmsgMessage, prefix: &str, client: TwitchClient) -> bool {
+    let Some((name, rest)) = parser::invocation(prefix, &message.message_text) else {
+        return false;
+    };
+    let channel = &message.channel_login;
+    let (chan, lookup) = (channel.clone(), name.to_lowercase());
+    let command = match db::blocking(move || db::find_custom_command(&chan, &lookup)).await {
+        Ok(Some(command)) => command,
+        Ok(None) => return false,
+        Err(e) => {
+            error!("Unable to look up custom command {prefix}{name}: {e:?}");
+            return false;
+        },
+    };
+    let origin = Origin::Chat(message.clone());
+    if !permissions::allowed(&origin, level(&command.level)).await {
+        return true;
+    }
+    // moderators aren't held back by cooldowns, the stored cooldown is shared by everyone
+    if !permissions::allowed(&origin, Level::Moderator).await {
+        let key = format!("twitch:{channel}:{}", command.name);
+        let cooldown = Cooldown::secs(command.cooldown.into(), 0);
+        if let Err(remaining) = cooldown::check(&key, &message.sender.login, cooldown) {
+            debug!("{prefix}{} is on cooldown in #{channel}", command.name);
+            if CONFIG.command_cooldown_reply {
+                let invocation = Invocation { origin, args: Default::default(), client };
+                let response = registry::cooldown_notice(prefix, &command.name, remaining);
+                if let Err(e) = invocation.reply(response).await {
+                    error!("Unable to send cooldown for {prefix}{}: {e:?}", command.name);
+                }
+            }
+            return true;
+        }
+    }
+    let id = command.id;
+    let uses = match db::blocking(move || db::increment_custom_command_uses(id)).await {
+        Ok(uses) => uses,
+        Err(e) => {
+            error!("Unable to count use of {prefix}{}: {e:?}", command.name);
+            command.uses
+        },
+    };
+    let stream_uptime = if variables::names(&command.response).contains(&"uptime") {
+        uptime(message).await
+    } else {
+        String::new()
+    };
+    let response = response(
+        &command.response,
+        &message.sender.name,
+        rest,
+        uses,
+        channel,
+        Some(&stream_uptime),
+    );
+    #[cfg(not(test))]
+    if let Err(e) = client.say(channel.clone(), truncate(&response, TWITCH_MESSAGE_LIMIT)).await {
+        error!("Unable to send {prefix}{}: {e}", command.name);
+    }
+    #[cfg(test)]
+    let _ = (client, response);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_options() {
+        let (options, response) = options("-ul=vip -cd=30 Hi $(touser)").unwrap();
+        assert_eq!(options, Options { level: Some(Level::Vip), cooldown: Some(30) });
+        assert_eq!(response, "Hi $(touser)");
+        assert_eq!(super::options("no options").unwrap(), (Options::default(), "no options"));
+        // a dash that isn't an option is part of the response
+        assert_eq!(super::options("-_- whatever").unwrap().1, "-_- whatever");
+    }
+
+    #[test]
+    fn fills_in_responses() {
+        let template = "$(user) hugs $(touser) in $(channel) #$(count) $(uptime)";
+        assert_eq!(
+            response(template, "TestUser", "@Friend and more", 3, "testchannel", None),
+            "TestUser hugs Friend in testchannel #3 $(uptime)"
+        );
+        assert_eq!(
+            response(template, "TestUser", "", 4, "testchannel", Some("1h")),
+            "TestUser hugs TestUser in testchannel #4 1h"
+        );
+    }
+
+    #[test]
+    fn rejects_bad_options() {
+        assert!(options("-ul=owner hi").is_err());
+        assert!(options("-cd=soon hi").is_err());
+        assert!(options("-xx=1 hi").is_err());
+    }
+}
@@ -0,0 +1,58 @@
+//!`!join <channel>` and `!part <channel>` whispers, see [`channels`](crate::twitch::channels).
+
+//crate
+use super::parser::{Arg, ArgKind};
+use super::permissions::Level;
+use super::registry::{Availability, Command, Invocation};
+use crate::cooldown::Cooldown;
+use crate::twitch::channels;
+
+//futures
+use futures::future::BoxFuture;
+
+pub(super) const JOIN: Command = Command {
+    name: "join",
+    aliases: &[],
+    args: &[Arg::new("channel", ArgKind::Word)],
+    availability: Availability::Whisper,
+    level: Level::BotAdmin,
+    cooldown: Cooldown::NONE,
+    handler: join,
+};
+
+pub(super) const PART: Command = Command {
+    name: "part",
+    aliases: &["leave"],
+    args: &[Arg::new("channel", ArgKind::Word)],
+    availability: Availability::Whisper,
+    level: Level::BotAdmin,
+    cooldown: Cooldown::NONE,
+    handler: part,
+};
+
+fn join(invocation: Invocation) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        let channel = invocation.args.str("channel").unwrap_or_default();
+        let reply = channels::join(channel, invocation.origin.sender_login()).await?;
+        invocation.reply(reply).await
+    })
+}
+
+fn part(invocation: Invocation) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        let channel = invocation.args.str("channel").unwrap_or_default();
+        let reply = channels::part(channel, invocation.origin.sender_login()).await?;
+        invocation.reply(reply).await
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_lines() {
+        assert_eq!(JOIN.usage("!"), "!join <channel>");
+        assert_eq!(PART.usage("!"), "!part <channel>");
+    }
+}
@@ -1,88 +1,71 @@
 //discord-twitch link
 
 #[rustfmt::skip]
-use crate::{error, debug};
+use crate::debug;
 
-use twitch_irc::{
-    login::RefreshingLoginCredentials,
-    message::{IRCMessage, WhisperMessage},
-    transport::tcp::SecureTCPTransport,
-    Error, TwitchIRCClient,
-};
+use twitch_irc::message::WhisperMessage;
 
-use super::BotTokenStorage;
+use super::parser::{Arg, ArgKind};
+use super::permissions::Level;
+use super::registry::{Availability, Command, Invocation, Origin};
+use crate::cooldown::Cooldown;
+use crate::twitch::{reply, TwitchClient};
 
-async fn send_message(
-    client: TwitchIRCClient<SecureTCPTransport, RefreshingLoginCredentials<BotTokenStorage>>,
-    message: IRCMessage,
-) -> Result<(), Error<SecureTCPTransport, RefreshingLoginCredentials<BotTokenStorage>>> {
-    #[cfg(test)]
-    {
-        let _ = client;
-        let _ = message;
-    }
-    #[cfg(test)]
-    let res = Ok(());
-    #[cfg(not(test))]
-    let res = client.send_message(message).await;
-    res
+//futures
+use futures::future::BoxFuture;
+
+// both are words as the order of the two is worked out in `handle`
+pub(super) const COMMAND: Command = Command {
+    name: "link",
+    aliases: &[],
+    args: &[Arg::new("twitch user", ArgKind::Word), Arg::new("discord id", ArgKind::Word)],
+    availability: Availability::Whisper,
+    level: Level::BotAdmin,
+    cooldown: Cooldown::NONE,
+    handler: run,
+};
+
+fn run(invocation: Invocation) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        match invocation.origin {
+            Origin::Whisper(message) => handle(message, invocation.client).await,
+            Origin::Chat(_) => Ok(()),
+        }
+    })
 }
 
-pub async fn handle(
-    message: WhisperMessage,
-    client: TwitchIRCClient<SecureTCPTransport, RefreshingLoginCredentials<BotTokenStorage>>,
-) -> Result<(), Error<SecureTCPTransport, RefreshingLoginCredentials<BotTokenStorage>>> {
-    #[cfg(test)]
-    let _ = client;
-    // It's unfortunate that the call to the say_in_reply_to function on client can't be tested directly
-    let bot_name = crate::CONFIG.clone().twitch_bot_name;
-    let bot_id = match std::env::var("TWITCH_USER_ID") {
-        Ok(i) => i,
-        Err(_) => {
-            error!("TWITCH_USER_ID not found in env defaulting to blank");
-            String::from("")
-        },
-    };
+pub async fn handle(message: WhisperMessage, client: TwitchClient) -> eyre::Result<()> {
     let reply_to = message.sender;
+    let whisper = |text: &str| reply::whisper(&client, &reply_to.id, &reply_to.login, text);
     let content = message.message_text;
     let parsed_content: Vec<String> = content.split_whitespace().map(|s| s.to_string()).collect();
-    let (first, second): (String, String);
-    let res: Result<(), Error<SecureTCPTransport, RefreshingLoginCredentials<BotTokenStorage>>>;
-    if parsed_content.len() == 3 {
-        (first, second) =
-            (parsed_content.get(1).unwrap().clone(), parsed_content.get(2).unwrap().clone());
-        let (twitch_un, discord_id): (String, String);
-        if second.parse::<u64>().is_ok() {
-            twitch_un = first;
-            discord_id = second;
-        } else {
-            twitch_un = second;
-            discord_id = first;
-            let raw_interim_response = format!("@badges=;color=#AA66FF;display-name={};emotes=;message-id=1;thread-id={}_{};turbo=1;user-id=12345678;user-type= :{}!{}@{}.tmi.twitch.tv WHISPER {} :Assuming inverted params", reply_to.login, bot_id, reply_to.id, bot_name, bot_name, bot_name, reply_to.name);
-            let interim_response = IRCMessage::parse(&raw_interim_response);
-            send_message(client.clone(), interim_response.unwrap()).await?;
-        }
-        if !twitch_un.is_empty() && !discord_id.is_empty() {
-            //TODO: Actually link these accounts in someway for now do this
-            debug!("twitch_un={twitch_un:?} discord_id={discord_id:?}");
-        } else {
-            let (_, _) = (twitch_un, discord_id);
-        }
-        let raw_response = format!("@badges=;color=#AA66FF;display-name={};emotes=;message-id=1;thread-id={}_{};turbo=1;user-id=12345678;user-type= :{}!{}@{}.tmi.twitch.tv WHISPER {} :Understood", reply_to.login, bot_id, reply_to.id, bot_name, bot_name, bot_name, reply_to.name);
-        let response = IRCMessage::parse(&raw_response);
-        res = send_message(client, response.unwrap()).await;
+    if parsed_content.len() != 3 {
+        return whisper("[Usage] !link <twitch @> <discord id number>");
+    }
+    let (first, second) = (parsed_content[1].clone(), parsed_content[2].clone());
+    let (twitch_un, discord_id): (String, String);
+    if second.parse::<u64>().is_ok() {
+        twitch_un = first;
+        discord_id = second;
     } else {
-        let raw_response = format!("@badges=;color=#AA66FF;display-name={};emotes=;message-id=1;thread-id={}_{};turbo=1;user-id=12345678;user-type= :{}!{}@{}.tmi.twitch.tv WHISPER {} :[Usage] !link <twitch @> <discord id number>", reply_to.login, bot_id, reply_to.id, bot_name, bot_name, bot_name, reply_to.name);
-        let response = IRCMessage::parse(&raw_response);
-        res = send_message(client, response.unwrap()).await;
+        twitch_un = second;
+        discord_id = first;
+        whisper("Assuming inverted params")?;
+    }
+    if !twitch_un.is_empty() && !discord_id.is_empty() {
+        //TODO: Actually link these accounts in someway for now do this
+        debug!("twitch_un={twitch_un:?} discord_id={discord_id:?}");
     }
-    res
+    whisper("Understood")
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use twitch_irc::ClientConfig;
+    use crate::twitch::tokens::BotTokenStorage;
+    use twitch_irc::login::RefreshingLoginCredentials;
+    use twitch_irc::message::IRCMessage;
+    use twitch_irc::{ClientConfig, TwitchIRCClient};
 
     #[tokio::test]
     async fn command_handle() {
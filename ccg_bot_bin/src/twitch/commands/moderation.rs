@@ -0,0 +1,242 @@
+//!`!timeout`, `!ban`, `!unban` and `!purge` through the Helix moderation endpoints, and `!permit`
+//!for the [link filter](crate::twitch::filters).
+//!
+//!The bot acts as itself, so it has to be a moderator in the channel. Every action is confirmed in
+//!chat and recorded in the [audit log](crate::audit). Twitch has no purge of its own, `!purge`
+//!times the user out for a second which clears their recent messages.
+
+//crate
+use super::parser::{self, Arg, ArgKind};
+use super::permissions::Level;
+use super::registry::{Availability, Command, Invocation, Origin};
+use crate::audit;
+use crate::cooldown::Cooldown;
+use crate::db::models::NewAuditEntry;
+use crate::twitch::{filters, helix};
+
+//futures
+use futures::future::BoxFuture;
+
+//std
+use std::fmt;
+use std::time::Duration;
+
+//twitch_api
+use twitch_api::types::UserId;
+
+///The longest timeout Twitch allows.
+const MAX_TIMEOUT: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+pub(super) const TIMEOUT: Command = Command {
+    name: "timeout",
+    aliases: &["to"],
+    args: &[
+        Arg::new("user", ArgKind::User),
+        Arg::new("duration", ArgKind::Duration),
+        Arg::optional("reason", ArgKind::Rest),
+    ],
+    availability: Availability::Chat,
+    level: Level::Moderator,
+    cooldown: Cooldown::NONE,
+    handler: timeout,
+};
+
+pub(super) const BAN: Command = Command {
+    name: "ban",
+    aliases: &[],
+    args: &[Arg::new("user", ArgKind::User), Arg::optional("reason", ArgKind::Rest)],
+    availability: Availability::Chat,
+    level: Level::Moderator,
+    cooldown: Cooldown::NONE,
+    handler: ban,
+};
+
+pub(super) const UNBAN: Command = Command {
+    name: "unban",
+    aliases: &["untimeout"],
+    args: &[Arg::new("user", ArgKind::User)],
+    availability: Availability::Chat,
+    level: Level::Moderator,
+    cooldown: Cooldown::NONE,
+    handler: unban,
+};
+
+pub(super) const PURGE: Command = Command {
+    name: "purge",
+    aliases: &[],
+    args: &[Arg::new("user", ArgKind::User)],
+    availability: Availability::Chat,
+    level: Level::Moderator,
+    cooldown: Cooldown::NONE,
+    handler: purge,
+};
+
+pub(super) const PERMIT: Command = Command {
+    name: "permit",
+    aliases: &[],
+    args: &[Arg::new("user", ArgKind::User)],
+    availability: Availability::Chat,
+    level: Level::Moderator,
+    cooldown: Cooldown::NONE,
+    handler: permit,
+};
+
+///A moderation action, displayed as it is stored in the audit log.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Action {
+    Timeout(Duration),
+    Ban,
+    Unban,
+    Purge,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::Timeout(_) => write!(f, "timeout"),
+            Action::Ban => write!(f, "ban"),
+            Action::Unban => write!(f, "unban"),
+            Action::Purge => write!(f, "purge"),
+        }
+    }
+}
+
+impl Action {
+    ///The confirmation sent to chat once the action succeeded.
+    pub fn confirmation(&self, user: &str, reason: Option<&str>) -> String {
+        let done = match self {
+            Action::Timeout(d) => format!("Timed out {user} for {}", parser::format_duration(*d)),
+            Action::Ban => format!("Banned {user}"),
+            Action::Unban => format!("Unbanned {user}"),
+            Action::Purge => format!("Purged {user}'s messages"),
+        };
+        match reason {
+            Some(reason) => format!("{done} ({reason})"),
+            None => done,
+        }
+    }
+}
+
+fn timeout(invocation: Invocation) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        let duration = invocation.args.duration("duration").unwrap_or_default();
+        if duration.is_zero() || duration > MAX_TIMEOUT {
+            let max = parser::format_duration(MAX_TIMEOUT);
+            return invocation.reply(format!("Timeouts must be between 1s and {max}")).await;
+        }
+        moderate(invocation, Action::Timeout(duration)).await
+    })
+}
+
+fn ban(invocation: Invocation) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(moderate(invocation, Action::Ban))
+}
+
+fn unban(invocation: Invocation) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(moderate(invocation, Action::Unban))
+}
+
+fn purge(invocation: Invocation) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(moderate(invocation, Action::Purge))
+}
+
+fn permit(invocation: Invocation) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        let Some(channel) = invocation.origin.channel() else {
+            return Ok(());
+        };
+        let login = invocation.args.str("user").unwrap_or_default();
+        filters::permit(channel, login);
+        invocation.reply(format!("{login} may post one link")).await
+    })
+}
+
+async fn moderate(invocation: Invocation, action: Action) -> eyre::Result<()> {
+    let Origin::Chat(message) = &invocation.origin else {
+        return Ok(());
+    };
+    let login = invocation.args.str("user").unwrap_or_default();
+    let reason = invocation.args.str("reason");
+    let helix = helix::get().ok_or_else(|| eyre::eyre!("Helix client isn't initialised yet"))?;
+    let token = helix.token().await?;
+    let client = &helix.client;
+    let Some(target) = client.get_user_from_login(login, &token).await? else {
+        return invocation.reply(format!("There is no user called {login}")).await;
+    };
+    let broadcaster = UserId::from(message.channel_id.clone());
+    let moderator = &token.uid;
+    let result = match action {
+        Action::Timeout(duration) => {
+            let secs = u32::try_from(duration.as_secs()).unwrap_or(u32::MAX);
+            client
+                .ban_user(
+                    &target.id,
+                    reason.unwrap_or_default(),
+                    secs,
+                    &broadcaster,
+                    moderator,
+                    &token,
+                )
+                .await
+                .map(|_| ())
+        },
+        Action::Ban => client
+            .ban_user(
+                &target.id,
+                reason.unwrap_or_default(),
+                None::<u32>,
+                &broadcaster,
+                moderator,
+                &token,
+            )
+            .await
+            .map(|_| ()),
+        Action::Unban => {
+            client.unban_user(&target.id, &broadcaster, moderator, &token).await.map(|_| ())
+        },
+        Action::Purge => client
+            .ban_user(&target.id, "purge", 1, &broadcaster, moderator, &token)
+            .await
+            .map(|_| ()),
+    };
+    if let Err(e) = result {
+        invocation.reply(format!("Unable to {action} {}", target.display_name)).await?;
+        return Err(eyre::eyre!("{action} of {login} in #{} failed: {e}", message.channel_login));
+    }
+    audit::record(NewAuditEntry {
+        platform: "twitch".to_string(),
+        channel: message.channel_login.clone(),
+        moderator: message.sender.login.clone(),
+        action: action.to_string(),
+        target: target.login.to_string(),
+        reason: reason.map(str::to_string),
+        duration_seconds: match action {
+            Action::Timeout(d) => u32::try_from(d.as_secs()).ok(),
+            _ => None,
+        },
+    });
+    invocation.reply(action.confirmation(target.display_name.as_str(), reason)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confirms_actions() {
+        let timeout = Action::Timeout(Duration::from_secs(5400));
+        assert_eq!(timeout.to_string(), "timeout");
+        assert_eq!(
+            timeout.confirmation("TestUser", Some("spam")),
+            "Timed out TestUser for 1h30m (spam)"
+        );
+        assert_eq!(Action::Ban.confirmation("TestUser", None), "Banned TestUser");
+        assert_eq!(Action::Purge.confirmation("TestUser", None), "Purged TestUser's messages");
+    }
+
+    #[test]
+    fn usage_lines() {
+        assert_eq!(TIMEOUT.usage("!"), "!timeout <user> <duration> [reason...]");
+        assert_eq!(UNBAN.usage("!"), "!unban <user>");
+    }
+}
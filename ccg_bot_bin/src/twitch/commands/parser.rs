@@ -0,0 +1,310 @@
+//!`nom` parsers turning chat text into a command name and typed arguments.
+
+//nom
+use nom::{
+    branch::alt,
+    bytes::complete::{is_not, tag, take_while1},
+    character::complete::{char, i64 as integer, multispace0, one_of, u64 as natural},
+    combinator::{all_consuming, map, opt},
+    multi::fold_many1,
+    sequence::{delimited, pair, preceded},
+    IResult,
+};
+
+//std
+use std::fmt;
+use std::time::Duration;
+
+///The kind of value an [`Arg`] accepts.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ArgKind {
+    ///A single word, or several wrapped in double quotes.
+    Word,
+    ///A Twitch login, with or without a leading `@`.
+    User,
+    ///A whole number.
+    Number,
+    ///A length of time such as `90`, `10m` or `1h30m`, bare numbers are seconds.
+    Duration,
+    ///Everything left in the message, only valid as the last argument.
+    Rest,
+}
+
+///One argument in a command's schema.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Arg {
+    pub name: &'static str,
+    pub kind: ArgKind,
+    pub required: bool,
+}
+
+impl Arg {
+    ///A required argument.
+    pub const fn new(name: &'static str, kind: ArgKind) -> Self {
+        Self { name, kind, required: true }
+    }
+
+    ///An optional argument, only trailing arguments may be optional.
+    #[allow(unused)]
+    pub const fn optional(name: &'static str, kind: ArgKind) -> Self {
+        Self { name, kind, required: false }
+    }
+}
+
+impl fmt::Display for Arg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.required, self.kind) {
+            (true, ArgKind::Rest) => write!(f, "<{}...>", self.name),
+            (false, ArgKind::Rest) => write!(f, "[{}...]", self.name),
+            (true, _) => write!(f, "<{}>", self.name),
+            (false, _) => write!(f, "[{}]", self.name),
+        }
+    }
+}
+
+///A parsed argument.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Value {
+    Word(String),
+    ///The login in lowercase without the `@`.
+    User(String),
+    Number(i64),
+    Duration(Duration),
+    Rest(String),
+}
+
+///Arguments parsed against a command's schema, looked up by name.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Args(Vec<(&'static str, Value)>);
+
+#[allow(unused)]
+impl Args {
+    ///The value of `name`, [`None`] if it is optional and wasn't given.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.0.iter().find(|(n, _)| *n == name).map(|(_, v)| v)
+    }
+
+    ///The text of a [`Value::Word`], [`Value::User`] or [`Value::Rest`] argument.
+    pub fn str(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            Value::Word(s) | Value::User(s) | Value::Rest(s) => Some(s),
+            Value::Number(_) | Value::Duration(_) => None,
+        }
+    }
+
+    ///The value of a [`Value::Number`] argument.
+    pub fn number(&self, name: &str) -> Option<i64> {
+        match self.get(name)? {
+            Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    ///The value of a [`Value::Duration`] argument.
+    pub fn duration(&self, name: &str) -> Option<Duration> {
+        match self.get(name)? {
+            Value::Duration(d) => Some(*d),
+            _ => None,
+        }
+    }
+}
+
+///Why arguments didn't match a command's schema.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum UsageError {
+    Missing(Arg),
+    Invalid(Arg, String),
+    TooMany,
+}
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UsageError::Missing(arg) => write!(f, "missing {arg}"),
+            UsageError::Invalid(arg, given) => write!(f, "`{given}` isn't a valid {arg}"),
+            UsageError::TooMany => write!(f, "too many arguments"),
+        }
+    }
+}
+
+///Splits `input` into a command name and the unparsed arguments if it starts with `prefix`.
+pub fn invocation<'a>(prefix: &str, input: &'a str) -> Option<(&'a str, &'a str)> {
+    let res: IResult<&str, &str> =
+        preceded(tag(prefix), take_while1(|c: char| !c.is_whitespace()))(input.trim_start());
+    let (rest, name) = res.ok()?;
+    Some((name, rest.trim()))
+}
+
+fn token(input: &str) -> IResult<&str, &str> {
+    preceded(
+        multispace0,
+        alt((
+            delimited(char('"'), is_not("\""), char('"')),
+            take_while1(|c: char| !c.is_whitespace()),
+        )),
+    )(input)
+}
+
+fn user(input: &str) -> IResult<&str, &str> {
+    preceded(opt(char('@')), take_while1(|c: char| c.is_ascii_alphanumeric() || c == '_'))(input)
+}
+
+fn unit(input: &str) -> IResult<&str, u64> {
+    map(one_of("smhdw"), |unit| match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        _ => 7 * 24 * 60 * 60,
+    })(input)
+}
+
+fn duration(input: &str) -> IResult<&str, Duration> {
+    let (rest, secs) = fold_many1(
+        pair(natural, opt(unit)),
+        || 0_u64,
+        |total, (n, unit)| total.saturating_add(n.saturating_mul(unit.unwrap_or(1))),
+    )(input)?;
+    Ok((rest, Duration::from_secs(secs)))
+}
+
+fn value(arg: Arg, token: &str) -> Result<Value, UsageError> {
+    let invalid = || UsageError::Invalid(arg, token.to_string());
+    match arg.kind {
+        ArgKind::Word => Ok(Value::Word(token.to_string())),
+        ArgKind::User => all_consuming(user)(token)
+            .map(|(_, login)| Value::User(login.to_lowercase()))
+            .map_err(|_| invalid()),
+        ArgKind::Number => {
+            all_consuming(integer)(token).map(|(_, n)| Value::Number(n)).map_err(|_| invalid())
+        },
+        ArgKind::Duration => parse_duration(token).map(Value::Duration).ok_or_else(invalid),
+        ArgKind::Rest => unreachable!("rest arguments aren't tokenised"),
+    }
+}
+
+///Parses `input` against `schema`.
+pub fn args(schema: &[Arg], input: &str) -> Result<Args, UsageError> {
+    let mut parsed = Vec::with_capacity(schema.len());
+    let mut input = input;
+    for arg in schema {
+        if arg.kind == ArgKind::Rest {
+            let rest = input.trim();
+            if !rest.is_empty() {
+                parsed.push((arg.name, Value::Rest(rest.to_string())));
+            } else if arg.required {
+                return Err(UsageError::Missing(*arg));
+            }
+            return Ok(Args(parsed));
+        }
+        match token(input) {
+            Ok((next, token)) => {
+                parsed.push((arg.name, value(*arg, token)?));
+                input = next;
+            },
+            Err(_) if arg.required => return Err(UsageError::Missing(*arg)),
+            Err(_) => break,
+        }
+    }
+    if input.trim().is_empty() {
+        Ok(Args(parsed))
+    } else {
+        Err(UsageError::TooMany)
+    }
+}
+
+///Parses a duration such as `90`, `5m` or `1h30m`, bare numbers are seconds.
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    all_consuming(duration)(&text.to_lowercase()).ok().map(|(_, d)| d)
+}
+
+///Formats `duration` the way [`ArgKind::Duration`] accepts it, e.g. `1h30m`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let parts =
+        [(secs / 86400, "d"), (secs / 3600 % 24, "h"), (secs / 60 % 60, "m"), (secs % 60, "s")];
+    let formatted: String =
+        parts.iter().filter(|(n, _)| *n > 0).map(|(n, unit)| format!("{n}{unit}")).collect();
+    if formatted.is_empty() {
+        "0s".to_string()
+    } else {
+        formatted
+    }
+}
+
+///Generates a usage line such as `!link <twitch user> <discord id>` from a schema.
+pub fn usage(prefix: &str, name: &str, schema: &[Arg]) -> String {
+    schema.iter().fold(format!("{prefix}{name}"), |usage, arg| format!("{usage} {arg}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: [Arg; 2] =
+        [Arg::new("twitch user", ArgKind::Word), Arg::new("discord id", ArgKind::Number)];
+
+    #[test]
+    fn splits_invocation() {
+        assert_eq!(invocation("!", "!ping"), Some(("ping", "")));
+        assert_eq!(invocation("?", "  ?so @TestUser  hi "), Some(("so", "@TestUser  hi")));
+        assert_eq!(invocation("!", "ping"), None);
+        assert_eq!(invocation("!", "! ping"), None);
+    }
+
+    #[test]
+    fn parses_schema() {
+        let args = args(&SCHEMA, "CourtesyCallGaming 379001295744532481").unwrap();
+        assert_eq!(args.str("twitch user"), Some("CourtesyCallGaming"));
+        assert_eq!(args.number("discord id"), Some(379001295744532481));
+    }
+
+    #[test]
+    fn parses_users_quotes_and_rest() {
+        let schema = [
+            Arg::new("user", ArgKind::User),
+            Arg::new("title", ArgKind::Word),
+            Arg::optional("reason", ArgKind::Rest),
+        ];
+        let parsed = args(&schema, "@TestUser \"two words\" and the rest").unwrap();
+        assert_eq!(parsed.str("user"), Some("testuser"));
+        assert_eq!(parsed.str("title"), Some("two words"));
+        assert_eq!(parsed.str("reason"), Some("and the rest"));
+        assert_eq!(args(&schema, "testuser word").unwrap().get("reason"), None);
+    }
+
+    #[test]
+    fn reports_usage_errors() {
+        assert_eq!(args(&SCHEMA, "CourtesyCallGaming"), Err(UsageError::Missing(SCHEMA[1])));
+        assert_eq!(
+            args(&SCHEMA, "CourtesyCallGaming abc"),
+            Err(UsageError::Invalid(SCHEMA[1], "abc".to_string()))
+        );
+        assert_eq!(args(&SCHEMA, "a 1 extra"), Err(UsageError::TooMany));
+        assert_eq!(args(&[], ""), Ok(Args::default()));
+    }
+
+    #[test]
+    fn parses_durations() {
+        let schema = [Arg::new("duration", ArgKind::Duration)];
+        let secs = |input| args(&schema, input).map(|a| a.duration("duration").unwrap().as_secs());
+        assert_eq!(secs("90"), Ok(90));
+        assert_eq!(secs("10m"), Ok(600));
+        assert_eq!(secs("1H30m"), Ok(5400));
+        assert_eq!(secs("1d12h"), Ok(129600));
+        assert_eq!(secs("2w"), Ok(1209600));
+        assert!(secs("10x").is_err());
+        assert!(secs("m").is_err());
+        assert_eq!(format_duration(Duration::from_secs(5400)), "1h30m");
+        assert_eq!(format_duration(Duration::from_secs(129601)), "1d12h1s");
+        assert_eq!(format_duration(Duration::ZERO), "0s");
+    }
+
+    #[test]
+    fn generates_usage() {
+        assert_eq!(usage("!", "link", &SCHEMA), "!link <twitch user> <discord id>");
+        let schema = [Arg::optional("message", ArgKind::Rest)];
+        assert_eq!(usage("!", "say", &schema), "!say [message...]");
+        assert_eq!(UsageError::Missing(SCHEMA[1]).to_string(), "missing <discord id>".to_string());
+    }
+}
@@ -0,0 +1,220 @@
+//!Ordered permission levels for commands.
+//!
+//!A user's level comes from their badges, so only the badge name is looked at and a version
+//!Twitch adds later can never demote anyone. Followers aren't marked by a badge, so that level is
+//!only checked against Helix when a command actually needs it.
+
+//crate
+use super::registry::{Command, Origin};
+use crate::twitch::helix;
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{warn, debug};
+
+//std
+use std::fmt;
+use std::str::FromStr;
+
+//twitch_api
+use twitch_api::helix::channels::GetChannelFollowersRequest;
+
+//twitch_irc
+use twitch_irc::message::{Badge, PrivmsgMessage};
+
+///Who may run a command, each level includes everyone above it.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Level {
+    Everyone,
+    Follower,
+    ///Subscribed at the given tier or higher, from 1 to 3.
+    Subscriber(u8),
+    Vip,
+    Moderator,
+    Broadcaster,
+    BotAdmin,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Level::Everyone => write!(f, "everyone"),
+            Level::Follower => write!(f, "follower"),
+            Level::Subscriber(1) => write!(f, "subscriber"),
+            Level::Subscriber(tier) => write!(f, "subscriber{tier}"),
+            Level::Vip => write!(f, "vip"),
+            Level::Moderator => write!(f, "moderator"),
+            Level::Broadcaster => write!(f, "broadcaster"),
+            Level::BotAdmin => write!(f, "bot_admin"),
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace([' ', '-'], "_").as_str() {
+            "everyone" => Ok(Level::Everyone),
+            "follower" => Ok(Level::Follower),
+            "subscriber" | "subscriber1" => Ok(Level::Subscriber(1)),
+            "subscriber2" => Ok(Level::Subscriber(2)),
+            "subscriber3" => Ok(Level::Subscriber(3)),
+            "vip" => Ok(Level::Vip),
+            "moderator" | "mod" => Ok(Level::Moderator),
+            "broadcaster" => Ok(Level::Broadcaster),
+            "bot_admin" | "admin" => Ok(Level::BotAdmin),
+            other => Err(format!("unknown permission level `{other}`")),
+        }
+    }
+}
+
+///Subscription tier from a `subscriber` or `founder` badge version.
+///
+///Versions are the months subscribed, with tier 2 and 3 adding 2000 or 3000. Anything that
+///doesn't parse is treated as tier 1 rather than dropping the subscription.
+fn tier(version: &str) -> u8 {
+    match version.parse::<u32>() {
+        Ok(v) if v >= 3000 => 3,
+        Ok(v) if v >= 2000 => 2,
+        _ => 1,
+    }
+}
+
+///The highest level granted by `badges`, never lower than [`Level::Everyone`].
+pub fn badge_level(badges: &[Badge]) -> Level {
+    badges
+        .iter()
+        .map(|badge| match badge.name.as_str() {
+            "broadcaster" => Level::Broadcaster,
+            "moderator" => Level::Moderator,
+            "vip" => Level::Vip,
+            "subscriber" | "founder" => Level::Subscriber(tier(&badge.version)),
+            _ => Level::Everyone,
+        })
+        .max()
+        .unwrap_or(Level::Everyone)
+}
+
+///The level `command` needs in `channel`, taking the channel's overrides into account.
+pub fn required(command: &Command, channel: Option<&str>) -> Level {
+    let Some(levels) = channel.and_then(|c| CONFIG.command_permissions.get(&c.to_lowercase()))
+    else {
+        return command.level;
+    };
+    match levels.get(command.name).map(|l| l.parse::<Level>()) {
+        Some(Ok(level)) => level,
+        Some(Err(e)) => {
+            warn!("ignoring override for {}: {e}", command.name);
+            command.level
+        },
+        None => command.level,
+    }
+}
+
+///Whether whoever sent `origin` is at least at `required`.
+pub async fn allowed(origin: &Origin, required: Level) -> bool {
+    required == Level::Everyone || level(origin, required == Level::Follower).await >= required
+}
+
+///The highest level whoever sent `origin` holds, Helix is only asked whether they follow when
+///`check_follow` is set and their badges don't grant more.
+pub async fn level(origin: &Origin, check_follow: bool) -> Level {
+    if super::has_bot_admin_rights(origin.sender_login().to_string(), &CONFIG) {
+        return Level::BotAdmin;
+    }
+    let Origin::Chat(message) = origin else {
+        // whispers carry no badges, only bot admins get past everyone
+        return Level::Everyone;
+    };
+    match badge_level(&message.badges) {
+        Level::Everyone if check_follow && is_follower(message).await => Level::Follower,
+        level => level,
+    }
+}
+
+async fn is_follower(message: &PrivmsgMessage) -> bool {
+    let Some(helix) = helix::get() else {
+        return false;
+    };
+    let token = match helix.token().await {
+        Ok(token) => token,
+        Err(e) => {
+            warn!("Unable to check follow status: {e}");
+            return false;
+        },
+    };
+    let request = GetChannelFollowersRequest::broadcaster_id(message.channel_id.as_str())
+        .user_id(message.sender.id.as_str());
+    match helix.client.req_get(request, &token).await {
+        Ok(response) => !response.data.is_empty(),
+        Err(e) => {
+            debug!(
+                "Unable to check if {} follows #{}: {e}",
+                message.sender.login, message.channel_login
+            );
+            false
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn badge(name: &str, version: &str) -> Badge {
+        Badge { name: name.to_string(), version: version.to_string() }
+    }
+
+    #[test]
+    fn levels_are_ordered() {
+        assert!(Level::Everyone < Level::Follower);
+        assert!(Level::Follower < Level::Subscriber(1));
+        assert!(Level::Subscriber(1) < Level::Subscriber(3));
+        assert!(Level::Subscriber(3) < Level::Vip);
+        assert!(Level::Vip < Level::Moderator);
+        assert!(Level::Moderator < Level::Broadcaster);
+        assert!(Level::Broadcaster < Level::BotAdmin);
+    }
+
+    #[test]
+    fn badges_ignore_versions() {
+        assert_eq!(badge_level(&[]), Level::Everyone);
+        assert_eq!(badge_level(&[badge("moderator", "2")]), Level::Moderator);
+        assert_eq!(
+            badge_level(&[badge("broadcaster", "1"), badge("vip", "1")]),
+            Level::Broadcaster
+        );
+        assert_eq!(badge_level(&[badge("subscriber", "3012")]), Level::Subscriber(3));
+        assert_eq!(badge_level(&[badge("founder", "0")]), Level::Subscriber(1));
+        assert_eq!(badge_level(&[badge("subscriber", "new")]), Level::Subscriber(1));
+        assert_eq!(badge_level(&[badge("glhf-pledge", "1")]), Level::Everyone);
+    }
+
+    #[test]
+    fn parses_levels() {
+        for level in [
+            Level::Everyone,
+            Level::Follower,
+            Level::Subscriber(1),
+            Level::Subscriber(2),
+            Level::Subscriber(3),
+            Level::Vip,
+            Level::Moderator,
+            Level::Broadcaster,
+            Level::BotAdmin,
+        ] {
+            assert_eq!(level.to_string().parse::<Level>(), Ok(level));
+        }
+        assert_eq!("Mod".parse::<Level>(), Ok(Level::Moderator));
+        assert!("owner".parse::<Level>().is_err());
+    }
+
+    #[test]
+    fn channel_overrides() {
+        let ping = super::super::COMMANDS.iter().find(|c| c.name == "ping").unwrap();
+        assert_eq!(required(ping, None), Level::Moderator);
+        // config.toml.example lowers !ping to vip in TwitchRivals
+        assert_eq!(required(ping, Some("TwitchRivals")), Level::Vip);
+    }
+}
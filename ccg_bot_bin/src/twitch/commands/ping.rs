@@ -5,7 +5,34 @@ use twitch_irc::{
     Error, TwitchIRCClient,
 };
 
+use super::permissions::Level;
+use super::registry::{Availability, Command, Invocation, Origin};
 use super::BotTokenStorage;
+use crate::cooldown::Cooldown;
+
+//futures
+use futures::future::BoxFuture;
+
+pub(super) const COMMAND: Command = Command {
+    name: "ping",
+    aliases: &[],
+    args: &[],
+    availability: Availability::Chat,
+    level: Level::Moderator,
+    cooldown: Cooldown::secs(5, 30),
+    handler: run,
+};
+
+fn run(invocation: Invocation) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        match invocation.origin {
+            Origin::Chat(message) => {
+                handle(message, invocation.client).await.map_err(|e| eyre::eyre!("{e}"))
+            },
+            Origin::Whisper(_) => Ok(()),
+        }
+    })
+}
 
 pub async fn handle(
     message: PrivmsgMessage,
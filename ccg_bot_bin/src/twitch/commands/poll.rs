@@ -0,0 +1,90 @@
+//!`!poll` and `!predict`, see [`polls`](crate::twitch::polls) for the syntax.
+
+//crate
+use super::parser::{Arg, ArgKind};
+use super::permissions::Level;
+use super::registry::{self, Availability, Command, Invocation};
+use crate::cooldown::Cooldown;
+use crate::twitch::polls;
+
+//futures
+use futures::future::BoxFuture;
+
+pub(super) const POLL: Command = Command {
+    name: "poll",
+    aliases: &[],
+    args: &[Arg::new("question|end", ArgKind::Rest)],
+    availability: Availability::Chat,
+    level: Level::Moderator,
+    cooldown: Cooldown::NONE,
+    handler: poll,
+};
+
+pub(super) const PREDICT: Command = Command {
+    name: "predict",
+    aliases: &["prediction"],
+    args: &[
+        Arg::new("start|lock|resolve|cancel", ArgKind::Word),
+        Arg::optional("details", ArgKind::Rest),
+    ],
+    availability: Availability::Chat,
+    level: Level::Moderator,
+    cooldown: Cooldown::NONE,
+    handler: predict,
+};
+
+///Why the command did nothing outside `channel`.
+fn elsewhere(channel: &str) -> String {
+    format!("Polls and predictions only run in #{channel}")
+}
+
+fn poll(invocation: Invocation) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        if invocation.outside_bot_channel(elsewhere).await? {
+            return Ok(());
+        }
+        let text = invocation.args.str("question|end").unwrap_or_default();
+        if text.eq_ignore_ascii_case("end") {
+            return invocation.reply_result(polls::end_poll().await).await;
+        }
+        match polls::spec(text, polls::POLL) {
+            Ok(spec) => invocation.reply_result(polls::start_poll(&spec).await).await,
+            Err(problem) => invocation.reply(problem).await,
+        }
+    })
+}
+
+fn predict(invocation: Invocation) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        if invocation.outside_bot_channel(elsewhere).await? {
+            return Ok(());
+        }
+        let action = invocation.args.str("start|lock|resolve|cancel").unwrap_or_default();
+        let details = invocation.args.str("details");
+        let result = match (action.to_lowercase().as_str(), details) {
+            ("start", Some(details)) => match polls::spec(details, polls::PREDICTION) {
+                Ok(spec) => polls::start_prediction(&spec).await,
+                Err(problem) => return invocation.reply(problem).await,
+            },
+            ("lock", None) => polls::lock_prediction().await,
+            ("resolve", Some(winner)) => polls::resolve_prediction(winner).await,
+            ("cancel", None) => polls::cancel_prediction().await,
+            _ => {
+                let prefix = registry::prefix_for(invocation.origin.channel());
+                return invocation.reply(format!("[Usage] {}", PREDICT.usage(prefix))).await;
+            },
+        };
+        invocation.reply_result(result).await
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_lines() {
+        assert_eq!(POLL.usage("!"), "!poll <question|end...>");
+        assert_eq!(PREDICT.usage("!"), "!predict <start|lock|resolve|cancel> [details...]");
+    }
+}
@@ -0,0 +1,147 @@
+//!`!quote` for the [quote database](crate::quotes).
+//!
+//!`!quote` or `!quote random` shows a random quote, `!quote <id>` a specific one and
+//!`!quote search <term>` the quotes mentioning a term. Anyone can `!quote add <text>`, moderators
+//!can also `!quote edit <id> <text>` and `!quote delete <id>`.
+
+//crate
+use super::parser::{Arg, ArgKind};
+use super::permissions::{self, Level};
+use super::registry::{self, Availability, Command, Invocation};
+use crate::audit;
+use crate::cooldown::Cooldown;
+use crate::db::models::NewAuditEntry;
+use crate::discord::bridge::{truncate, TWITCH_MESSAGE_LIMIT};
+use crate::quotes;
+
+//futures
+use futures::future::BoxFuture;
+
+pub(super) const COMMAND: Command = Command {
+    name: "quote",
+    aliases: &["quotes"],
+    args: &[
+        Arg::optional("id|add|search|edit|delete", ArgKind::Word),
+        Arg::optional("text", ArgKind::Rest),
+    ],
+    availability: Availability::Chat,
+    level: Level::Everyone,
+    cooldown: Cooldown::secs(5, 30),
+    handler: run,
+};
+
+///What `!quote` was asked to do.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Request<'a> {
+    Random,
+    Show(u32),
+    Add(&'a str),
+    Search(&'a str),
+    Edit(u32, &'a str),
+    Delete(u32),
+}
+
+///Works out the request from the first argument and the text after it.
+pub fn request<'a>(action: Option<&'a str>, text: Option<&'a str>) -> Option<Request<'a>> {
+    let text = text.map(str::trim).filter(|t| !t.is_empty());
+    let id = |s: &str| s.trim_start_matches('#').parse::<u32>().ok();
+    match (action.map(str::to_lowercase).as_deref(), text) {
+        (None | Some("random"), None) => Some(Request::Random),
+        (Some("add"), Some(text)) => Some(Request::Add(text)),
+        (Some("search"), Some(term)) => Some(Request::Search(term)),
+        (Some("edit"), Some(rest)) => {
+            let (quote, text) = rest.split_once(char::is_whitespace)?;
+            Some(Request::Edit(id(quote)?, text.trim()))
+        },
+        (Some("delete" | "remove"), Some(quote)) => Some(Request::Delete(id(quote)?)),
+        (Some(_), None) => Some(Request::Show(id(action?)?)),
+        _ => None,
+    }
+}
+
+fn run(invocation: Invocation) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        let Some(channel) = invocation.origin.channel().map(str::to_string) else {
+            return Ok(());
+        };
+        let prefix = registry::prefix_for(Some(&channel));
+        let args = &invocation.args;
+        let Some(request) = request(args.str("id|add|search|edit|delete"), args.str("text")) else {
+            return invocation.reply(format!("[Usage] {}", COMMAND.usage(prefix))).await;
+        };
+        let moderator = permissions::allowed(&invocation.origin, Level::Moderator).await;
+        let reply = match request {
+            Request::Random => match quotes::random().await? {
+                Some(quote) => quotes::format(&quote),
+                None => format!("No quotes yet, add one with {prefix}quote add"),
+            },
+            Request::Show(id) => match quotes::find(id).await? {
+                Some(quote) => quotes::format(&quote),
+                None => format!("There is no quote #{id}"),
+            },
+            Request::Add(text) => {
+                let added_by = invocation.origin.sender_login();
+                let quote = quotes::add(text, Some(&channel), "twitch", added_by).await?;
+                format!("Added quote #{}", quote.id)
+            },
+            Request::Search(term) => quotes::format_search(term, &quotes::search(term).await?),
+            Request::Edit(..) | Request::Delete(_) if !moderator => {
+                "Only moderators can change quotes".to_string()
+            },
+            Request::Edit(id, text) => match quotes::edit(id, text).await? {
+                0 => format!("There is no quote #{id}"),
+                _ => {
+                    record(&invocation, &channel, "quote edit", id);
+                    format!("Updated quote #{id}")
+                },
+            },
+            Request::Delete(id) => match quotes::delete(id).await? {
+                0 => format!("There is no quote #{id}"),
+                _ => {
+                    record(&invocation, &channel, "quote delete", id);
+                    format!("Deleted quote #{id}")
+                },
+            },
+        };
+        invocation.reply(truncate(&reply, TWITCH_MESSAGE_LIMIT)).await
+    })
+}
+
+fn record(invocation: &Invocation, channel: &str, action: &str, id: u32) {
+    audit::record(NewAuditEntry {
+        platform: "twitch".to_string(),
+        channel: channel.to_string(),
+        moderator: invocation.origin.sender_login().to_string(),
+        action: action.to_string(),
+        target: format!("#{id}"),
+        reason: None,
+        duration_seconds: None,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_requests() {
+        assert_eq!(request(None, None), Some(Request::Random));
+        assert_eq!(request(Some("Random"), None), Some(Request::Random));
+        assert_eq!(request(Some("#12"), None), Some(Request::Show(12)));
+        assert_eq!(
+            request(Some("add"), Some("I meant to do that")),
+            Some(Request::Add("I meant to do that"))
+        );
+        assert_eq!(request(Some("search"), Some("meant")), Some(Request::Search("meant")));
+        assert_eq!(request(Some("edit"), Some("12 New text")), Some(Request::Edit(12, "New text")));
+        assert_eq!(request(Some("delete"), Some("#12")), Some(Request::Delete(12)));
+    }
+
+    #[test]
+    fn rejects_bad_requests() {
+        assert_eq!(request(Some("add"), None), None);
+        assert_eq!(request(Some("twelve"), None), None);
+        assert_eq!(request(Some("edit"), Some("12")), None);
+        assert_eq!(request(Some("12"), Some("extra")), None);
+    }
+}
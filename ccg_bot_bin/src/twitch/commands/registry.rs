@@ -0,0 +1,324 @@
+//!Declarative command registry.
+//!
+//!Each command describes itself with a [`Command`], its name, aliases, argument schema and where
+//!it may be used. [`dispatch`] matches incoming chat or whispers against the registry, parses the
+//!arguments and either runs the handler or replies with a usage line generated from the schema.
+//!Commands on [cooldown](crate::cooldown) are ignored unless the sender is a moderator.
+
+//crate
+use super::parser::{self, Arg, Args};
+use super::permissions::{self, Level};
+use crate::cooldown::{self, Cooldown};
+use crate::twitch::{bot_channel, reply, TwitchClient};
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, debug};
+
+//futures
+use futures::future::BoxFuture;
+
+//std
+use std::time::Duration;
+
+//twitch_irc
+use twitch_irc::message::{PrivmsgMessage, WhisperMessage};
+
+///Where a command may be used.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Availability {
+    Chat,
+    Whisper,
+    #[allow(unused)]
+    Both,
+}
+
+impl Availability {
+    fn allows(self, origin: &Origin) -> bool {
+        matches!(
+            (self, origin),
+            (Availability::Both, _)
+                | (Availability::Chat, Origin::Chat(_))
+                | (Availability::Whisper, Origin::Whisper(_))
+        )
+    }
+}
+
+///The message a command was invoked from.
+#[derive(Clone, Debug)]
+pub enum Origin {
+    Chat(PrivmsgMessage),
+    Whisper(WhisperMessage),
+}
+
+impl Origin {
+    ///The text of the message.
+    pub fn text(&self) -> &str {
+        match self {
+            Origin::Chat(m) => &m.message_text,
+            Origin::Whisper(m) => &m.message_text,
+        }
+    }
+
+    ///The login of whoever sent the message.
+    pub fn sender_login(&self) -> &str {
+        match self {
+            Origin::Chat(m) => &m.sender.login,
+            Origin::Whisper(m) => &m.sender.login,
+        }
+    }
+
+    ///The channel the message was sent in, [`None`] for whispers.
+    pub fn channel(&self) -> Option<&str> {
+        match self {
+            Origin::Chat(m) => Some(&m.channel_login),
+            Origin::Whisper(_) => None,
+        }
+    }
+}
+
+///Runs a command, the returned error is logged.
+pub type Handler = fn(Invocation) -> BoxFuture<'static, eyre::Result<()>>;
+
+///A command and everything needed to parse and run it.
+#[derive(Clone, Copy, Debug)]
+pub struct Command {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub args: &'static [Arg],
+    pub availability: Availability,
+    ///The lowest level that may run this command, channels may override it.
+    pub level: Level,
+    ///Default cooldowns, which `[commands.cooldowns]` may override.
+    pub cooldown: Cooldown,
+    pub handler: Handler,
+}
+
+impl Command {
+    ///Whether `name` is this command's name or one of its aliases, ignoring case.
+    pub fn matches(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+            || self.aliases.iter().any(|a| a.eq_ignore_ascii_case(name))
+    }
+
+    ///The usage line for this command, e.g. `!link <twitch user> <discord id>`.
+    pub fn usage(&self, prefix: &str) -> String {
+        parser::usage(prefix, self.name, self.args)
+    }
+}
+
+///A parsed command ready to be handled.
+pub struct Invocation {
+    pub origin: Origin,
+    pub args: Args,
+    pub client: TwitchClient,
+}
+
+impl Invocation {
+    ///Replies in chat, or by [whisper](reply::whisper) if the command was whispered.
+    pub async fn reply(&self, text: impl Into<String>) -> eyre::Result<()> {
+        let text = text.into();
+        match &self.origin {
+            #[cfg(test)]
+            Origin::Chat(_) => {
+                let _ = text;
+                Ok(())
+            },
+            #[cfg(not(test))]
+            Origin::Chat(m) => {
+                self.client.say_in_reply_to(m, text).await.map_err(|e| eyre::eyre!("{e}"))
+            },
+            Origin::Whisper(m) => {
+                reply::whisper(&self.client, &m.sender.id, &m.sender.login, &text)
+            },
+        }
+    }
+
+    ///Replies with what `why` makes of the [bot's channel](crate::twitch::bot_channel) unless the
+    ///command was used there, returning whether it wasn't.
+    pub async fn outside_bot_channel(
+        &self,
+        why: impl FnOnce(&str) -> String,
+    ) -> eyre::Result<bool> {
+        let channel = bot_channel();
+        if self.origin.channel() == Some(channel.as_str()) {
+            return Ok(false);
+        }
+        self.reply(why(&channel)).await?;
+        Ok(true)
+    }
+
+    ///Replies with the outcome of a Helix change, keeping the details of a failure for the logs.
+    pub async fn reply_result(&self, result: eyre::Result<String>) -> eyre::Result<()> {
+        match result {
+            Ok(reply) => self.reply(reply).await,
+            Err(e) => {
+                self.reply("Twitch didn't accept that, check the bot's logs").await?;
+                Err(e)
+            },
+        }
+    }
+}
+
+///The command prefix for `channel`, whispers use the default prefix.
+pub fn prefix_for(channel: Option<&str>) -> &'static str {
+    channel
+        .and_then(|c| CONFIG.command_prefixes.get(&c.to_lowercase()))
+        .unwrap_or(&CONFIG.command_prefix)
+}
+
+///Finds the command called `name` in `commands`.
+pub fn find<'a>(commands: &'a [Command], name: &str) -> Option<&'a Command> {
+    commands.iter().find(|c| c.matches(name))
+}
+
+///The reply sent for a command on cooldown when `cooldown_reply` is set.
+pub fn cooldown_notice(prefix: &str, name: &str, remaining: Duration) -> String {
+    format!("{prefix}{name} is on cooldown, try again in {}", cooldown::format_remaining(remaining))
+}
+
+///Starts `command`'s cooldowns for the sender, or returns how long is left on them.
+///
+///Moderators, and bot admins in whispers, are never held back.
+async fn cooling_down(command: &Command, origin: &Origin) -> Result<(), Duration> {
+    if permissions::allowed(origin, Level::Moderator).await {
+        return Ok(());
+    }
+    let key = format!("twitch:{}:{}", origin.channel().unwrap_or("whisper"), command.name);
+    cooldown::check(&key, origin.sender_login(), command.cooldown.configured(command.name))
+}
+
+///Runs the command in `origin` if there is one, returning whether it was handled.
+///
+///Commands the sender lacks the level for are ignored, but still count as handled, as do commands
+///on cooldown. Arguments that don't match the command's schema are answered with its usage.
+pub async fn dispatch(
+    commands: &'static [Command],
+    origin: Origin,
+    prefix: &str,
+    client: TwitchClient,
+) -> bool {
+    let Some((name, rest)) = parser::invocation(prefix, origin.text()) else {
+        return false;
+    };
+    let Some(command) = find(commands, name).filter(|c| c.availability.allows(&origin)) else {
+        return false;
+    };
+    let rest = rest.to_string();
+    let required = permissions::required(command, origin.channel());
+    if !permissions::allowed(&origin, required).await {
+        debug!("{} needs {required} to run {prefix}{}", origin.sender_login(), command.name);
+        return true;
+    }
+    // a usage error doesn't start the cooldown, so the corrected retry goes through
+    let args = match parser::args(command.args, &rest) {
+        Ok(args) => args,
+        Err(usage_error) => {
+            let invocation = Invocation { origin, args: Args::default(), client };
+            let response = format!("[Usage] {} ({usage_error})", command.usage(prefix));
+            if let Err(e) = invocation.reply(response).await {
+                error!("Unable to send usage for {}: {e:?}", command.name);
+            }
+            return true;
+        },
+    };
+    if let Err(remaining) = cooling_down(command, &origin).await {
+        debug!("{prefix}{} is on cooldown for {}", command.name, origin.sender_login());
+        if CONFIG.command_cooldown_reply {
+            let invocation = Invocation { origin, args: Args::default(), client };
+            let response = cooldown_notice(prefix, command.name, remaining);
+            if let Err(e) = invocation.reply(response).await {
+                error!("Unable to send cooldown for {}: {e:?}", command.name);
+            }
+        }
+        return true;
+    }
+    debug!("{} ran {prefix}{}", origin.sender_login(), command.name);
+    let invocation = Invocation { origin, args, client };
+    tokio::spawn(async move {
+        if let Err(e) = (command.handler)(invocation).await {
+            error!("Command {} failed: {e:?}", command.name);
+        }
+    });
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::twitch::commands::parser::ArgKind;
+    use crate::twitch::tokens::BotTokenStorage;
+    use twitch_irc::message::IRCMessage;
+    use twitch_irc::{login::RefreshingLoginCredentials, ClientConfig, TwitchIRCClient};
+
+    fn noop(_: Invocation) -> BoxFuture<'static, eyre::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    static COMMANDS: &[Command] = &[
+        Command {
+            name: "shoutout",
+            aliases: &["so"],
+            args: &[Arg::new("user", ArgKind::User)],
+            availability: Availability::Chat,
+            level: Level::Everyone,
+            cooldown: Cooldown::NONE,
+            handler: noop,
+        },
+        Command {
+            name: "secret",
+            aliases: &[],
+            args: &[],
+            availability: Availability::Whisper,
+            level: Level::Everyone,
+            cooldown: Cooldown::NONE,
+            handler: noop,
+        },
+    ];
+
+    fn privmsg(text: &str) -> Origin {
+        message("moderator/1", text)
+    }
+
+    fn message(badges: &str, text: &str) -> Origin {
+        let src = format!("@badge-info=;badges={badges};color=#AA66FF;display-name=TestUser;emotes=;flags=;id=8da29c58-d182-40cd-8b65-1dc446b45c65;mod=1;room-id=78127347;subscriber=0;tmi-sent-ts=1693037683123;turbo=0;user-id=12345678;user-type= :testuser!testuser@testuser.tmi.twitch.tv PRIVMSG #zoes17 :{text}");
+        Origin::Chat(PrivmsgMessage::try_from(IRCMessage::parse(&src).unwrap()).unwrap())
+    }
+
+    fn client() -> TwitchClient {
+        let rlc = RefreshingLoginCredentials::init_with_username(
+            Some("TestUser".to_string()),
+            "client_id".to_string(),
+            "client_secret".to_string(),
+            BotTokenStorage::new(),
+        );
+        let (_, client) = TwitchIRCClient::new(ClientConfig::new_simple(rlc));
+        client
+    }
+
+    #[test]
+    fn finds_by_alias() {
+        assert_eq!(find(COMMANDS, "SO").map(|c| c.name), Some("shoutout"));
+        assert!(find(COMMANDS, "nope").is_none());
+        assert_eq!(COMMANDS[0].usage("!"), "!shoutout <user>");
+    }
+
+    #[tokio::test]
+    async fn dispatches_by_availability() {
+        assert!(dispatch(COMMANDS, privmsg("!so @TestUser"), "!", client()).await);
+        // usage errors are still handled
+        assert!(dispatch(COMMANDS, privmsg("!so"), "!", client()).await);
+        assert!(!dispatch(COMMANDS, privmsg("!secret"), "!", client()).await);
+        assert!(!dispatch(COMMANDS, privmsg("?so TestUser"), "!", client()).await);
+        assert!(!dispatch(COMMANDS, privmsg("This is a test"), "!", client()).await);
+    }
+
+    #[tokio::test]
+    async fn moderators_skip_cooldowns() {
+        let command = Command { name: "cooldown", cooldown: Cooldown::secs(60, 0), ..COMMANDS[0] };
+        let viewer = message("", "!cooldown");
+        assert!(cooling_down(&command, &viewer).await.is_ok());
+        assert!(cooling_down(&command, &viewer).await.is_err());
+        assert!(cooling_down(&command, &privmsg("!cooldown")).await.is_ok());
+    }
+}
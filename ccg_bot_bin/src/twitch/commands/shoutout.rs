@@ -0,0 +1,44 @@
+//!`!so <user>` shouts out another streamer, see [`shoutouts`](crate::twitch::shoutouts).
+
+//crate
+use super::parser::{Arg, ArgKind};
+use super::permissions::Level;
+use super::registry::{Availability, Command, Invocation, Origin};
+use crate::cooldown::Cooldown;
+use crate::twitch::shoutouts;
+
+//futures
+use futures::future::BoxFuture;
+
+pub(super) const COMMAND: Command = Command {
+    name: "so",
+    aliases: &["shoutout"],
+    args: &[Arg::new("user", ArgKind::User)],
+    availability: Availability::Chat,
+    level: Level::Moderator,
+    cooldown: Cooldown::NONE,
+    handler: run,
+};
+
+fn run(invocation: Invocation) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        let Origin::Chat(message) = &invocation.origin else {
+            return Ok(());
+        };
+        let login = invocation.args.str("user").unwrap_or_default();
+        match shoutouts::shoutout(&message.channel_login, &message.channel_id, login).await? {
+            Some(announcement) => invocation.reply(announcement).await,
+            None => invocation.reply(format!("There is no channel called {login}")).await,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_line() {
+        assert_eq!(COMMAND.usage("!"), "!so <user>");
+    }
+}
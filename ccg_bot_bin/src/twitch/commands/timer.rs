@@ -0,0 +1,206 @@
+//!`!timer add|remove|list` for managing a channel's [timers](crate::twitch::timers).
+//!
+//!Options go between the interval and the message, `-lines=<n>` sets how many chat lines must be
+//!seen between posts and `-live` only posts while the stream is live, e.g.
+//!`!timer add socials 15 -lines=10 -live Follow us on Discord!`.
+
+//crate
+use super::parser::{Arg, ArgKind};
+use super::permissions::Level;
+use super::registry::{self, Availability, Command, Invocation};
+use crate::cooldown::Cooldown;
+use crate::db::{self, models::NewTimer};
+use crate::discord::bridge::{truncate, TWITCH_MESSAGE_LIMIT};
+
+//futures
+use futures::future::BoxFuture;
+
+//nom
+use nom::{
+    bytes::complete::take_while1,
+    character::complete::{alpha1, char, multispace0, u32 as number},
+    combinator::opt,
+    sequence::{pair, preceded},
+    IResult,
+};
+
+///Lines needed between posts when `-lines` isn't set.
+const DEFAULT_MIN_LINES: u32 = 5;
+///Longest name the `timers` table can hold.
+const NAME_LIMIT: usize = 25;
+
+pub(super) const COMMAND: Command = Command {
+    name: "timer",
+    aliases: &[],
+    args: &[Arg::new("add|remove|list", ArgKind::Word), Arg::optional("details", ArgKind::Rest)],
+    availability: Availability::Chat,
+    level: Level::Moderator,
+    cooldown: Cooldown::NONE,
+    handler: run,
+};
+
+///A timer parsed from `!timer add`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Spec<'a> {
+    pub name: &'a str,
+    pub interval_minutes: u32,
+    pub min_lines: u32,
+    pub live_only: bool,
+    pub message: &'a str,
+}
+
+fn word(input: &str) -> IResult<&str, &str> {
+    preceded(multispace0, take_while1(|c: char| !c.is_whitespace()))(input)
+}
+
+fn option(input: &str) -> IResult<&str, (&str, Option<&str>)> {
+    preceded(
+        multispace0,
+        preceded(
+            char('-'),
+            pair(alpha1, opt(preceded(char('='), take_while1(|c: char| !c.is_whitespace())))),
+        ),
+    )(input)
+}
+
+///Parses `<name> <minutes> [-lines=<n>] [-live] <message>`.
+pub fn spec(input: &str) -> Result<Spec<'_>, String> {
+    let usage = || "expected <name> <minutes> [-lines=<n>] [-live] <message>".to_string();
+    let (rest, name) = word(input).map_err(|_| usage())?;
+    let (mut rest, interval_minutes) = preceded(multispace0, number)(rest)
+        .map_err(|_: nom::Err<nom::error::Error<&str>>| usage())?;
+    if interval_minutes == 0 {
+        return Err("the interval must be at least a minute".to_string());
+    }
+    let mut spec = Spec {
+        name,
+        interval_minutes,
+        min_lines: DEFAULT_MIN_LINES,
+        live_only: false,
+        message: "",
+    };
+    while let Ok((next, (key, value))) = option(rest) {
+        match (key.to_lowercase().as_str(), value) {
+            ("lines", Some(value)) => {
+                spec.min_lines =
+                    value.parse().map_err(|_| format!("`{value}` isn't a number of lines"))?
+            },
+            ("live", None) => spec.live_only = true,
+            (other, _) => {
+                return Err(format!("unknown option `-{other}`, use -lines=<n> or -live"))
+            },
+        }
+        rest = next;
+    }
+    spec.message = rest.trim();
+    if spec.message.is_empty() {
+        return Err(usage());
+    }
+    Ok(spec)
+}
+
+fn run(invocation: Invocation) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        let Some(channel) = invocation.origin.channel().map(str::to_string) else {
+            return Ok(());
+        };
+        let prefix = registry::prefix_for(Some(&channel));
+        let details = invocation.args.str("details").unwrap_or_default();
+        let reply = match invocation.args.str("add|remove|list").unwrap_or_default() {
+            "add" => add(&channel, details).await?,
+            "remove" => {
+                let name = details.trim().to_lowercase();
+                if name.is_empty() {
+                    return invocation.reply(format!("[Usage] {}", COMMAND.usage(prefix))).await;
+                }
+                let chan = channel.clone();
+                let lookup = name.clone();
+                match db::blocking(move || db::delete_timer(&chan, &lookup)).await? {
+                    0 => format!("There is no timer called {name}"),
+                    _ => format!("Removed timer {name}"),
+                }
+            },
+            "list" => {
+                let chan = channel.clone();
+                let timers = db::blocking(move || db::find_timers(&chan)).await?;
+                if timers.is_empty() {
+                    format!("No timers yet, add one with {prefix}timer add")
+                } else {
+                    let list = timers
+                        .iter()
+                        .map(|t| {
+                            let live = if t.live_only { ", live only" } else { "" };
+                            format!("{} (every {}m{live})", t.name, t.interval_minutes)
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    truncate(&format!("Timers: {list}"), TWITCH_MESSAGE_LIMIT)
+                }
+            },
+            _ => format!("[Usage] {}", COMMAND.usage(prefix)),
+        };
+        invocation.reply(reply).await
+    })
+}
+
+async fn add(channel: &str, details: &str) -> eyre::Result<String> {
+    let spec = match spec(details) {
+        Ok(spec) => spec,
+        Err(e) => return Ok(e),
+    };
+    let name = spec.name.to_lowercase();
+    if name.chars().count() > NAME_LIMIT {
+        return Ok(format!("Names can be at most {NAME_LIMIT} characters"));
+    }
+    let timer = NewTimer {
+        channel: channel.to_string(),
+        name: name.clone(),
+        message: spec.message.to_string(),
+        interval_minutes: spec.interval_minutes,
+        min_lines: spec.min_lines,
+        live_only: spec.live_only,
+    };
+    let created = db::blocking(move || {
+        if db::find_timers(&timer.channel)?.iter().any(|t| t.name == timer.name) {
+            return Ok(false);
+        }
+        db::create_timer(&timer).map(|()| true)
+    })
+    .await?;
+    if !created {
+        return Ok(format!("There is already a timer called {name}"));
+    }
+    Ok(format!("Added timer {name}, posting every {}m", spec.interval_minutes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_spec() {
+        assert_eq!(
+            spec("socials 15 -lines=10 -live Follow us on Discord!"),
+            Ok(Spec {
+                name: "socials",
+                interval_minutes: 15,
+                min_lines: 10,
+                live_only: true,
+                message: "Follow us on Discord!",
+            })
+        );
+        let plain = spec("rules 30 Be nice -_-").unwrap();
+        assert_eq!((plain.min_lines, plain.live_only), (DEFAULT_MIN_LINES, false));
+        assert_eq!(plain.message, "Be nice -_-");
+    }
+
+    #[test]
+    fn rejects_bad_spec() {
+        assert!(spec("socials").is_err());
+        assert!(spec("socials soon hi").is_err());
+        assert!(spec("socials 0 hi").is_err());
+        assert!(spec("socials 15 -lines=many hi").is_err());
+        assert!(spec("socials 15 -live=yes hi").is_err());
+        assert!(spec("socials 15 -live").is_err());
+    }
+}
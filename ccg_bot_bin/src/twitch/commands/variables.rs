@@ -0,0 +1,77 @@
+//!`$(name)` variables in command responses.
+
+///Names of the variables used in `template`, in order of appearance.
+pub fn names(template: &str) -> Vec<&str> {
+    let mut names = vec![];
+    let mut rest = template;
+    while let Some(start) = rest.find("$(") {
+        let tail = &rest[start + 2..];
+        match tail.find(')') {
+            Some(end) => {
+                names.push(&tail[..end]);
+                rest = &tail[end + 1..];
+            },
+            None => break,
+        }
+    }
+    names
+}
+
+///Replaces each `$(name)` in `template` with `lookup(name)`, unknown variables are left as is.
+pub fn expand<F>(template: &str, lookup: F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("$(") {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start + 2..];
+        let Some(end) = tail.find(')') else {
+            rest = &rest[start..];
+            break;
+        };
+        let name = &tail[..end];
+        match lookup(name.trim()) {
+            Some(value) => out.push_str(&value),
+            None => {
+                out.push_str("$(");
+                out.push_str(name);
+                out.push(')');
+            },
+        }
+        rest = &tail[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "user" => Some("TestUser".to_string()),
+            "count" => Some("3".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn expands_known_variables() {
+        assert_eq!(expand("Hi $(user), used $(count) times", lookup), "Hi TestUser, used 3 times");
+        assert_eq!(expand("$(user)$(user)", lookup), "TestUserTestUser");
+    }
+
+    #[test]
+    fn leaves_unknown_and_unclosed() {
+        assert_eq!(expand("$(nope) and $(user", lookup), "$(nope) and $(user");
+        assert_eq!(expand("costs $5 (maybe)", lookup), "costs $5 (maybe)");
+    }
+
+    #[test]
+    fn lists_names() {
+        assert_eq!(names("$(user) is $(uptime) in $(channel"), vec!["user", "uptime"]);
+    }
+}
@@ -0,0 +1,286 @@
+//!Handlers for EventSub notifications, keyed by subscription type.
+//!
+//!The websocket loop only parses notifications and hands them to [`dispatch`], which runs every
+//![`Handler`] registered for the notification's type. A feature that needs an event adds its
+//!handler to [`HANDLERS`] and its type to [`defaults`] or `[eventsub.channels]`, the loop itself
+//!doesn't change.
+
+//crate
+use crate::twitch::moderation::{self, ModerationEvent};
+use crate::twitch::{alerts, golive, helix, polls, rewards, rolesync, shoutouts};
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, debug};
+
+//futures
+use futures::future::BoxFuture;
+
+//twitch_api
+use twitch_api::eventsub::{self, Event};
+
+///Handles one notification, the returned error is logged.
+pub type Handle = fn(Event) -> BoxFuture<'static, eyre::Result<()>>;
+
+///A handler for notifications of one subscription type.
+#[derive(Clone, Copy, Debug)]
+pub struct Handler {
+    ///The subscription type, e.g. `stream.online`.
+    pub event: &'static str,
+    ///Run for every notification of that type.
+    pub handle: Handle,
+}
+
+///Every handler, a type may have several.
+pub(crate) static HANDLERS: &[Handler] = &[
+    Handler { event: "channel.ban", handle: ban },
+    Handler { event: "channel.channel_points_custom_reward_redemption.add", handle: redemption },
+    Handler { event: "channel.follow", handle: follow },
+    Handler { event: "channel.moderator.add", handle: role_sync },
+    Handler { event: "channel.moderator.remove", handle: role_sync },
+    Handler { event: "channel.poll.end", handle: poll_end },
+    Handler { event: "channel.prediction.end", handle: prediction_end },
+    Handler { event: "channel.raid", handle: raid },
+    Handler { event: "channel.subscribe", handle: role_sync },
+    Handler { event: "channel.subscription.end", handle: role_sync },
+    Handler { event: "channel.unban", handle: unban },
+    Handler { event: "channel.update", handle: channel_update },
+    Handler { event: "channel.vip.add", handle: role_sync },
+    Handler { event: "channel.vip.remove", handle: role_sync },
+    Handler { event: "stream.offline", handle: stream_offline },
+    Handler { event: "stream.online", handle: stream_online },
+];
+
+///Types that need the broadcaster's permission, the bot's channel or one that authorized the bot.
+const AUTHORIZED_ONLY: &[&str] = &[
+    "channel.ban",
+    "channel.unban",
+    "channel.subscribe",
+    "channel.subscription.end",
+    "channel.vip.add",
+    "channel.vip.remove",
+    "channel.moderator.add",
+    "channel.moderator.remove",
+    "channel.poll.end",
+    "channel.prediction.end",
+    "channel.channel_points_custom_reward_redemption.add",
+];
+
+///Whether any handler is registered for `event`.
+pub fn handled(event: &str) -> bool {
+    HANDLERS.iter().any(|h| h.event == event)
+}
+
+///The subscription types `channel` gets when `[eventsub.channels]` doesn't list it.
+pub fn defaults(authorized: bool, raids: bool, follows: bool) -> Vec<&'static str> {
+    let mut events = vec!["stream.online", "stream.offline", "channel.update"];
+    if authorized {
+        events.extend_from_slice(AUTHORIZED_ONLY);
+    }
+    // following needs a moderator's token, the bot only has one for its own and authorized channels
+    if authorized && follows {
+        events.push("channel.follow");
+    }
+    if raids {
+        events.push("channel.raid");
+    }
+    events
+}
+
+///The subscription types to create for `channel`, `authorized` if its broadcaster authorized the bot.
+pub fn wanted(channel: &str, authorized: bool) -> Vec<String> {
+    let configured = CONFIG.eventsub.as_ref().and_then(|e| e.channels.as_ref()).and_then(|c| {
+        c.iter().find(|(name, _)| name.eq_ignore_ascii_case(channel)).map(|(_, events)| events)
+    });
+    match configured {
+        Some(events) => events.clone(),
+        None => {
+            let raids = CONFIG.shoutouts.as_ref().and_then(|s| s.raids).unwrap_or(false);
+            let follows = CONFIG.alerts.as_ref().is_some_and(|a| a.follow.is_some());
+            let authorized = authorized || channel.eq_ignore_ascii_case(&CONFIG.twitch_bot_name);
+            let defaults = defaults(authorized, raids, follows);
+            defaults.into_iter().map(str::to_string).collect()
+        },
+    }
+}
+
+///Runs every handler registered for `event_type` on `event`, each in its own task.
+pub fn dispatch(event_type: &str, event: Event) {
+    let handlers: Vec<&Handler> = HANDLERS.iter().filter(|h| h.event == event_type).collect();
+    if handlers.is_empty() {
+        return debug!("no handler for {event_type} notifications");
+    }
+    for handler in handlers {
+        let event = event.clone();
+        tokio::spawn(async move {
+            if let Err(e) = (handler.handle)(event).await {
+                error!("{} handler failed: {e:?}", handler.event);
+            }
+        });
+    }
+}
+
+fn ban(event: Event) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        match event {
+            Event::ChannelBanV1(eventsub::Payload {
+                message: eventsub::Message::Notification(n),
+                ..
+            }) => moderation::handle(ModerationEvent::from(&n)).await,
+            _ => Ok(()),
+        }
+    })
+}
+
+fn unban(event: Event) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        match event {
+            Event::ChannelUnbanV1(eventsub::Payload {
+                message: eventsub::Message::Notification(n),
+                ..
+            }) => moderation::handle(ModerationEvent::from(&n)).await,
+            _ => Ok(()),
+        }
+    })
+}
+
+// role sync re-reads everything itself, it only needs to know something changed
+fn role_sync(_: Event) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async {
+        rolesync::request_sync();
+        Ok(())
+    })
+}
+
+fn stream_online(event: Event) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        let Event::StreamOnlineV1(eventsub::Payload {
+            message: eventsub::Message::Notification(n),
+            ..
+        }) = event
+        else {
+            return Ok(());
+        };
+        let helix =
+            helix::get().ok_or_else(|| eyre::eyre!("Helix client isn't initialised yet"))?;
+        let token = helix.token().await?;
+        golive::online(&helix.client, &token, &n).await
+    })
+}
+
+fn stream_offline(event: Event) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        match event {
+            Event::StreamOfflineV1(eventsub::Payload {
+                message: eventsub::Message::Notification(n),
+                ..
+            }) => golive::offline(&n).await,
+            _ => Ok(()),
+        }
+    })
+}
+
+fn channel_update(event: Event) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        match event {
+            Event::ChannelUpdateV2(eventsub::Payload {
+                message: eventsub::Message::Notification(n),
+                ..
+            }) => golive::update(&n).await,
+            _ => Ok(()),
+        }
+    })
+}
+
+fn raid(event: Event) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        if let Event::ChannelRaidV1(eventsub::Payload {
+            message: eventsub::Message::Notification(n),
+            ..
+        }) = event
+        {
+            shoutouts::raided(
+                n.to_broadcaster_user_login.as_str(),
+                n.to_broadcaster_user_id.as_str(),
+                n.from_broadcaster_user_login.as_str(),
+                n.viewers,
+            )
+            .await;
+        }
+        Ok(())
+    })
+}
+
+fn poll_end(event: Event) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        if let Event::ChannelPollEndV1(eventsub::Payload {
+            message: eventsub::Message::Notification(n),
+            ..
+        }) = event
+        {
+            polls::poll_ended(&n).await;
+        }
+        Ok(())
+    })
+}
+
+fn prediction_end(event: Event) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        if let Event::ChannelPredictionEndV1(eventsub::Payload {
+            message: eventsub::Message::Notification(n),
+            ..
+        }) = event
+        {
+            polls::prediction_ended(&n).await;
+        }
+        Ok(())
+    })
+}
+
+fn follow(event: Event) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        match event {
+            Event::ChannelFollowV2(eventsub::Payload {
+                message: eventsub::Message::Notification(n),
+                ..
+            }) => alerts::followed(&n).await,
+            _ => Ok(()),
+        }
+    })
+}
+
+fn redemption(event: Event) -> BoxFuture<'static, eyre::Result<()>> {
+    Box::pin(async move {
+        match event {
+            Event::ChannelPointsCustomRewardRedemptionAddV1(eventsub::Payload {
+                message: eventsub::Message::Notification(n),
+                ..
+            }) => rewards::redeemed(&n).await,
+            _ => Ok(()),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_by_authorization() {
+        let others = defaults(false, false, true);
+        assert_eq!(others, ["stream.online", "stream.offline", "channel.update"]);
+        let own = defaults(true, true, true);
+        assert!(own.contains(&"channel.ban"));
+        assert!(own.contains(&"channel.follow"));
+        assert!(own.contains(&"channel.raid"));
+        assert!(own.iter().all(|event| handled(event)));
+    }
+
+    #[test]
+    fn handlers_are_sorted() {
+        let events: Vec<&str> = HANDLERS.iter().map(|h| h.event).collect();
+        let mut sorted = events.clone();
+        sorted.sort();
+        assert_eq!(events, sorted);
+    }
+}
@@ -2,17 +2,24 @@
 #[rustfmt::skip]
 use crate::{error, warn, info/*, info_span */,debug, trace};
 
-use chrono::{DateTime, Utc};
 use eyre::Context;
-use serde::Deserialize;
-// use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::io::AsyncWriteExt;
+use futures::{StreamExt, TryStreamExt};
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::time::Instant;
 use tokio_tungstenite::tungstenite;
 use twitch_api::{
     eventsub::{
         self,
-        event::websocket::{EventsubWebsocketData, ReconnectPayload, SessionData, WelcomePayload},
-        Event, EventSubSubscription,
+        channel::{
+            ChannelBanV1, ChannelFollowV2, ChannelModeratorAddV1, ChannelModeratorRemoveV1,
+            ChannelPointsCustomRewardRedemptionAddV1, ChannelPollEndV1, ChannelPredictionEndV1,
+            ChannelRaidV1, ChannelSubscribeV1, ChannelSubscriptionEndV1, ChannelUnbanV1,
+            ChannelUpdateV2, ChannelVipAddV1, ChannelVipRemoveV1,
+        },
+        event::websocket::{EventsubWebsocketData, ReconnectPayload, WelcomePayload},
+        stream::{StreamOfflineV1, StreamOnlineV1},
+        Event, EventSubSubscription, EventSubscription,
     },
     twitch_oauth2::TwitchToken,
     types, HelixClient,
@@ -24,26 +31,65 @@ use crate::{
     utils::non_op_dbg,
 };
 
-#[derive(Clone, Debug, Deserialize)]
-struct InnerEvent {
-    pub user_id: String,
-    pub user_login: String,
-    pub broadcaster_user_id: String,
-    pub broadcaster_user_login: String,
-    pub broadcaster_user_name: String,
-    pub moderator_user_id: String,
-    pub moderator_user_login: String,
-    pub moderator_user_name: String,
-    pub reason: String,
-    pub banned_at: DateTime<Utc>,
-    pub ends_at: Option<DateTime<Utc>>,
-    pub is_permanent: bool,
+///How long to wait for the welcome message on a new connection.
+const WELCOME_TIMEOUT: Duration = Duration::from_secs(10);
+///Extra time allowed past the keepalive timeout before the session counts as lost.
+const KEEPALIVE_GRACE: Duration = Duration::from_secs(5);
+///The first delay before starting a new session.
+const BACKOFF_BASE: Duration = Duration::from_secs(1);
+///The longest delay between attempts to start a new session.
+const BACKOFF_MAX: Duration = Duration::from_secs(120);
+
+///The id of the current websocket session, so channels joined later can subscribe to it.
+static SESSION: std::sync::Mutex<Option<String>> = std::sync::Mutex::new(None);
+
+type Socket =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// Exponential backoff between attempts to start a new session
+#[derive(Debug, Default)]
+pub struct Backoff {
+    attempts: u32,
+}
+
+impl Backoff {
+    /// The delay before the next attempt, doubling each time up to [`BACKOFF_MAX`]
+    pub fn next(&mut self) -> Duration {
+        let delay = BACKOFF_BASE.saturating_mul(2u32.saturating_pow(self.attempts));
+        self.attempts = self.attempts.saturating_add(1);
+        delay.min(BACKOFF_MAX)
+    }
+
+    /// Start over from [`BACKOFF_BASE`], once a session is welcomed
+    pub fn reset(&mut self) {
+        self.attempts = 0;
+    }
+}
+
+/// How long the session may go without any message before it counts as lost
+pub fn keepalive_deadline(keepalive_timeout_seconds: Option<i64>) -> Duration {
+    match keepalive_timeout_seconds.and_then(|secs| u64::try_from(secs).ok()) {
+        Some(secs) => Duration::from_secs(secs) + KEEPALIVE_GRACE,
+        None => WELCOME_TIMEOUT,
+    }
 }
 
-#[derive(Clone, Debug, Deserialize)]
-struct EventRoot {
-    pub subscription: EventSubSubscription,
-    pub event: InnerEvent,
+/// What the connection loop has to do after a message
+#[derive(Debug, PartialEq)]
+pub enum Control {
+    /// Nothing, keep reading
+    Continue,
+    /// A session was welcomed
+    Welcome {
+        /// The id of the session
+        session_id: String,
+        /// How long Twitch may go without sending anything
+        keepalive: Duration,
+    },
+    /// Twitch asked for a new connection to this url
+    Reconnect(String),
+    /// The connection was closed
+    Closed(String),
 }
 
 // WebSockets use user access tokens
@@ -66,14 +112,7 @@ pub struct WebsocketClient {
 impl WebsocketClient {
     /// Connect to the websocket and return the stream
     #[allow(unused)]
-    pub async fn connect(
-        &self,
-    ) -> Result<
-        tokio_tungstenite::WebSocketStream<
-            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
-        >,
-        eyre::Error,
-    > {
+    pub async fn connect(&self) -> Result<Socket, eyre::Error> {
         info!("connecting to twitch");
         let config = tungstenite::protocol::WebSocketConfig {
             max_write_buffer_size: 9 << 14,   // 18 KiB
@@ -95,52 +134,111 @@ impl WebsocketClient {
         Ok(socket)
     }
 
-    /// Run the websocket subscriber
+    /// Run the websocket subscriber, starting a new session whenever one is lost
     #[allow(unused)]
     pub async fn run(mut self) -> eyre::Result<()> {
-        // Establish the stream
-        let mut s = self.connect().await.context("when establishing connection")?;
-        debug_assert!(crate::utils::non_op_trace(format!("{:?}", s)));
-        // Loop over the stream, processing messages as they come in.
+        let mut backoff = Backoff::default();
         loop {
-            tokio::select!(
-            Some(msg) = futures::StreamExt::next(&mut s) => {
-                let msg = match msg {
-                    Err(tungstenite::Error::Protocol(
-                        tungstenite::error::ProtocolError::ResetWithoutClosingHandshake,
-                    )) => {
-                        warn!(
-                            "connection was sent an unexpected frame or was reset, reestablishing it"
-                        );
-                        s = self
-                            .connect()
-                            .await
-                            .context("when reestablishing connection")?;
-                        continue
-                    }
-                    _ => {
-                        if msg.is_err() {
-                            debug_assert!(non_op_dbg(format!("{:?}", &msg)));
+            if let Err(e) = self.session(&mut backoff).await {
+                error!("eventsub session lost: {e:?}");
+            }
+            // a new session starts without any subscriptions, the welcome restores them
+            self.set_session(None);
+            self.connect_url = twitch_api::TWITCH_EVENTSUB_WEBSOCKET_URL.as_str().parse()?;
+            let delay = backoff.next();
+            warn!("starting a new eventsub session in {delay:?}");
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Run one session until it is lost, following any reconnects Twitch asks for
+    async fn session(&mut self, backoff: &mut Backoff) -> eyre::Result<()> {
+        let mut socket = self.connect().await.context("when establishing connection")?;
+        debug_assert!(crate::utils::non_op_trace(format!("{:?}", socket)));
+        // the old connection keeps delivering events until the new one is welcomed
+        let mut reconnecting: Option<Socket> = None;
+        // and may close before then, which isn't the session ending
+        let mut old_closed = false;
+        let mut keepalive = WELCOME_TIMEOUT;
+        let mut deadline = Instant::now() + keepalive;
+        loop {
+            let (msg, from_new) = tokio::select! {
+                // the welcome on the new connection has to win over a close on the old one
+                biased;
+                Some(msg) = next_message(&mut reconnecting) => (Some(msg), true),
+                msg = socket.next(), if !old_closed => (msg, false),
+                _ = tokio::time::sleep_until(deadline) => {
+                    eyre::bail!("no message within the {keepalive:?} keepalive timeout")
+                },
+            };
+            let Some(msg) = msg else {
+                if reconnecting.is_some() {
+                    debug!("old eventsub connection ended while reconnecting");
+                    old_closed = true;
+                    continue;
+                }
+                eyre::bail!("connection ended without a close frame");
+            };
+            deadline = Instant::now() + keepalive;
+            if let Err(e) = &msg {
+                debug_assert!(non_op_dbg(format!("{:?}", e)));
+                if !from_new && reconnecting.is_some() {
+                    debug!("old eventsub connection failed while reconnecting: {e}");
+                    old_closed = true;
+                    continue;
+                }
+            }
+            let msg = msg.context(match from_new {
+                true => "when reconnecting",
+                false => "when getting message",
+            })?;
+            match self.process_message(msg).await? {
+                Control::Continue => {},
+                Control::Welcome { session_id, keepalive: timeout } => {
+                    keepalive = timeout;
+                    deadline = Instant::now() + keepalive;
+                    backoff.reset();
+                    if from_new {
+                        info!("reconnected to eventsub, subscriptions carried over");
+                        if let Some(new) = reconnecting.take() {
+                            socket = new;
+                            old_closed = false;
                         }
-                        msg.context("when getting message")?
-                    },
-                };
-                self.process_message(&mut s, msg).await?
-            })
+                        self.set_session(Some(session_id));
+                    } else {
+                        self.process_welcome_message(&session_id).await?;
+                    }
+                },
+                Control::Reconnect(url) => {
+                    info!("eventsub asked for a reconnect");
+                    self.connect_url = url.parse()?;
+                    reconnecting =
+                        Some(self.connect().await.context("when following a reconnect")?);
+                },
+                Control::Closed(reason) if from_new => {
+                    eyre::bail!("reconnect closed before its welcome: {reason}")
+                },
+                // twitch closes the old connection once the new one is welcomed
+                Control::Closed(reason) if reconnecting.is_some() => {
+                    debug!("old eventsub connection closed while reconnecting: {reason}");
+                    old_closed = true;
+                },
+                Control::Closed(reason) => eyre::bail!("{reason}"),
+            }
         }
     }
 
+    /// Keep [`session_id`] in step with the session of this client, so joins subscribe to it
+    fn set_session(&mut self, session_id: Option<String>) {
+        *current_session() = session_id.clone();
+        self.session_id = session_id;
+    }
+
     /// Process a message from the websocket
     #[allow(unused)]
-    pub async fn process_message(
-        &mut self,
-        sock: &mut tokio_tungstenite::WebSocketStream<
-            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
-        >,
-        msg: tungstenite::Message,
-    ) -> eyre::Result<()> {
+    pub async fn process_message(&mut self, msg: tungstenite::Message) -> eyre::Result<Control> {
         let m = msg.clone();
-        warn!("{m:?}");
+        trace!("{m:?}");
         match msg {
             tungstenite::Message::Text(s) => {
                 // Parse the message into a [twitch_api::eventsub::EventsubWebsocketData]
@@ -148,176 +246,327 @@ impl WebsocketClient {
                 match parsed_msg {
                     EventsubWebsocketData::Welcome {
                         payload: WelcomePayload { session }, ..
-                    }
-                    | EventsubWebsocketData::Reconnect {
+                    } => Ok(Control::Welcome {
+                        session_id: session.id.to_string(),
+                        keepalive: keepalive_deadline(session.keepalive_timeout_seconds),
+                    }),
+                    EventsubWebsocketData::Reconnect {
                         payload: ReconnectPayload { session },
                         ..
-                    } => {
-                        self.process_welcome_message(session).await?;
-                        Ok(())
+                    } => match session.reconnect_url {
+                        Some(url) => Ok(Control::Reconnect(url.to_string())),
+                        None => Err(eyre::eyre!("reconnect message without a reconnect_url")),
                     },
-                    // Here is where you would handle the events you want to listen to
-                    EventsubWebsocketData::Notification { metadata: _, payload } => {
-                        match payload {
-                            Event::ChannelBanV1(eventsub::Payload {
-                                ref message,
-                                ref subscription,
-                                ..
-                            }) => {
-                                let m = message;
-                                let s = subscription;
-                                let e = crate::utils::json::from_str::<EventRoot>(&format!(
-                                    "{:?}",
-                                    payload
-                                ))
-                                .context("Error parsing EventRoot from payload debug impl")?;
-                                let banned_user_id = e.clone().event.user_id;
-                                let user_from_db = crate::db::find_twitch_user_by_id(
-                                    banned_user_id.parse::<u32>()?,
-                                )?;
-                                let discord_users =
-                                    crate::db::find_discord_user_by_twitch_id(user_from_db.tid)?;
-                                eprintln!("[EventRoot]: {discord_users:?}");
-                                // info!(?m, ?s, "got ban event");
-                                eprintln!("[EventRoot]: {e:?}");
-                            },
-                            Event::ChannelUnbanV1(eventsub::Payload { message, .. }) => {
-                                let m = message;
-                                // info!(?m, "got unban event");
-                                eprintln!("{m:?}");
-                            },
-                            _ => {},
-                        };
-                        Ok(())
+                    // handlers are registered in `events`, keyed by subscription type
+                    EventsubWebsocketData::Notification { metadata, payload } => {
+                        super::events::dispatch(&metadata.subscription_type.to_string(), payload);
+                        Ok(Control::Continue)
                     },
                     EventsubWebsocketData::Revocation { metadata, payload: _ } => {
-                        eyre::bail!("got revocation event: {metadata:?}")
+                        warn!("subscription revoked: {metadata:?}");
+                        Ok(Control::Continue)
                     },
                     EventsubWebsocketData::Keepalive { metadata, payload: _ } => {
-                        Ok(trace!("Staying alive: {metadata:?}"))
+                        trace!("Staying alive: {metadata:?}");
+                        Ok(Control::Continue)
                     },
-                    _ => Ok(()),
+                    _ => Ok(Control::Continue),
                 }
             },
-            tungstenite::Message::Close(ocf) => match ocf {
-                Some(cf) => Err(eyre::eyre!("Socket closed [{:?}] with: {}", cf.code, cf.reason)),
-                None => Ok(()),
+            tungstenite::Message::Close(ocf) => Ok(Control::Closed(match ocf {
+                Some(cf) => format!("Socket closed [{:?}] with: {}", cf.code, cf.reason),
+                None => "Socket closed".to_string(),
+            })),
+            tungstenite::Message::Binary(vu8) => {
+                debug!("received binary message: {vu8:?}");
+                Ok(Control::Continue)
             },
-            tungstenite::Message::Binary(vu8) => Ok(debug!("received binary message: {vu8:?}")),
+            // tungstenite answers pings itself
             tungstenite::Message::Ping(vu8) => {
-                let len = sock.get_mut().write(&vu8).await?;
-                Ok(trace!("received ping with length: {len}"))
+                trace!("received ping with length: {}", vu8.len());
+                Ok(Control::Continue)
             },
             tungstenite::Message::Pong(vu8) => {
-                let len = vu8.len();
-                Ok(debug!("received pong with length: {len}"))
+                debug!("received pong with length: {}", vu8.len());
+                Ok(Control::Continue)
+            },
+            tungstenite::Message::Frame(f) => {
+                debug!("Raw frame: {f}");
+                Ok(Control::Continue)
             },
-            tungstenite::Message::Frame(f) => Ok(debug!("Raw frame: {f}")),
         }
     }
 
     #[allow(unused)]
-    pub async fn process_welcome_message(
-        &mut self,
-        data: SessionData<'_>,
-    ) -> Result<(), eyre::Report> {
-        self.session_id = Some(data.id.to_string());
-        if let Some(url) = data.reconnect_url {
-            self.connect_url = url.parse()?;
-        }
+    pub async fn process_welcome_message(&mut self, session_id: &str) -> Result<(), eyre::Report> {
+        self.set_session(Some(session_id.to_string()));
         // check if the token is expired, if it is, request a new token. This only works if using a oauth service for getting a token
         if self.user_token.is_elapsed() {
             self.user_token.refresh_token(&self.client).await?;
         }
-        let transport = eventsub::Transport::websocket(data.id.clone());
-        let channels: Vec<String> = crate::CONFIG.clone().twitch_channels;
+        let transport = eventsub::Transport::websocket(session_id.to_string());
+        let channels: Vec<String> = super::channels::current().await;
         let bot_name = crate::CONFIG.clone().twitch_bot_name;
         assert_eq!(self.user_token.name.clone().take(), bot_name);
+        // subscriptions carry over a reconnect, so only the ones still missing are created
+        let mut active = self.clean_up(session_id).await?;
         for channel in channels {
-            if channel.as_str().to_lowercase() == bot_name {
-                let user_id = self
-                    .client
-                    .get_user_from_login(&UserName::new(channel.clone()), &self.user_token)
-                    .await?
-                    .unwrap_or_else(|| panic!("Unable to retrieve user from: {}", channel.clone()))
-                    .id;
-                match self
-                    .client
-                    .create_eventsub_subscription(
-                        eventsub::channel::ChannelBanV1::broadcaster_user_id(user_id.clone()),
-                        transport.clone(),
-                        &self.user_token,
-                    )
-                    .await
-                {
-                    Ok(i) => info!("[{}] {}", channel.clone(), i.condition.broadcaster_user_id),
-                    Err(e) => error!("[{}] {:?}", channel.clone(), e),
-                };
-                match self
-                    .client
-                    .create_eventsub_subscription(
-                        eventsub::channel::ChannelUnbanV1::broadcaster_user_id(user_id),
-                        transport.clone(),
-                        &self.user_token,
-                    )
-                    .await
-                {
-                    Ok(i) => debug!("[{}] {}", channel.clone(), i.condition.broadcaster_user_id),
-                    Err(e) => error!("[{}] {:?}", channel.clone(), e),
-                };
-            } else {
-                //TODO: Add logic to get a user token from users that aren't the bot itself.
-                info!("ignoring channel `{channel}` as non-bot users aren't supported yet.");
+            let subscribed = subscribe_channel(
+                &self.client,
+                &self.user_token,
+                &channel,
+                &transport,
+                &mut active,
+            );
+            if let Err(e) = subscribed.await {
+                error!("[{channel}] Unable to subscribe: {e:?}");
+            }
+        }
+        let mut kinds: Vec<&str> = active.iter().map(|(kind, _)| kind.as_str()).collect();
+        kinds.sort_unstable();
+        kinds.dedup();
+        info!("{} eventsub subscriptions active: {}", active.len(), kinds.join(", "));
+        Ok(())
+    }
+
+    /// Delete websocket subscriptions left over from earlier sessions, returning the
+    /// `(type, broadcaster id)` of the ones that belong to `session_id`
+    async fn clean_up(&self, session_id: &str) -> eyre::Result<HashSet<(String, String)>> {
+        let mut active = HashSet::new();
+        self.clean_up_with(&self.user_token, session_id, &mut active).await?;
+        // subscriptions created with a broadcaster's token are only listed with that token
+        for broadcaster in super::broadcasters::tokens(&self.client).await {
+            if let Err(e) = self.clean_up_with(&broadcaster, session_id, &mut active).await {
+                warn!("Unable to list the subscriptions of {}: {e:?}", broadcaster.name);
+            }
+        }
+        Ok(active)
+    }
+
+    /// [`clean_up`](Self::clean_up) the subscriptions `token` can see, adding the current ones to
+    /// `active`
+    async fn clean_up_with(
+        &self,
+        token: &Token,
+        session_id: &str,
+        active: &mut HashSet<(String, String)>,
+    ) -> eyre::Result<()> {
+        let subscriptions: Vec<EventSubSubscription> = self
+            .client
+            .get_eventsub_subscriptions(None, None, None, token)
+            .map_ok(|page| futures::stream::iter(page.subscriptions.into_iter().map(Ok)))
+            .try_flatten()
+            .try_collect()
+            .await?;
+        for subscription in subscriptions {
+            let eventsub::TransportResponse::Websocket(ref websocket) = subscription.transport
+            else {
+                continue;
+            };
+            if websocket.session_id == session_id {
+                if let Some(id) = broadcaster_of(&subscription.condition) {
+                    active.insert((subscription.type_.to_string(), id));
+                }
+                continue;
+            }
+            match self.client.delete_eventsub_subscription(subscription.id.clone(), token).await {
+                Ok(_) => {
+                    debug!("deleted stale {} subscription {}", subscription.type_, subscription.id)
+                },
+                Err(e) => warn!("Unable to delete stale subscription {}: {e:?}", subscription.id),
             }
         }
-        info!("listening to ban and unbans");
         Ok(())
     }
 }
 
+/// The session the websocket client was last welcomed with, [`None`] until it is
+pub(crate) fn session_id() -> Option<String> {
+    current_session().clone()
+}
+
+fn current_session() -> std::sync::MutexGuard<'static, Option<String>> {
+    match SESSION.lock() {
+        Ok(session) => session,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// Create the websocket subscriptions `channel` wants that aren't in `active`, adding them to it
+pub(crate) async fn subscribe_channel(
+    client: &HelixClient<'static, reqwest::Client>,
+    bot_token: &Token,
+    channel: &str,
+    transport: &eventsub::Transport,
+    active: &mut HashSet<(String, String)>,
+) -> eyre::Result<()> {
+    let user_id = client
+        .get_user_from_login(&UserName::new(channel.to_string()), bot_token)
+        .await?
+        .ok_or_else(|| eyre::eyre!("Unable to retrieve user from: {channel}"))?
+        .id;
+    // other channels need their broadcaster's token for anything beyond public events
+    let broadcaster = match channel.eq_ignore_ascii_case(&crate::CONFIG.twitch_bot_name) {
+        true => None,
+        false => super::broadcasters::token(client, channel).await.unwrap_or_else(|e| {
+            error!("[{channel}] Unable to load the broadcaster's token: {e:?}");
+            None
+        }),
+    };
+    let token = broadcaster.as_ref().unwrap_or(bot_token);
+    for kind in super::events::wanted(channel, broadcaster.is_some()) {
+        if !super::events::handled(&kind) {
+            warn!("[{channel}] `{kind}` has no handler, subscribing anyway");
+        }
+        if active.contains(&(kind.clone(), user_id.to_string())) {
+            continue;
+        }
+        if subscribe_to(client, token, &kind, channel, user_id.clone(), transport).await {
+            active.insert((kind, user_id.to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Delete every subscription `token` can see for `broadcaster_id`, returning how many were deleted
+pub(crate) async fn unsubscribe_channel<T: TwitchToken + Send + Sync>(
+    client: &HelixClient<'static, reqwest::Client>,
+    token: &T,
+    broadcaster_id: &str,
+) -> eyre::Result<usize> {
+    let subscriptions: Vec<EventSubSubscription> = client
+        .get_eventsub_subscriptions(None, None, None, token)
+        .map_ok(|page| futures::stream::iter(page.subscriptions.into_iter().map(Ok)))
+        .try_flatten()
+        .try_collect()
+        .await?;
+    let mut deleted = 0;
+    for subscription in subscriptions {
+        if broadcaster_of(&subscription.condition).as_deref() != Some(broadcaster_id) {
+            continue;
+        }
+        match client.delete_eventsub_subscription(subscription.id.clone(), token).await {
+            Ok(_) => deleted += 1,
+            Err(e) => warn!("Unable to delete subscription {}: {e:?}", subscription.id),
+        }
+    }
+    Ok(deleted)
+}
+
+/// Subscribe to the type named `kind` for `user_id`, returning whether it was created
+pub(crate) async fn subscribe_to<T: TwitchToken + Send + Sync>(
+    client: &HelixClient<'static, reqwest::Client>,
+    token: &T,
+    kind: &str,
+    channel: &str,
+    user_id: types::UserId,
+    transport: &eventsub::Transport,
+) -> bool {
+    // the typed subscriptions differ for every kind, so each arm only names its own
+    macro_rules! to {
+        ($event:expr) => {
+            subscribe(client, token, channel, $event, transport).await
+        };
+    }
+    let id = user_id;
+    match kind {
+        "channel.ban" => to!(ChannelBanV1::broadcaster_user_id(id)),
+        "channel.channel_points_custom_reward_redemption.add" => {
+            to!(ChannelPointsCustomRewardRedemptionAddV1::broadcaster_user_id(id))
+        },
+        "channel.follow" => {
+            // the token's user has to moderate the channel, the broadcaster does
+            let moderator = token.user_id().map(|m| m.to_owned()).unwrap_or_else(|| id.clone());
+            to!(ChannelFollowV2::new(id, moderator))
+        },
+        "channel.moderator.add" => to!(ChannelModeratorAddV1::broadcaster_user_id(id)),
+        "channel.moderator.remove" => to!(ChannelModeratorRemoveV1::broadcaster_user_id(id)),
+        "channel.poll.end" => to!(ChannelPollEndV1::broadcaster_user_id(id)),
+        "channel.prediction.end" => to!(ChannelPredictionEndV1::broadcaster_user_id(id)),
+        "channel.raid" => to!(ChannelRaidV1::to_broadcaster_user_id(id)),
+        "channel.subscribe" => to!(ChannelSubscribeV1::broadcaster_user_id(id)),
+        "channel.subscription.end" => to!(ChannelSubscriptionEndV1::broadcaster_user_id(id)),
+        "channel.unban" => to!(ChannelUnbanV1::broadcaster_user_id(id)),
+        "channel.update" => to!(ChannelUpdateV2::broadcaster_user_id(id)),
+        "channel.vip.add" => to!(ChannelVipAddV1::broadcaster_user_id(id)),
+        "channel.vip.remove" => to!(ChannelVipRemoveV1::broadcaster_user_id(id)),
+        "stream.offline" => to!(StreamOfflineV1::broadcaster_user_id(id)),
+        "stream.online" => to!(StreamOnlineV1::broadcaster_user_id(id)),
+        _ => {
+            warn!("[{channel}] unknown eventsub subscription type `{kind}`");
+            false
+        },
+    }
+}
+
+/// Subscribe to `event`, logging rather than returning any failure
+async fn subscribe<T: TwitchToken + Send + Sync, E: EventSubscription + Send>(
+    client: &HelixClient<'static, reqwest::Client>,
+    token: &T,
+    channel: &str,
+    event: E,
+    transport: &eventsub::Transport,
+) -> bool {
+    match client.create_eventsub_subscription(event, transport.clone(), token).await {
+        Ok(_) => {
+            debug!("[{channel}] subscribed to {}", E::EVENT_TYPE);
+            true
+        },
+        Err(e) => {
+            error!("[{channel}] {:?}", e);
+            false
+        },
+    }
+}
+
+/// The next message of the connection being reconnected to, if there is one
+async fn next_message(
+    socket: &mut Option<Socket>,
+) -> Option<Result<tungstenite::Message, tungstenite::Error>> {
+    match socket {
+        Some(socket) => socket.next().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// The broadcaster a subscription's condition is for, raids are keyed by the raided channel
+pub(crate) fn broadcaster_of(condition: &serde_json::Value) -> Option<String> {
+    ["broadcaster_user_id", "to_broadcaster_user_id"]
+        .iter()
+        .find_map(|key| condition.get(key)?.as_str())
+        .map(str::to_string)
+}
+
 #[cfg(test)]
 mod tests {
-
     use super::*;
 
-    #[tokio::test]
-    async fn deserialize_eventroot() -> Result<(), serde_json::error::Error> {
-        let payload = r#"{
-            "subscription":{
-                "id":"3934e444-0cc9-44d8-9e03-8b35277dde03",
-                "status":"enabled",
-                "type":"channel.ban",
-                "version":"1",
-                "condition":{
-                    "broadcaster_user_id":"12345678"
-                },
-                "transport":{
-                    "method":"websocket",
-                    "session_id":"AgoQ4JtKY91JT2a8FuNEDgaWeRIGY2VsbC1i"
-                },
-                "created_at":"2024-09-30T05:32:33.999694466Z",
-                "cost":0
-            },
-            "event":{
-                "user_id":"696969690",
-                "user_login":"testbanuser",
-                "user_name":"TestBanUser",
-                "broadcaster_user_id":"12345678",
-                "broadcaster_user_login":"testbroadcaster",
-                "broadcaster_user_name":"TestBroadcaster",
-                "moderator_user_id":"87654321",
-                "moderator_user_login":"testmoderator",
-                "moderator_user_name":"TestModerator",
-                "reason":"",
-                "banned_at":"2024-09-30T05:35:13.221774018Z",
-                "ends_at":null,
-                "is_permanent":true
-            }
-        }"#;
-        let res: Result<EventRoot, serde_json::Error> = crate::utils::json::from_str(&payload);
-        match res {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e),
+    #[test]
+    fn backs_off_exponentially() {
+        let mut backoff = Backoff::default();
+        let delays: Vec<u64> = (0..4).map(|_| backoff.next().as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 8]);
+        for _ in 0..40 {
+            backoff.next();
         }
+        assert_eq!(backoff.next(), BACKOFF_MAX);
+        backoff.reset();
+        assert_eq!(backoff.next(), BACKOFF_BASE);
+    }
+
+    #[test]
+    fn keepalive_deadlines() {
+        assert_eq!(keepalive_deadline(Some(10)), Duration::from_secs(15));
+        assert_eq!(keepalive_deadline(None), WELCOME_TIMEOUT);
+        assert_eq!(keepalive_deadline(Some(-1)), WELCOME_TIMEOUT);
+    }
+
+    #[test]
+    fn finds_condition_broadcaster() {
+        let ban = serde_json::json!({ "broadcaster_user_id": "12345678" });
+        assert_eq!(broadcaster_of(&ban).as_deref(), Some("12345678"));
+        let raid = serde_json::json!({ "from_broadcaster_user_id": "", "to_broadcaster_user_id": "87654321" });
+        assert_eq!(broadcaster_of(&raid).as_deref(), Some("87654321"));
+        assert_eq!(broadcaster_of(&serde_json::json!({})), None);
     }
 }
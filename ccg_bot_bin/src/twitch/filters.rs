@@ -0,0 +1,355 @@
+//!Chat filters for links, capitals, symbol spam, message length and emote count.
+//!
+//!Filters are configured in `[filters]` and only run for the filters that have a table. Each
+//!offence in a row moves a user one step further through `escalation`, starting with deleting the
+//!message and moving on to longer and longer timeouts, until they go `reset_minutes` without one.
+//!Moderators are always exempt, and each filter can exempt subscribers or VIPs too. `!permit`
+//!lets a user post a single link. Actions are recorded in the [audit log](crate::audit).
+
+//crate
+use super::commands::permissions::{badge_level, Level};
+use crate::audit;
+use crate::config::{Filters, LimitFilter, LinkFilter, RatioFilter};
+use crate::db::models::NewAuditEntry;
+use crate::twitch::helix;
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, warn};
+
+use lazy_static::lazy_static;
+
+//std
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+//twitch_api
+use twitch_api::types::UserId;
+
+//twitch_irc
+use twitch_irc::message::PrivmsgMessage;
+
+///How long a `!permit` lasts if it isn't used.
+const PERMIT_LENGTH: Duration = Duration::from_secs(120);
+const DEFAULT_ESCALATION: [u32; 3] = [0, 10, 600];
+///Top level domains a bare `name.tld` has to end in to count as a link, anything else needs a
+///scheme or `www.` so words like `file.txt` or `e.g` are left alone.
+const TLDS: [&str; 39] = [
+    "app", "be", "biz", "ca", "cc", "club", "co", "com", "de", "dev", "eu", "fm", "fr", "gg",
+    "gift", "info", "io", "link", "live", "ly", "me", "net", "nl", "online", "org", "pw", "ru",
+    "shop", "site", "store", "stream", "tk", "to", "top", "tv", "uk", "us", "ws", "xyz",
+];
+
+lazy_static! {
+    ///Users allowed one link, keyed by channel and login.
+    static ref PERMITS: Mutex<HashMap<(String, String), Instant>> = Mutex::new(HashMap::new());
+    ///Offences in a row and when the last one was, keyed by channel and login.
+    static ref STRIKES: Mutex<HashMap<(String, String), (usize, Instant)>> =
+        Mutex::new(HashMap::new());
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+///Why a message was filtered.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Offence {
+    Link,
+    Caps,
+    Symbols,
+    Length,
+    Emotes,
+}
+
+impl fmt::Display for Offence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Offence::Link => write!(f, "posting a link"),
+            Offence::Caps => write!(f, "excessive caps"),
+            Offence::Symbols => write!(f, "symbol spam"),
+            Offence::Length => write!(f, "message too long"),
+            Offence::Emotes => write!(f, "too many emotes"),
+        }
+    }
+}
+
+///The domains of anything in `text` that looks like a link, lowercased.
+pub fn domains(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .filter_map(|word| {
+            let word = word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+            let (scheme, rest) = match word.split_once("://") {
+                Some((_, rest)) => (true, rest),
+                None => (false, word.as_str()),
+            };
+            let host = rest.split(['/', '?', '#', ':']).next().unwrap_or_default();
+            let labels: Vec<&str> = host.split('.').collect();
+            let tld = labels.last().copied().unwrap_or_default();
+            let valid = labels.len() > 1
+                && labels
+                    .iter()
+                    .all(|l| !l.is_empty() && l.chars().all(|c| c.is_alphanumeric() || c == '-'))
+                && tld.len() >= 2
+                && tld.chars().all(|c| c.is_ascii_alphabetic())
+                && (scheme || host.starts_with("www.") || TLDS.contains(&tld));
+            valid.then(|| host.strip_prefix("www.").unwrap_or(host).to_string())
+        })
+        .collect()
+}
+
+///Whether `domain` is one of `allowed` or a subdomain of one.
+pub fn allowed_domain(domain: &str, allowed: &[String]) -> bool {
+    allowed.iter().any(|a| {
+        let a = a.to_lowercase();
+        domain == a || domain.ends_with(&format!(".{a}"))
+    })
+}
+
+///The percentage of `text`'s characters matching `counted` out of those matching `considered`,
+///[`None`] if fewer than `min_length` are considered.
+fn percent(
+    text: &str,
+    min_length: usize,
+    considered: impl Fn(char) -> bool,
+    counted: impl Fn(char) -> bool,
+) -> Option<usize> {
+    let total = text.chars().filter(|c| considered(*c)).count();
+    if total < min_length.max(1) {
+        return None;
+    }
+    Some(text.chars().filter(|c| considered(*c) && counted(*c)).count() * 100 / total)
+}
+
+fn too_much(filter: &RatioFilter, percent: Option<usize>) -> bool {
+    percent.is_some_and(|p| p > usize::from(filter.max_percent.unwrap_or(70)))
+}
+
+///Whether a filter with the `exempt` setting skips users at `level`, moderators always are.
+fn exempt(setting: &Option<String>, level: Level) -> bool {
+    let lowest = match setting.as_deref().map(str::parse::<Level>) {
+        Some(Ok(lowest)) => lowest.min(Level::Moderator),
+        Some(Err(e)) => {
+            warn!("{e}, only exempting moderators from the filter");
+            Level::Moderator
+        },
+        None => Level::Subscriber(1),
+    };
+    level >= lowest
+}
+
+///Checks `message` against `filters`, returning the first offence found.
+///
+///Messages with a link only pass if the sender was given a permit, using it up.
+pub fn check(filters: &Filters, message: &PrivmsgMessage) -> Option<Offence> {
+    let level = badge_level(&message.badges);
+    let text = message.message_text.as_str();
+    let offence = check_text(filters, level, text, message.emotes.len())?;
+    if offence == Offence::Link && take_permit(&message.channel_login, &message.sender.login) {
+        return None;
+    }
+    Some(offence)
+}
+
+fn check_text(filters: &Filters, level: Level, text: &str, emotes: usize) -> Option<Offence> {
+    if let Some(LinkFilter { allowed_domains, exempt: e }) = &filters.links {
+        let allowed = allowed_domains.as_deref().unwrap_or_default();
+        if !exempt(e, level) && domains(text).iter().any(|d| !allowed_domain(d, allowed)) {
+            return Some(Offence::Link);
+        }
+    }
+    if let Some(filter) = filters.caps.as_ref().filter(|f| !exempt(&f.exempt, level)) {
+        let caps =
+            percent(text, filter.min_length.unwrap_or(15), char::is_alphabetic, char::is_uppercase);
+        if too_much(filter, caps) {
+            return Some(Offence::Caps);
+        }
+    }
+    if let Some(filter) = filters.symbols.as_ref().filter(|f| !exempt(&f.exempt, level)) {
+        let symbols = percent(
+            text,
+            filter.min_length.unwrap_or(15),
+            |c| !c.is_whitespace(),
+            |c| !c.is_alphanumeric(),
+        );
+        if too_much(filter, symbols) {
+            return Some(Offence::Symbols);
+        }
+    }
+    let over = |filter: &Option<LimitFilter>, count: usize| {
+        filter
+            .as_ref()
+            .is_some_and(|f| !exempt(&f.exempt, level) && f.max.is_some_and(|max| count > max))
+    };
+    if over(&filters.length, text.chars().count()) {
+        return Some(Offence::Length);
+    }
+    if over(&filters.emotes, emotes) {
+        return Some(Offence::Emotes);
+    }
+    None
+}
+
+///Lets `login` post one link in `channel`.
+pub fn permit(channel: &str, login: &str) {
+    let key = (channel.to_lowercase(), login.to_lowercase());
+    let mut permits = lock(&PERMITS);
+    // permits nobody used are dropped once they would have run out anyway
+    permits.retain(|_, given| given.elapsed() < PERMIT_LENGTH);
+    permits.insert(key, Instant::now());
+}
+
+fn take_permit(channel: &str, login: &str) -> bool {
+    let key = (channel.to_lowercase(), login.to_lowercase());
+    lock(&PERMITS).remove(&key).is_some_and(|given| given.elapsed() < PERMIT_LENGTH)
+}
+
+///Counts an offence by `login` and returns the timeout in seconds it earns, `0` to only delete.
+fn strike(filters: &Filters, channel: &str, login: &str, now: Instant) -> u32 {
+    let reset = Duration::from_secs(filters.reset_minutes.unwrap_or(60) * 60);
+    let escalation = filters.escalation.as_deref().unwrap_or(&DEFAULT_ESCALATION);
+    let mut strikes = lock(&STRIKES);
+    strikes.retain(|_, (_, last)| now.duration_since(*last) < reset);
+    let entry = strikes.entry((channel.to_string(), login.to_string())).or_insert((0, now));
+    if now.duration_since(entry.1) >= reset {
+        entry.0 = 0;
+    }
+    let step = entry.0;
+    *entry = (step + 1, now);
+    escalation.get(step).or(escalation.last()).copied().unwrap_or_default()
+}
+
+///Filters `message` if it breaks any filter, returning whether it was filtered.
+///
+///The verdict is immediate, deleting the message or timing the user out happens in the
+///background so chat isn't held up by Helix.
+#[allow(unused)]
+pub fn enforce(message: &PrivmsgMessage) -> bool {
+    let Some(filters) = CONFIG.filters.as_ref() else {
+        return false;
+    };
+    let Some(offence) = check(filters, message) else {
+        return false;
+    };
+    let timeout = strike(filters, &message.channel_login, &message.sender.login, Instant::now());
+    let message = message.clone();
+    tokio::spawn(async move {
+        if let Err(e) = punish(&message, offence, timeout).await {
+            let (login, channel) = (&message.sender.login, &message.channel_login);
+            error!("Unable to filter {login} in #{channel}: {e:?}");
+        }
+    });
+    true
+}
+
+async fn punish(message: &PrivmsgMessage, offence: Offence, timeout: u32) -> eyre::Result<()> {
+    let helix = helix::get().ok_or_else(|| eyre::eyre!("Helix client isn't initialised yet"))?;
+    let token = helix.token().await?;
+    let broadcaster = UserId::from(message.channel_id.clone());
+    let target = UserId::from(message.sender.id.clone());
+    let reason = offence.to_string();
+    if timeout == 0 {
+        helix
+            .client
+            .delete_chat_message(&broadcaster, &token.uid, message.message_id.as_str(), &token)
+            .await?;
+    } else {
+        helix
+            .client
+            .ban_user(&target, reason.as_str(), timeout, &broadcaster, &token.uid, &token)
+            .await?;
+    }
+    audit::record(NewAuditEntry {
+        platform: "twitch".to_string(),
+        channel: message.channel_login.clone(),
+        moderator: CONFIG.twitch_bot_name.to_lowercase(),
+        action: if timeout == 0 { "delete" } else { "timeout" }.to_string(),
+        target: message.sender.login.clone(),
+        reason: Some(reason),
+        duration_seconds: Some(timeout).filter(|t| *t > 0),
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filters() -> Filters {
+        Filters {
+            escalation: None,
+            reset_minutes: None,
+            links: Some(LinkFilter {
+                allowed_domains: Some(vec!["twitch.tv".to_string()]),
+                exempt: None,
+            }),
+            caps: Some(RatioFilter::default()),
+            symbols: Some(RatioFilter { max_percent: Some(50), ..Default::default() }),
+            length: Some(LimitFilter { max: Some(40), exempt: Some("vip".to_string()) }),
+            emotes: Some(LimitFilter { max: Some(3), exempt: None }),
+        }
+    }
+
+    #[test]
+    fn finds_domains() {
+        assert_eq!(
+            domains("see https://www.Example.com/path and clips.twitch.tv/abc, ok."),
+            vec!["example.com", "clips.twitch.tv"]
+        );
+        assert!(domains("no links here... 3.14 or v1.2").is_empty());
+        assert!(domains("open file.txt, i.e. the readme.md").is_empty());
+        assert_eq!(domains("http://file.txt www.some.thing"), vec!["file.txt", "some.thing"]);
+        assert!(allowed_domain("clips.twitch.tv", &["twitch.tv".to_string()]));
+        assert!(!allowed_domain("nottwitch.tv", &["twitch.tv".to_string()]));
+    }
+
+    #[test]
+    fn checks_each_filter() {
+        let f = filters();
+        let viewer = Level::Everyone;
+        assert_eq!(check_text(&f, viewer, "go to example.com", 0), Some(Offence::Link));
+        assert_eq!(check_text(&f, viewer, "watch twitch.tv/zoes17", 0), None);
+        assert_eq!(check_text(&f, viewer, "THIS IS ALL CAPS YELLING", 0), Some(Offence::Caps));
+        assert_eq!(check_text(&f, viewer, "OK", 0), None);
+        assert_eq!(check_text(&f, viewer, "!!!!!!!!!!!!!!!!!!!!", 0), Some(Offence::Symbols));
+        assert_eq!(check_text(&f, viewer, &"a ".repeat(30), 0), Some(Offence::Length));
+        assert_eq!(check_text(&f, viewer, "Kappa Kappa Kappa Kappa", 4), Some(Offence::Emotes));
+        assert_eq!(check_text(&f, viewer, "just chatting", 1), None);
+    }
+
+    #[test]
+    fn exemptions() {
+        let f = filters();
+        let sub = Level::Subscriber(1);
+        assert_eq!(check_text(&f, sub, "go to example.com", 0), None);
+        assert_eq!(check_text(&f, sub, &"a ".repeat(30), 0), Some(Offence::Length));
+        assert_eq!(check_text(&f, Level::Vip, &"a ".repeat(30), 0), None);
+        assert!(exempt(&Some("broadcaster".to_string()), Level::Moderator));
+    }
+
+    #[test]
+    fn permits_one_link() {
+        permit("FilterChannel", "TestUser");
+        assert!(take_permit("filterchannel", "testuser"));
+        assert!(!take_permit("filterchannel", "testuser"));
+    }
+
+    #[test]
+    fn escalates_and_resets() {
+        let f = filters();
+        let start = Instant::now();
+        let strikes: Vec<u32> =
+            (0..4).map(|_| strike(&f, "filterchannel", "spammer", start)).collect();
+        assert_eq!(strikes, vec![0, 10, 600, 600]);
+        strike(&f, "filterchannel", "onetime", start);
+        assert_eq!(strike(&f, "filterchannel", "spammer", start + Duration::from_secs(3600)), 0);
+        // strikes past the reset are forgotten
+        let key = ("filterchannel".to_string(), "onetime".to_string());
+        assert!(!lock(&STRIKES).contains_key(&key));
+    }
+}
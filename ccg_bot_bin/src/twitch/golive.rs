@@ -0,0 +1,256 @@
+//!Go-live announcements posted to Discord.
+//!
+//!When a channel goes live an embed with the stream's title, game and thumbnail is posted to the
+//!configured announcement channel, pinging the announcement role if one is set. The embed is
+//!kept up to date by `channel.update` and edited to show how long the stream ran once it ends.
+
+//crate
+use crate::discord::builders::discordembed::DiscordEmbed;
+use crate::discord::HTTP;
+use crate::twitch::tokens::Token;
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, info, debug};
+
+//chrono
+use chrono::{DateTime, Duration, Utc};
+
+use lazy_static::lazy_static;
+
+//serenity
+use serenity::all::{
+    ChannelId, CreateAllowedMentions, CreateEmbed, CreateEmbedFooter, CreateMessage, EditMessage,
+    MessageId, RoleId, Timestamp,
+};
+
+//std
+use std::collections::HashMap;
+
+//tokio
+use tokio::sync::Mutex;
+
+//twitch_api
+use twitch_api::{
+    eventsub::{
+        channel::ChannelUpdateV2Payload,
+        stream::{StreamOfflineV1Payload, StreamOnlineV1Payload},
+    },
+    helix::streams::GetStreamsRequest,
+    HelixClient,
+};
+
+///Size substituted into the `{width}x{height}` placeholders of a stream thumbnail url.
+const THUMBNAIL_SIZE: (&str, &str) = ("1280", "720");
+
+///Twitch purple.
+const LIVE_COLOR: u32 = 0x9146FF;
+
+///Grey used once the stream has ended.
+const OFFLINE_COLOR: u32 = 0x747F8D;
+
+lazy_static! {
+    ///The announcement of every stream that is currently live, keyed by broadcaster login.
+    static ref LIVE: Mutex<HashMap<String, Announcement>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Clone, Debug)]
+struct Announcement {
+    message_id: MessageId,
+    broadcaster_name: String,
+    broadcaster_login: String,
+    title: String,
+    game: String,
+    thumbnail_url: Option<String>,
+    started_at: DateTime<Utc>,
+}
+
+impl Announcement {
+    fn embed(&self, ended_at: Option<DateTime<Utc>>) -> CreateEmbed {
+        let url = format!("https://twitch.tv/{}", self.broadcaster_login);
+        // Discord rejects empty titles and field values, which Twitch happily sends
+        let title = match self.title.trim() {
+            "" => format!("{} on Twitch", self.broadcaster_name),
+            title => title.to_string(),
+        };
+        let mut embed = DiscordEmbed::new().title(title).url_object(url);
+        if !self.game.trim().is_empty() {
+            embed = embed.field("Game", self.game.clone(), true);
+        }
+        embed = match ended_at {
+            Some(ended_at) => embed
+                .color(OFFLINE_COLOR)
+                .description(format!("{} was live", self.broadcaster_name))
+                .field("Duration", format_duration(ended_at - self.started_at), true)
+                .footer(CreateEmbedFooter::new("Stream ended"))
+                .timestamp(to_timestamp(ended_at)),
+            None => embed
+                .color(LIVE_COLOR)
+                .description(format!("{} is now live!", self.broadcaster_name))
+                .footer(CreateEmbedFooter::new("Live since"))
+                .timestamp(to_timestamp(self.started_at)),
+        };
+        if let (Some(thumbnail), None) = (&self.thumbnail_url, ended_at) {
+            // cache bust, Discord would otherwise keep showing the first frame it fetched
+            embed = embed.image(format!("{thumbnail}?t={}", Utc::now().timestamp()));
+        }
+        embed.build()
+    }
+}
+
+fn to_timestamp(time: DateTime<Utc>) -> Timestamp {
+    Timestamp::from_unix_timestamp(time.timestamp()).unwrap_or_else(|_| Timestamp::now())
+}
+
+fn announcement_channel() -> Option<ChannelId> {
+    let Some(id) = CONFIG.announcement_channel_id.as_ref() else {
+        debug!("no announcement channel configured, skipping go-live announcement");
+        return None;
+    };
+    match id.parse::<u64>() {
+        Ok(id) if id != 0 => Some(ChannelId::new(id)),
+        _ => {
+            error!("invalid announcement channel_id `{id}`");
+            None
+        },
+    }
+}
+
+fn announcement_role() -> Option<RoleId> {
+    CONFIG.announcement_role_id.as_ref()?.parse::<u64>().ok().filter(|id| *id != 0).map(RoleId::new)
+}
+
+///Posts the go-live announcement for the stream in `payload`.
+pub async fn online(
+    client: &HelixClient<'static, reqwest::Client>,
+    token: &Token,
+    payload: &StreamOnlineV1Payload,
+) -> eyre::Result<()> {
+    let Some(channel_id) = announcement_channel() else {
+        return Ok(());
+    };
+    let login = payload.broadcaster_user_login.to_string();
+    if LIVE.lock().await.contains_key(&login) {
+        debug!("{login} is already announced as live");
+        return Ok(());
+    }
+    let ids = [payload.broadcaster_user_id.clone()];
+    let request = GetStreamsRequest::user_ids(&ids[..]);
+    let stream = client.req_get(request, token).await?.data.into_iter().next();
+    let mut announcement = Announcement {
+        message_id: MessageId::default(),
+        broadcaster_name: payload.broadcaster_user_name.to_string(),
+        broadcaster_login: login.clone(),
+        title: String::new(),
+        game: String::new(),
+        thumbnail_url: None,
+        started_at: DateTime::parse_from_rfc3339(payload.started_at.as_str())
+            .map(|t| t.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now()),
+    };
+    if let Some(stream) = stream {
+        announcement.title = stream.title;
+        announcement.game = stream.game_name;
+        announcement.thumbnail_url = Some(thumbnail_url(&stream.thumbnail_url));
+    }
+    let mut message = CreateMessage::new().embed(announcement.embed(None));
+    if let Some(role) = announcement_role() {
+        message = message
+            .content(format!("<@&{role}>"))
+            .allowed_mentions(CreateAllowedMentions::new().roles(vec![role]));
+    }
+    announcement.message_id = channel_id.send_message(&*HTTP, message).await?.id;
+    info!("announced {login} going live");
+    LIVE.lock().await.insert(login, announcement);
+    Ok(())
+}
+
+///Edits the announcement for the stream in `payload` to show how long it ran.
+pub async fn offline(payload: &StreamOfflineV1Payload) -> eyre::Result<()> {
+    let Some(channel_id) = announcement_channel() else {
+        return Ok(());
+    };
+    let login = payload.broadcaster_user_login.to_string();
+    let Some(announcement) = LIVE.lock().await.remove(&login) else {
+        debug!("{login} went offline without an announcement to edit");
+        return Ok(());
+    };
+    let edit = EditMessage::new().embed(announcement.embed(Some(Utc::now())));
+    channel_id.edit_message(&*HTTP, announcement.message_id, edit).await?;
+    info!("{login} went offline");
+    Ok(())
+}
+
+///Updates the title and game shown on a live announcement.
+pub async fn update(payload: &ChannelUpdateV2Payload) -> eyre::Result<()> {
+    let Some(channel_id) = announcement_channel() else {
+        return Ok(());
+    };
+    let login = payload.broadcaster_user_login.to_string();
+    let announcement = {
+        let mut live = LIVE.lock().await;
+        let Some(announcement) = live.get_mut(&login) else {
+            return Ok(());
+        };
+        announcement.title = payload.title.clone();
+        announcement.game = payload.category_name.clone();
+        announcement.clone()
+    };
+    let edit = EditMessage::new().embed(announcement.embed(None));
+    channel_id.edit_message(&*HTTP, announcement.message_id, edit).await?;
+    debug!("updated go-live announcement for {login}");
+    Ok(())
+}
+
+///Fills in the size placeholders of a Helix stream thumbnail url.
+pub fn thumbnail_url(template: &str) -> String {
+    template.replace("{width}", THUMBNAIL_SIZE.0).replace("{height}", THUMBNAIL_SIZE.1)
+}
+
+///Formats a stream duration as `1h 2m`, or `2m` for streams shorter than an hour.
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes().max(0);
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{m}m"),
+        (h, m) => format!("{h}h {m}m"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format_duration(Duration::seconds(59)), "0m");
+        assert_eq!(format_duration(Duration::minutes(42)), "42m");
+        assert_eq!(format_duration(Duration::minutes(125)), "2h 5m");
+        assert_eq!(format_duration(Duration::seconds(-5)), "0m");
+    }
+
+    #[test]
+    fn embeds_without_title_or_game() {
+        let announcement = Announcement {
+            message_id: MessageId::default(),
+            broadcaster_name: "ZoeS17".to_string(),
+            broadcaster_login: "zoes17".to_string(),
+            title: String::new(),
+            game: String::new(),
+            thumbnail_url: None,
+            started_at: Utc::now(),
+        };
+        let embed = serde_json::to_value(announcement.embed(None)).unwrap();
+        assert_eq!(embed["title"], "ZoeS17 on Twitch");
+        assert!(embed["fields"].as_array().map_or(true, |f| f.is_empty()));
+    }
+
+    #[test]
+    fn fills_thumbnail_size() {
+        let template =
+            "https://static-cdn.jtvnw.net/previews-ttv/live_user_zoes17-{width}x{height}.jpg";
+        assert_eq!(
+            thumbnail_url(template),
+            "https://static-cdn.jtvnw.net/previews-ttv/live_user_zoes17-1280x720.jpg"
+        );
+    }
+}
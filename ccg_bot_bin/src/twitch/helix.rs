@@ -0,0 +1,46 @@
+//!Shared Helix client for features that live outside the EventSub websocket.
+//!
+//![`super::new`] stores the bot's [`Token`] here once chat is connected, anything that needs to
+//!call Helix can then borrow the client and a fresh token through [`get`].
+
+//crate
+use crate::twitch::tokens::Token;
+
+//std
+use std::sync::OnceLock;
+
+//tokio
+use tokio::sync::Mutex;
+
+//twitch_api
+use twitch_api::{twitch_oauth2::TwitchToken, HelixClient};
+
+static HELIX: OnceLock<Helix> = OnceLock::new();
+
+///A [`HelixClient`] paired with the bot's user token.
+pub(crate) struct Helix {
+    pub client: HelixClient<'static, reqwest::Client>,
+    token: Mutex<Token>,
+}
+
+impl Helix {
+    ///Returns the bot's token, refreshing it first if it has expired.
+    pub async fn token(&self) -> eyre::Result<Token> {
+        let mut token = self.token.lock().await;
+        if token.is_elapsed() {
+            token.refresh_token(&self.client).await?;
+        }
+        Ok(token.clone())
+    }
+}
+
+///Makes `client` and `token` available through [`get`], only the first call has any effect.
+#[allow(unused)]
+pub(crate) fn init(client: HelixClient<'static, reqwest::Client>, token: Token) {
+    let _ = HELIX.set(Helix { client, token: Mutex::new(token) });
+}
+
+///The shared [`Helix`] client, [`None`] until Twitch chat has connected.
+pub(crate) fn get() -> Option<&'static Helix> {
+    HELIX.get()
+}
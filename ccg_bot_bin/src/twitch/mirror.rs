@@ -0,0 +1,474 @@
+//!Mirrors Twitch chat into Discord through webhooks.
+//!
+//!Messages are queued as they arrive and flushed once a second by [`run`]. Each message is posted
+//!with the sender's display name and profile picture so it reads like native chat, but when more
+//!than [`BATCH_THRESHOLD`] messages are waiting they are combined into as few posts as possible.
+//!Deleted messages (`CLEARMSG`) are removed from Discord and timeouts or bans (`CLEARCHAT`) strike
+//!through whatever was mirrored for that user. While a channel's webhook can't be loaded its queue
+//!is dropped rather than kept, and loading it is retried after [`WEBHOOK_RETRY`].
+
+//crate
+use crate::config::Mirror;
+use crate::discord::HTTP;
+use crate::twitch::helix;
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, debug};
+
+use lazy_static::lazy_static;
+
+//serenity
+use serenity::all::{
+    CreateAllowedMentions, EditWebhookMessage, ExecuteWebhook, MessageId, Webhook,
+};
+
+//std
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+//tokio
+use tokio::sync::Mutex;
+
+//twitch_irc
+use twitch_irc::message::PrivmsgMessage;
+
+///More queued messages than this in one flush are batched together.
+pub const BATCH_THRESHOLD: usize = 3;
+///How often queued messages are flushed to Discord.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+///How many posted messages per channel are remembered for deletions and timeouts.
+const HISTORY: usize = 200;
+///Discord rejects message content longer than this.
+const DISCORD_MESSAGE_LIMIT: usize = 2000;
+///Most messages queued per channel, the oldest are dropped past this.
+const MAX_PENDING: usize = 500;
+///How long to wait before loading a webhook again after it failed.
+const WEBHOOK_RETRY: Duration = Duration::from_secs(60);
+///How long a looked up profile picture is reused.
+const AVATAR_TTL: Duration = Duration::from_secs(60 * 60);
+
+lazy_static! {
+    static ref MIRRORS: Mutex<HashMap<String, MirrorState>> = Mutex::new(
+        CONFIG
+            .mirrors
+            .iter()
+            .map(|m| (m.twitch_channel.to_lowercase(), MirrorState::new(m.clone())))
+            .collect()
+    );
+    static ref AVATARS: Mutex<HashMap<String, (Option<String>, Instant)>> =
+        Mutex::new(HashMap::new());
+}
+
+///A single chat line waiting for, or already posted to, Discord.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MirroredLine {
+    pub twitch_id: String,
+    pub user_id: String,
+    pub user_login: String,
+    pub display_name: String,
+    pub text: String,
+    pub struck: bool,
+}
+
+impl MirroredLine {
+    fn render(&self, batched: bool) -> String {
+        let line = if batched {
+            format!("**{}**: {}", escape_markdown(&self.display_name), self.text)
+        } else {
+            self.text.clone()
+        };
+        if self.struck {
+            format!("~~{line}~~")
+        } else {
+            line
+        }
+    }
+}
+
+impl From<&PrivmsgMessage> for MirroredLine {
+    fn from(m: &PrivmsgMessage) -> Self {
+        Self {
+            twitch_id: m.message_id.clone(),
+            user_id: m.sender.id.clone(),
+            user_login: m.sender.login.clone(),
+            display_name: m.sender.name.clone(),
+            text: escape_markdown(&m.message_text),
+            struck: false,
+        }
+    }
+}
+
+///A Discord message made of one or more mirrored lines.
+#[derive(Clone, Debug)]
+struct Posted {
+    discord_id: MessageId,
+    batched: bool,
+    lines: Vec<MirroredLine>,
+}
+
+impl Posted {
+    fn content(&self) -> String {
+        render_lines(&self.lines, self.batched)
+    }
+}
+
+struct MirrorState {
+    config: Mirror,
+    webhook: Option<Webhook>,
+    ///When loading the webhook may be tried again after it failed.
+    retry_at: Option<Instant>,
+    pending: Vec<MirroredLine>,
+    posted: VecDeque<Posted>,
+}
+
+impl MirrorState {
+    fn new(config: Mirror) -> Self {
+        Self { config, webhook: None, retry_at: None, pending: vec![], posted: VecDeque::new() }
+    }
+
+    fn queue(&mut self, line: MirroredLine) {
+        if self.pending.len() >= MAX_PENDING {
+            self.pending.remove(0);
+        }
+        self.pending.push(line);
+    }
+
+    fn remember(&mut self, posted: Posted) {
+        self.posted.push_back(posted);
+        while self.posted.len() > HISTORY {
+            self.posted.pop_front();
+        }
+    }
+
+    ///Strikes every line matching `matches`, deleting unbatched posts instead when `delete` is set,
+    ///and returns the Discord changes that brings so they can be made without holding the lock.
+    fn clear<F: Fn(&MirroredLine) -> bool>(&mut self, matches: F, delete: bool) -> Vec<Change> {
+        self.pending.retain(|l| !matches(l));
+        let mut changes = vec![];
+        for posted in self.posted.iter_mut() {
+            let mut changed = false;
+            for line in posted.lines.iter_mut().filter(|l| !l.struck && matches(l)) {
+                line.struck = true;
+                changed = true;
+            }
+            if !changed {
+                continue;
+            }
+            changes.push(match delete && !posted.batched {
+                true => Change::Delete(posted.discord_id),
+                false => Change::Edit(posted.discord_id, posted.content()),
+            });
+        }
+        self.posted.retain(|p| !changes.contains(&Change::Delete(p.discord_id)));
+        changes
+    }
+}
+
+///An edit to something already mirrored, worked out by [`MirrorState::clear`].
+#[derive(Clone, Debug, PartialEq)]
+enum Change {
+    Delete(MessageId),
+    Edit(MessageId, String),
+}
+
+///The webhook mirroring `channel_login`, loaded on first use without holding the lock while
+///Discord answers. [`None`] until [`WEBHOOK_RETRY`] passes once loading it failed.
+async fn webhook(channel_login: &str) -> Option<Webhook> {
+    let url = {
+        let mirrors = MIRRORS.lock().await;
+        let state = mirrors.get(channel_login)?;
+        if let Some(webhook) = &state.webhook {
+            return Some(webhook.clone());
+        }
+        if state.retry_at.is_some_and(|at| Instant::now() < at) {
+            return None;
+        }
+        state.config.webhook_url.clone()
+    };
+    let loaded = Webhook::from_url(&*HTTP, &url).await;
+    let mut mirrors = MIRRORS.lock().await;
+    let state = mirrors.get_mut(channel_login)?;
+    match loaded {
+        Ok(webhook) => {
+            state.retry_at = None;
+            state.webhook = Some(webhook.clone());
+            Some(webhook)
+        },
+        Err(e) => {
+            error!(
+                "Unable to load webhook for #{channel_login}, retrying in {WEBHOOK_RETRY:?}: {e}"
+            );
+            state.retry_at = Some(Instant::now() + WEBHOOK_RETRY);
+            None
+        },
+    }
+}
+
+///Makes `changes` in the background so chat isn't held up by Discord.
+fn apply(channel_login: &str, changes: Vec<Change>) {
+    if changes.is_empty() {
+        return;
+    }
+    let channel_login = channel_login.to_string();
+    tokio::spawn(async move {
+        let Some(webhook) = webhook(&channel_login).await else {
+            return;
+        };
+        for change in changes {
+            match change {
+                Change::Delete(id) => {
+                    if let Err(e) = webhook.delete_message(&*HTTP, None, id).await {
+                        error!("Unable to delete mirrored message {id}: {e}");
+                    }
+                },
+                Change::Edit(id, content) => {
+                    let builder = EditWebhookMessage::new()
+                        .content(content)
+                        .allowed_mentions(CreateAllowedMentions::new());
+                    if let Err(e) = webhook.edit_message(&*HTTP, id, builder).await {
+                        error!("Unable to strike mirrored message {id}: {e}");
+                    }
+                },
+            }
+        }
+    });
+}
+
+async fn execute(webhook: &Webhook, builder: ExecuteWebhook) -> Option<MessageId> {
+    match webhook.execute(&*HTTP, true, builder).await {
+        Ok(Some(message)) => Some(message.id),
+        Ok(None) => None,
+        Err(e) => {
+            error!("Unable to mirror to webhook {}: {e}", webhook.id);
+            None
+        },
+    }
+}
+
+async fn avatar(user_id: &str) -> Option<String> {
+    if let Some((avatar, at)) = AVATARS.lock().await.get(user_id) {
+        if at.elapsed() < AVATAR_TTL {
+            return avatar.clone();
+        }
+    }
+    let helix = helix::get()?;
+    let token = helix.token().await.ok()?;
+    let avatar = match helix.client.get_user_from_id(user_id, &token).await {
+        Ok(user) => user.and_then(|u| u.profile_image_url),
+        Err(e) => {
+            debug!("Unable to look up avatar for {user_id}: {e}");
+            return None;
+        },
+    };
+    let mut avatars = AVATARS.lock().await;
+    avatars.retain(|_, (_, at)| at.elapsed() < AVATAR_TTL);
+    avatars.insert(user_id.to_string(), (avatar.clone(), Instant::now()));
+    avatar
+}
+
+///Renders `lines` as one Discord message.
+fn render_lines(lines: &[MirroredLine], batched: bool) -> String {
+    lines.iter().map(|l| l.render(batched)).collect::<Vec<_>>().join("\n")
+}
+
+///Splits `lines` into groups that each fit in a single Discord message.
+pub fn batch(lines: Vec<MirroredLine>) -> Vec<Vec<MirroredLine>> {
+    let mut batches: Vec<Vec<MirroredLine>> = vec![];
+    let mut current: Vec<MirroredLine> = vec![];
+    let mut length = 0;
+    for line in lines {
+        let rendered = line.render(true).chars().count() + 1;
+        if !current.is_empty() && length + rendered > DISCORD_MESSAGE_LIMIT {
+            batches.push(std::mem::take(&mut current));
+            length = 0;
+        }
+        length += rendered;
+        current.push(line);
+    }
+    if !current.is_empty() {
+        batches.push(current);
+    }
+    batches
+}
+
+///Escapes Discord markdown so chat, emotes included, shows up exactly as it was typed.
+pub fn escape_markdown(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '*' | '_' | '~' | '`' | '|' | '>' | '\\' | '#' | '[' | ']') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+///Queues `message` if its channel is mirrored.
+#[allow(unused)]
+pub async fn push(message: &PrivmsgMessage) {
+    if let Some(state) = MIRRORS.lock().await.get_mut(&message.channel_login) {
+        state.queue(MirroredLine::from(message));
+    }
+}
+
+///Removes a single deleted message from the mirror.
+#[allow(unused)]
+pub async fn clear_message(channel_login: &str, message_id: &str) {
+    let changes = match MIRRORS.lock().await.get_mut(channel_login) {
+        Some(state) => state.clear(|l| l.twitch_id == message_id, true),
+        None => return,
+    };
+    apply(channel_login, changes);
+}
+
+///Strikes through everything mirrored from `user_login`, or the whole channel for [`None`].
+#[allow(unused)]
+pub async fn clear_user(channel_login: &str, user_login: Option<&str>) {
+    let changes = match MIRRORS.lock().await.get_mut(channel_login) {
+        Some(state) => state.clear(|l| user_login.map_or(true, |u| l.user_login == u), false),
+        None => return,
+    };
+    apply(channel_login, changes);
+}
+
+///Posts everything queued for `channel_login`, the lock is never held while Discord answers so
+///chat isn't held up by it.
+async fn flush(channel_login: &str) {
+    if MIRRORS.lock().await.get(channel_login).map_or(true, |s| s.pending.is_empty()) {
+        return;
+    }
+    let Some(webhook) = webhook(channel_login).await else {
+        // nothing can be posted until the webhook loads again, so the queue mustn't grow meanwhile
+        if let Some(state) = MIRRORS.lock().await.get_mut(channel_login) {
+            debug!("dropping {} lines for #{channel_login} without a webhook", state.pending.len());
+            state.pending.clear();
+        }
+        return;
+    };
+    let pending = match MIRRORS.lock().await.get_mut(channel_login) {
+        Some(state) => state.pending.drain(..).collect::<Vec<MirroredLine>>(),
+        None => return,
+    };
+    let mut posted = vec![];
+    if pending.len() > BATCH_THRESHOLD {
+        for lines in batch(pending) {
+            let builder = ExecuteWebhook::new()
+                .username(format!("#{channel_login}"))
+                .content(render_lines(&lines, true))
+                .allowed_mentions(CreateAllowedMentions::new());
+            if let Some(discord_id) = execute(&webhook, builder).await {
+                posted.push(Posted { discord_id, batched: true, lines });
+            }
+        }
+    } else {
+        for line in pending {
+            let mut builder = ExecuteWebhook::new()
+                .username(line.display_name.clone())
+                .content(line.render(false))
+                .allowed_mentions(CreateAllowedMentions::new());
+            if let Some(avatar) = avatar(&line.user_id).await {
+                builder = builder.avatar_url(avatar);
+            }
+            if let Some(discord_id) = execute(&webhook, builder).await {
+                posted.push(Posted { discord_id, batched: false, lines: vec![line] });
+            }
+        }
+    }
+    if let Some(state) = MIRRORS.lock().await.get_mut(channel_login) {
+        posted.into_iter().for_each(|p| state.remember(p));
+    }
+}
+
+///Flushes queued messages forever, returns immediately if no mirrors are configured.
+#[allow(unused)]
+pub async fn run() {
+    if CONFIG.mirrors.is_empty() {
+        return;
+    }
+    let channels: Vec<String> = MIRRORS.lock().await.keys().cloned().collect();
+    let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        interval.tick().await;
+        for channel in &channels {
+            flush(channel).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use twitch_irc::message::IRCMessage;
+
+    fn line(text: &str) -> MirroredLine {
+        MirroredLine {
+            twitch_id: "8da29c58-d182-40cd-8b65-1dc446b45c65".to_string(),
+            user_id: "12345678".to_string(),
+            user_login: "testuser".to_string(),
+            display_name: "TestUser".to_string(),
+            text: text.to_string(),
+            struck: false,
+        }
+    }
+
+    #[test]
+    fn from_privmsg() {
+        let src = "@badge-info=;badges=;color=#AA66FF;display-name=TestUser;emotes=25:8-12;flags=;id=8da29c58-d182-40cd-8b65-1dc446b45c65;mod=0;room-id=78127347;subscriber=0;tmi-sent-ts=1693037683123;turbo=0;user-id=12345678;user-type= :testuser!testuser@testuser.tmi.twitch.tv PRIVMSG #zoes17 :so_cool Kappa";
+        let message = PrivmsgMessage::try_from(IRCMessage::parse(src).unwrap()).unwrap();
+        assert_eq!(MirroredLine::from(&message), line("so\\_cool Kappa"));
+    }
+
+    #[test]
+    fn renders_lines() {
+        let mut struck = line("bye");
+        struck.struck = true;
+        assert_eq!(line("hi").render(false), "hi");
+        assert_eq!(line("hi").render(true), "**TestUser**: hi");
+        assert_eq!(struck.render(false), "~~bye~~");
+        assert_eq!(
+            render_lines(&[line("hi"), struck], true),
+            "**TestUser**: hi\n~~**TestUser**: bye~~"
+        );
+    }
+
+    #[test]
+    fn batches_fit_discord_limit() {
+        let lines: Vec<MirroredLine> = (0..10).map(|_| line(&"a".repeat(450))).collect();
+        let batches = batch(lines);
+        assert!(batches.len() > 1);
+        for b in &batches {
+            assert!(render_lines(b, true).chars().count() <= DISCORD_MESSAGE_LIMIT);
+        }
+        assert_eq!(batches.iter().map(|b| b.len()).sum::<usize>(), 10);
+    }
+
+    #[test]
+    fn clears_lines() {
+        let mut state = MirrorState::new(Mirror::default());
+        let id = MessageId::new(1);
+        state.remember(Posted { discord_id: id, batched: false, lines: vec![line("hi")] });
+        assert!(state.clear(|l| l.user_login == "someoneelse", false).is_empty());
+        assert_eq!(
+            state.clear(|l| l.user_login == "testuser", false),
+            [Change::Edit(id, "~~hi~~".to_string())]
+        );
+        assert!(state.clear(|l| l.user_login == "testuser", true).is_empty());
+        assert_eq!(state.posted.len(), 1);
+    }
+
+    #[test]
+    fn caps_pending_lines() {
+        let mut state = MirrorState::new(Mirror::default());
+        for n in 0..MAX_PENDING + 2 {
+            state.queue(line(&n.to_string()));
+        }
+        assert_eq!(state.pending.len(), MAX_PENDING);
+        assert_eq!(state.pending[0].text, "2");
+    }
+
+    #[test]
+    fn escapes_markdown() {
+        assert_eq!(escape_markdown("**bold** `code`"), "\\*\\*bold\\*\\* \\`code\\`");
+    }
+}
@@ -1,6 +1,8 @@
 //!This way be Twitch logging
 
 //crate
+#[cfg(not(test))]
+use crate::archive::{self, ArchiveRecord};
 use crate::config::Config;
 //skip reordering to allow easy reference to verbosity(from least to most)
 #[rustfmt::skip]
@@ -16,6 +18,7 @@ use eyre::WrapErr;
 
 //std
 use std::fmt;
+use std::sync::OnceLock;
 
 //twitch_api
 #[cfg(not(test))]
@@ -24,21 +27,54 @@ use twitch_api::{client::ClientDefault, HelixClient};
 //twitch_irc
 use twitch_irc::login::RefreshingLoginCredentials;
 #[cfg(not(test))]
-use twitch_irc::message::{IRCMessage, JoinMessage, PrivmsgMessage, ServerMessage};
+use twitch_irc::message::{
+    ClearChatAction, ClearChatMessage, ClearMsgMessage, IRCMessage, JoinMessage, PrivmsgMessage,
+    ServerMessage, UserNoticeMessage,
+};
 use twitch_irc::{SecureTCPTransport, TwitchIRCClient};
 
 //module(s)
+pub(crate) mod alerts;
 pub(crate) mod api;
+pub(crate) mod broadcast;
+pub(crate) mod broadcasters;
+pub(crate) mod channels;
 mod commands;
+pub(crate) mod events;
 // #[cfg(not(test))]
 pub(crate) mod eventsub;
+mod filters;
+pub(crate) mod golive;
+pub(crate) mod helix;
+pub(crate) mod mirror;
+pub(crate) mod moderation;
+pub(crate) mod polls;
+pub(crate) mod reply;
+pub(crate) mod rewards;
+mod rolesync;
+pub(crate) mod shoutouts;
+mod timers;
 #[doc(hidden)]
 pub(crate) mod tokens;
+pub(crate) mod webhook;
 
 #[derive(Debug)]
 #[doc(hidden)]
 pub struct Handler(pub Config);
 
+///The chat client type used throughout the Twitch side of the bot.
+pub(crate) type TwitchClient =
+    TwitchIRCClient<SecureTCPTransport, RefreshingLoginCredentials<tokens::BotTokenStorage>>;
+
+///The bot's own channel, the only one it holds the broadcaster's token for.
+pub(crate) fn bot_channel() -> String {
+    crate::CONFIG.twitch_bot_name.to_lowercase()
+}
+
+///The running chat client, set once [`new`] has connected so other parts of the bot can talk in
+///Twitch chat.
+pub(crate) static IRC_CLIENT: OnceLock<TwitchClient> = OnceLock::new();
+
 #[non_exhaustive]
 #[derive(Debug)]
 #[doc(hidden)]
@@ -120,7 +156,14 @@ pub async fn new(config: Config) -> eyre::Result<Handler> {
     let storage = tokens::BotTokenStorage::init(&mut tokens::BotTokenStorage::default(), prefix);
     let client_config = storage.clone().client_config(cfg.clone()).await;
     #[cfg(not(test))]
-    let token = storage.token().await;
+    let helix_client: HelixClient<'static, reqwest::Client> = HelixClient::with_client(
+        <reqwest::Client>::default_client_with_name(Some(
+            "twitch-rs/helix".parse().wrap_err_with(|| "when creating header name").unwrap(),
+        ))
+        .wrap_err_with(|| "when creating client")?,
+    );
+    #[cfg(not(test))]
+    let token = storage.authorized_token(&helix_client).await?;
     #[cfg(not(test))]
     let app_token = tokens::AppToken::new().await;
 
@@ -136,17 +179,40 @@ pub async fn new(config: Config) -> eyre::Result<Handler> {
     >::new(client_config);
     #[cfg(not(test))]
     {
+        let _ = IRC_CLIENT.set(client.clone());
+        helix::init(helix_client, token.clone());
         let client_clone = client.clone();
         let mut join_handles = vec![];
+        join_handles.push(tokio::spawn(mirror::run()));
+        join_handles.push(tokio::spawn(reply::run()));
+        join_handles.push(tokio::spawn(rewards::expire_roles()));
+        join_handles.push(tokio::spawn(rolesync::run()));
+        join_handles.push(tokio::spawn(shoutouts::run()));
+        join_handles.push(tokio::spawn(timers::run()));
         join_handles.push(tokio::spawn(async move {
             while let Some(message) = incoming_messages.recv().await {
                 match message {
                     //Match each of the non-exhaustive cases explictly so we can error on unknown ones
                     ServerMessage::ClearChat { .. } => {
                         parse_message("trace", format!("{:?}", message));
+                        let m =
+                            ClearChatMessage::try_from(Into::<IRCMessage>::into(message.clone()))
+                                .unwrap();
+                        let user_login = match &m.action {
+                            ClearChatAction::UserBanned { user_login, .. }
+                            | ClearChatAction::UserTimedOut { user_login, .. } => {
+                                Some(user_login.as_str())
+                            },
+                            ClearChatAction::ChatCleared => None,
+                        };
+                        mirror::clear_user(&m.channel_login, user_login).await;
                     },
                     ServerMessage::ClearMsg { .. } => {
                         parse_message("trace", format!("{:?}", message));
+                        let m =
+                            ClearMsgMessage::try_from(Into::<IRCMessage>::into(message.clone()))
+                                .unwrap();
+                        mirror::clear_message(&m.channel_login, &m.message_id).await;
                     },
                     ServerMessage::Generic { .. } => {
                         parse_message("trace", format!("{:?}", message));
@@ -174,11 +240,18 @@ pub async fn new(config: Config) -> eyre::Result<Handler> {
                     ServerMessage::Privmsg { .. } => {
                         let m = PrivmsgMessage::try_from(Into::<IRCMessage>::into(message.clone()))
                             .unwrap();
-                        commands::parse_command(message, client_clone.clone()).await;
+                        // commands wait on the database and Helix, chat doesn't wait on them
+                        if !filters::enforce(&m) {
+                            tokio::spawn(commands::parse_command(message, client_clone.clone()));
+                        }
                         println!(
                             "[twitch / {}] {}: {}",
                             m.channel_login, m.sender.login, m.message_text
-                        )
+                        );
+                        archive::record(ArchiveRecord::from(&m));
+                        mirror::push(&m).await;
+                        timers::count_line(&m.channel_login);
+                        alerts::cheered(&m);
                     },
                     ServerMessage::Reconnect { .. } => {
                         parse_message("trace", format!("{:?}", message));
@@ -188,6 +261,10 @@ pub async fn new(config: Config) -> eyre::Result<Handler> {
                     },
                     ServerMessage::UserNotice { .. } => {
                         parse_message("trace", format!("{:?}", message));
+                        let m =
+                            UserNoticeMessage::try_from(Into::<IRCMessage>::into(message.clone()))
+                                .unwrap();
+                        alerts::user_notice(&m);
                     },
                     ServerMessage::UserState { .. } => {
                         parse_message("trace", format!("{:?}", message));
@@ -195,16 +272,27 @@ pub async fn new(config: Config) -> eyre::Result<Handler> {
                     ServerMessage::Whisper { .. } => {
                         // Should this be left at debug or should it be trace because of reporting safety?
                         // We don't want users to accidentaly leak their whispers.
-                        commands::parse_command(message, client_clone.clone()).await;
+                        tokio::spawn(commands::parse_command(message, client_clone.clone()));
                     },
                     _ => eprintln!("received unexpected message variant {:?}", message),
                 }
             }
         }));
-        for channel in &cfg.twitch_channels {
+        // webhooks replace the websocket entirely when configured
+        let use_webhook = webhook::config().is_some();
+        if use_webhook || cfg.broadcaster_redirect_url.is_some() {
+            let app_token = app_token.clone();
+            join_handles.push(tokio::spawn(async move {
+                if let Err(e) = api::serve(app_token).await {
+                    error!("Rocket server stopped: {e}");
+                }
+            }));
+        }
+        // channels joined or parted with `!join` and `!part` are merged with the config
+        for channel in &channels::current().await {
             let un = token.clone().name.take();
             debug_assert!(non_op_trace(format!("`{}` ?= `{}`", un, channel.to_lowercase())));
-            if channel.to_lowercase() == un {
+            if channel.to_lowercase() == un && !use_webhook {
                 let reqwest_client = <reqwest::Client>::default_client_with_name(Some(
                     "twitch-rs/eventsub"
                         .parse()
@@ -241,7 +329,7 @@ pub async fn new(config: Config) -> eyre::Result<Handler> {
                         .unwrap(),
                 };
 
-                //TODO: Figure out why this bails once, likely due to nested await's
+                // `run` only returns if the reconnect url can't be parsed
                 join_handles.push(tokio::spawn(async move {
                     match websocket_client.run().await {
                         Ok(_) => (),
@@ -295,6 +383,7 @@ mod tests {
             twitch_client_secret: "".to_string(),
             twitch_redirect_url: "".to_string(),
             bot_admins: vec![],
+            ..Default::default()
         });
         let _ = format!("{:?}", handle);
     }
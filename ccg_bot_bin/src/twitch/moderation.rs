@@ -0,0 +1,187 @@
+//!Moderation actions taken in a channel, whichever EventSub payload reported them.
+//!
+//!Handlers map the typed `twitch_api` payloads into a [`ModerationEvent`] so anything reacting to
+//!bans, timeouts or unbans only deals with one shape.
+
+//crate
+use crate::db;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{info, debug};
+
+//chrono
+use chrono::{DateTime, Utc};
+
+//twitch_api
+use twitch_api::eventsub::channel::{ChannelBanV1Payload, ChannelUnbanV1Payload};
+use twitch_api::types::Timestamp;
+
+///What was done to the user.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ModerationAction {
+    Ban {
+        reason: String,
+    },
+    Timeout {
+        reason: String,
+        ///When the timeout ends.
+        ends_at: DateTime<Utc>,
+    },
+    Unban,
+}
+
+///A moderation action on a user in a broadcaster's channel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModerationEvent {
+    pub broadcaster_id: String,
+    pub broadcaster_login: String,
+    pub moderator_login: String,
+    pub moderator_name: String,
+    pub user_id: String,
+    pub user_login: String,
+    pub user_name: String,
+    pub action: ModerationAction,
+    ///When it happened, the time it was received for payloads without one.
+    pub at: DateTime<Utc>,
+}
+
+impl ModerationEvent {
+    ///Seconds a timeout lasts, [`None`] for anything else.
+    pub fn duration_seconds(&self) -> Option<i64> {
+        match &self.action {
+            ModerationAction::Timeout { ends_at, .. } => Some((*ends_at - self.at).num_seconds()),
+            _ => None,
+        }
+    }
+}
+
+fn to_utc(timestamp: &Timestamp) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp.as_str()).map(|t| t.with_timezone(&Utc)).ok()
+}
+
+impl From<&ChannelBanV1Payload> for ModerationEvent {
+    fn from(payload: &ChannelBanV1Payload) -> Self {
+        let reason = payload.reason.clone();
+        let ends_at = payload.ends_at.as_ref().and_then(to_utc);
+        let action = match (payload.is_permanent, ends_at) {
+            (false, Some(ends_at)) => ModerationAction::Timeout { reason, ends_at },
+            _ => ModerationAction::Ban { reason },
+        };
+        ModerationEvent {
+            broadcaster_id: payload.broadcaster_user_id.to_string(),
+            broadcaster_login: payload.broadcaster_user_login.to_string(),
+            moderator_login: payload.moderator_user_login.to_string(),
+            moderator_name: payload.moderator_user_name.to_string(),
+            user_id: payload.user_id.to_string(),
+            user_login: payload.user_login.to_string(),
+            user_name: payload.user_name.to_string(),
+            action,
+            at: to_utc(&payload.banned_at).unwrap_or_else(Utc::now),
+        }
+    }
+}
+
+impl From<&ChannelUnbanV1Payload> for ModerationEvent {
+    fn from(payload: &ChannelUnbanV1Payload) -> Self {
+        ModerationEvent {
+            broadcaster_id: payload.broadcaster_user_id.to_string(),
+            broadcaster_login: payload.broadcaster_user_login.to_string(),
+            moderator_login: payload.moderator_user_login.to_string(),
+            moderator_name: payload.moderator_user_name.to_string(),
+            user_id: payload.user_id.to_string(),
+            user_login: payload.user_login.to_string(),
+            user_name: payload.user_name.to_string(),
+            action: ModerationAction::Unban,
+            at: Utc::now(),
+        }
+    }
+}
+
+///Logs `event` along with the Discord accounts linked to the user, if any.
+pub async fn handle(event: ModerationEvent) -> eyre::Result<()> {
+    info!(
+        "[twitch / #{}] {} {:?} {} ({:?})",
+        event.broadcaster_login,
+        event.moderator_login,
+        event.action,
+        event.user_login,
+        event.duration_seconds()
+    );
+    // users who never linked an account aren't in the database
+    let uid = event.user_id.parse::<u32>()?;
+    let Some(user) = db::blocking(move || db::find_twitch_user_by_id(uid)).await? else {
+        return Ok(debug!("{} has no linked Discord account", event.user_login));
+    };
+    let discord_users = db::blocking(move || db::find_discord_user_by_twitch_id(user.tid)).await?;
+    info!("{} is linked to {discord_users:?}", event.user_login);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use twitch_api::eventsub::{self, Event};
+
+    fn ban_notification(ends_at: &str, is_permanent: bool) -> String {
+        format!(
+            r#"{{
+            "subscription":{{
+                "id":"3934e444-0cc9-44d8-9e03-8b35277dde03",
+                "status":"enabled",
+                "type":"channel.ban",
+                "version":"1",
+                "condition":{{
+                    "broadcaster_user_id":"12345678"
+                }},
+                "transport":{{
+                    "method":"websocket",
+                    "session_id":"AgoQ4JtKY91JT2a8FuNEDgaWeRIGY2VsbC1i"
+                }},
+                "created_at":"2024-09-30T05:32:33.999694466Z",
+                "cost":0
+            }},
+            "event":{{
+                "user_id":"696969690",
+                "user_login":"testbanuser",
+                "user_name":"TestBanUser",
+                "broadcaster_user_id":"12345678",
+                "broadcaster_user_login":"testbroadcaster",
+                "broadcaster_user_name":"TestBroadcaster",
+                "moderator_user_id":"87654321",
+                "moderator_user_login":"testmoderator",
+                "moderator_user_name":"TestModerator",
+                "reason":"spam",
+                "banned_at":"2024-09-30T05:35:13Z",
+                "ends_at":{ends_at},
+                "is_permanent":{is_permanent}
+            }}
+        }}"#
+        )
+    }
+
+    fn moderation_event(source: &str) -> ModerationEvent {
+        match Event::parse(source).unwrap() {
+            Event::ChannelBanV1(eventsub::Payload {
+                message: eventsub::Message::Notification(payload),
+                ..
+            }) => ModerationEvent::from(&payload),
+            event => panic!("expected a ban notification, got {event:?}"),
+        }
+    }
+
+    #[test]
+    fn maps_bans() {
+        let event = moderation_event(&ban_notification("null", true));
+        assert_eq!(event.action, ModerationAction::Ban { reason: "spam".to_string() });
+        assert_eq!(event.user_name, "TestBanUser");
+        assert_eq!(event.moderator_login, "testmoderator");
+        assert_eq!(event.duration_seconds(), None);
+    }
+
+    #[test]
+    fn maps_timeouts() {
+        let event = moderation_event(&ban_notification(r#""2024-09-30T05:45:13Z""#, false));
+        assert!(matches!(event.action, ModerationAction::Timeout { .. }));
+        assert_eq!(event.duration_seconds(), Some(600));
+    }
+}
@@ -0,0 +1,386 @@
+//!Polls and predictions in the bot's own channel, started from chat or Discord.
+//!
+//!Twitch only lets the broadcaster's own token manage these, and the bot only holds its own, so
+//!they are limited to the bot's channel like ban events are. Both are written as
+//!`"Question" first | second | third 2m`, the trailing duration is optional. Results are posted to
+//!chat and to `[polls] discord_channel_id` when `channel.poll.end` or `channel.prediction.end`
+//!arrives.
+
+//crate
+use crate::discord::builders::discordembed::DiscordEmbed;
+use crate::discord::HTTP;
+use crate::twitch::commands::parser;
+use crate::twitch::helix::{self, Helix};
+use crate::twitch::tokens::Token;
+use crate::twitch::IRC_CLIENT;
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, debug};
+
+//serenity
+use serenity::all::{ChannelId, CreateMessage};
+
+//std
+use std::time::Duration;
+
+//twitch_api
+use twitch_api::{
+    eventsub::channel::{
+        poll::PollEndStatus, prediction::PredictionEndStatus, ChannelPollEndV1Payload,
+        ChannelPredictionEndV1Payload,
+    },
+    helix::{
+        polls::{
+            CreatePollBody, CreatePollRequest, EndPollBody, EndPollRequest, GetPollsRequest,
+            NewPollChoice, Poll,
+        },
+        predictions::{
+            CreatePredictionBody, CreatePredictionRequest, EndPredictionBody, EndPredictionRequest,
+            GetPredictionsRequest, NewPredictionOutcome, Prediction,
+        },
+    },
+    types::{PollStatus, PredictionStatus},
+};
+
+///Twitch purple.
+const RESULT_COLOR: u32 = 0x9146FF;
+
+///What Twitch accepts for a poll or prediction.
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    pub kind: &'static str,
+    pub max_title: usize,
+    pub max_options: usize,
+    pub max_option: usize,
+    pub min_duration: Duration,
+    pub max_duration: Duration,
+    pub default_duration: Duration,
+}
+
+pub const POLL: Limits = Limits {
+    kind: "poll",
+    max_title: 60,
+    max_options: 5,
+    max_option: 25,
+    min_duration: Duration::from_secs(15),
+    max_duration: Duration::from_secs(30 * 60),
+    default_duration: Duration::from_secs(60),
+};
+
+pub const PREDICTION: Limits = Limits {
+    kind: "prediction",
+    max_title: 45,
+    max_options: 10,
+    max_option: 25,
+    min_duration: Duration::from_secs(30),
+    max_duration: Duration::from_secs(30 * 60),
+    default_duration: Duration::from_secs(2 * 60),
+};
+
+///A poll or prediction ready to be started.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Spec {
+    pub title: String,
+    pub options: Vec<String>,
+    pub duration: Duration,
+}
+
+///Checks a question, `|` separated options and optional duration against `limits`.
+pub fn build(
+    title: &str,
+    options: &str,
+    duration: Option<&str>,
+    limits: Limits,
+) -> Result<Spec, String> {
+    let kind = limits.kind;
+    let title = title.trim();
+    if title.is_empty() || title.chars().count() > limits.max_title {
+        return Err(format!("The {kind} question must be 1 to {} characters", limits.max_title));
+    }
+    let options: Vec<String> =
+        options.split('|').map(str::trim).filter(|o| !o.is_empty()).map(str::to_string).collect();
+    if options.len() < 2 || options.len() > limits.max_options {
+        return Err(format!("A {kind} needs 2 to {} options", limits.max_options));
+    }
+    if options.iter().any(|o| o.chars().count() > limits.max_option) {
+        return Err(format!("{kind} options can be at most {} characters", limits.max_option));
+    }
+    let duration = match duration {
+        Some(text) => parser::parse_duration(text).ok_or(format!("`{text}` isn't a duration"))?,
+        None => limits.default_duration,
+    };
+    if duration < limits.min_duration || duration > limits.max_duration {
+        return Err(format!(
+            "A {kind} must last {} to {}",
+            parser::format_duration(limits.min_duration),
+            parser::format_duration(limits.max_duration)
+        ));
+    }
+    Ok(Spec { title: title.to_string(), options, duration })
+}
+
+///Parses `"Question" first | second 2m`, a trailing duration needs its unit.
+pub fn spec(text: &str, limits: Limits) -> Result<Spec, String> {
+    let usage = || format!("Write the {} as \"Question\" first | second [duration]", limits.kind);
+    let rest = text.trim().strip_prefix('"').ok_or_else(usage)?;
+    let (title, rest) = rest.split_once('"').ok_or_else(usage)?;
+    let rest = rest.trim();
+    let duration = rest
+        .rsplit_once(char::is_whitespace)
+        .filter(|(_, last)| last.ends_with(|c: char| c.is_ascii_alphabetic()))
+        .filter(|(_, last)| parser::parse_duration(last).is_some());
+    match duration {
+        Some((options, last)) => build(title, options, Some(last), limits),
+        None => build(title, rest, None, limits),
+    }
+}
+
+///Finds an outcome by its 1 based number or its title, ignoring case.
+pub fn pick_outcome(outcomes: &[&str], choice: &str) -> Option<usize> {
+    let choice = choice.trim();
+    match choice.parse::<usize>() {
+        Ok(n) if (1..=outcomes.len()).contains(&n) => Some(n - 1),
+        _ => outcomes.iter().position(|o| o.eq_ignore_ascii_case(choice)),
+    }
+}
+
+///The results of a poll, most votes first.
+pub fn poll_results(title: &str, choices: &[(String, i64)]) -> String {
+    let total: i64 = choices.iter().map(|(_, votes)| votes).sum();
+    let mut choices = choices.to_vec();
+    choices.sort_by(|a, b| b.1.cmp(&a.1));
+    let results: Vec<String> = choices
+        .iter()
+        .map(|(choice, votes)| {
+            let percent = if total > 0 { votes * 100 / total } else { 0 };
+            format!("{choice} {votes} ({percent}%)")
+        })
+        .collect();
+    format!("Poll \"{title}\" ended: {}", results.join(", "))
+}
+
+///The results of a prediction, [`None`] as the winner means it was cancelled.
+pub fn prediction_results(
+    title: &str,
+    winner: Option<&str>,
+    outcomes: &[(String, i64, i64)],
+) -> String {
+    let Some(winner) = winner else {
+        return format!("Prediction \"{title}\" was cancelled, points have been refunded");
+    };
+    let results: Vec<String> = outcomes
+        .iter()
+        .map(|(outcome, users, points)| format!("{outcome}: {users} users, {points} points"))
+        .collect();
+    format!("Prediction \"{title}\" resolved, {winner} won! {}", results.join(" | "))
+}
+
+async fn broadcaster() -> eyre::Result<(&'static Helix, Token)> {
+    let helix = helix::get().ok_or_else(|| eyre::eyre!("Helix client isn't initialised yet"))?;
+    Ok((helix, helix.token().await?))
+}
+
+///Starts a poll, returning the chat confirmation.
+pub async fn start_poll(spec: &Spec) -> eyre::Result<String> {
+    let (helix, token) = broadcaster().await?;
+    let choices: Vec<NewPollChoice> =
+        spec.options.iter().map(|o| NewPollChoice::new(o.as_str())).collect();
+    let secs = spec.duration.as_secs() as i64;
+    let body = CreatePollBody::new(&token.uid, spec.title.as_str(), secs, choices.as_slice());
+    helix.client.req_post(CreatePollRequest::new(), body, &token).await?;
+    Ok(format!(
+        "Poll started: \"{}\" for {}, vote with {}",
+        spec.title,
+        parser::format_duration(spec.duration),
+        spec.options.join(" | ")
+    ))
+}
+
+async fn active_poll(helix: &Helix, token: &Token) -> eyre::Result<Option<Poll>> {
+    let request = GetPollsRequest::broadcaster_id(&token.uid);
+    let polls = helix.client.req_get(request, token).await?.data;
+    Ok(polls.into_iter().find(|p| p.status == PollStatus::Active))
+}
+
+///Ends the running poll early, returning the chat confirmation.
+pub async fn end_poll() -> eyre::Result<String> {
+    let (helix, token) = broadcaster().await?;
+    let Some(poll) = active_poll(helix, &token).await? else {
+        return Ok("There is no poll running".to_string());
+    };
+    let body = EndPollBody::new(&token.uid, &poll.id, PollStatus::Terminated);
+    helix.client.req_patch(EndPollRequest::new(), body, &token).await?;
+    Ok(format!("Ended the poll \"{}\"", poll.title))
+}
+
+///Starts a prediction, returning the chat confirmation.
+pub async fn start_prediction(spec: &Spec) -> eyre::Result<String> {
+    let (helix, token) = broadcaster().await?;
+    let outcomes: Vec<NewPredictionOutcome> =
+        spec.options.iter().map(|o| NewPredictionOutcome::new(o.as_str())).collect();
+    let secs = spec.duration.as_secs() as i64;
+    let body =
+        CreatePredictionBody::new(&token.uid, spec.title.as_str(), outcomes.as_slice(), secs);
+    helix.client.req_post(CreatePredictionRequest::new(), body, &token).await?;
+    Ok(format!(
+        "Prediction started: \"{}\", predict within {}",
+        spec.title,
+        parser::format_duration(spec.duration)
+    ))
+}
+
+async fn open_prediction(helix: &Helix, token: &Token) -> eyre::Result<Option<Prediction>> {
+    let request = GetPredictionsRequest::broadcaster_id(&token.uid);
+    let predictions = helix.client.req_get(request, token).await?.data;
+    Ok(predictions
+        .into_iter()
+        .find(|p| matches!(p.status, PredictionStatus::Active | PredictionStatus::Locked)))
+}
+
+///Stops new predictions on the open prediction, returning the chat confirmation.
+pub async fn lock_prediction() -> eyre::Result<String> {
+    let (helix, token) = broadcaster().await?;
+    let Some(prediction) = open_prediction(helix, &token).await? else {
+        return Ok("There is no prediction running".to_string());
+    };
+    let body = EndPredictionBody::new(&token.uid, &prediction.id, PredictionStatus::Locked);
+    helix.client.req_patch(EndPredictionRequest::new(), body, &token).await?;
+    Ok(format!("Predictions are locked for \"{}\"", prediction.title))
+}
+
+///Pays out the open prediction to `winner`, an outcome number or title.
+pub async fn resolve_prediction(winner: &str) -> eyre::Result<String> {
+    let (helix, token) = broadcaster().await?;
+    let Some(prediction) = open_prediction(helix, &token).await? else {
+        return Ok("There is no prediction running".to_string());
+    };
+    let titles: Vec<&str> = prediction.outcomes.iter().map(|o| o.title.as_str()).collect();
+    let Some(index) = pick_outcome(&titles, winner) else {
+        return Ok(format!("Pick the winner from {}", titles.join(" | ")));
+    };
+    let body = EndPredictionBody::new(&token.uid, &prediction.id, PredictionStatus::Resolved)
+        .winning_outcome_id(&prediction.outcomes[index].id);
+    helix.client.req_patch(EndPredictionRequest::new(), body, &token).await?;
+    Ok(format!("Resolved \"{}\", {} won", prediction.title, titles[index]))
+}
+
+///Cancels the open prediction and refunds everyone, returning the chat confirmation.
+pub async fn cancel_prediction() -> eyre::Result<String> {
+    let (helix, token) = broadcaster().await?;
+    let Some(prediction) = open_prediction(helix, &token).await? else {
+        return Ok("There is no prediction running".to_string());
+    };
+    let body = EndPredictionBody::new(&token.uid, &prediction.id, PredictionStatus::Canceled);
+    helix.client.req_patch(EndPredictionRequest::new(), body, &token).await?;
+    Ok(format!("Cancelled \"{}\", points have been refunded", prediction.title))
+}
+
+async fn announce(channel: &str, title: &str, results: String) {
+    if let Some(client) = IRC_CLIENT.get() {
+        if let Err(e) = client.say(channel.to_string(), results.clone()).await {
+            error!("Unable to post results to #{channel}: {e}");
+        }
+    }
+    let Some(id) = CONFIG.polls.as_ref().and_then(|p| p.discord_channel_id.as_ref()) else {
+        return debug!("no polls channel configured, results only posted to chat");
+    };
+    let Ok(id) = id.parse::<u64>() else {
+        return error!("invalid polls discord_channel_id `{id}`");
+    };
+    let embed =
+        DiscordEmbed::new().title(title.to_string()).description(results).color(RESULT_COLOR);
+    let message = CreateMessage::new().embed(embed.build());
+    if let Err(e) = ChannelId::new(id).send_message(&*HTTP, message).await {
+        error!("Unable to post results to Discord: {e}");
+    }
+}
+
+///Posts the results of the poll in `payload`, archived polls were already posted when they ended.
+pub async fn poll_ended(payload: &ChannelPollEndV1Payload) {
+    if payload.status == PollEndStatus::Archived {
+        return;
+    }
+    let choices: Vec<(String, i64)> = payload
+        .choices
+        .iter()
+        .map(|c| (c.title.to_string(), c.votes.unwrap_or_default()))
+        .collect();
+    let results = poll_results(&payload.title, &choices);
+    announce(payload.broadcaster_user_login.as_str(), "Poll results", results).await;
+}
+
+///Posts the results of the prediction in `payload`.
+pub async fn prediction_ended(payload: &ChannelPredictionEndV1Payload) {
+    let winner_id = payload.winning_outcome_id.as_ref().map(|id| id.to_string());
+    let winner = match payload.status {
+        PredictionEndStatus::Resolved => payload
+            .outcomes
+            .iter()
+            .find(|o| Some(o.id.to_string()) == winner_id)
+            .map(|o| o.title.to_string()),
+        _ => None,
+    };
+    let outcomes: Vec<(String, i64, i64)> = payload
+        .outcomes
+        .iter()
+        .map(|o| {
+            (o.title.to_string(), o.users.unwrap_or_default(), o.channel_points.unwrap_or_default())
+        })
+        .collect();
+    let results = prediction_results(&payload.title, winner.as_deref(), &outcomes);
+    announce(payload.broadcaster_user_login.as_str(), "Prediction results", results).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_specs() {
+        let spec = spec("\"Best game?\" Halo | Halo 2 | Portal 90s", POLL).unwrap();
+        assert_eq!(spec.title, "Best game?");
+        assert_eq!(spec.options, ["Halo", "Halo 2", "Portal"]);
+        assert_eq!(spec.duration, Duration::from_secs(90));
+        let spec = super::spec("\"Best game?\" Halo | Halo 2", POLL).unwrap();
+        assert_eq!(spec.options, ["Halo", "Halo 2"]);
+        assert_eq!(spec.duration, POLL.default_duration);
+    }
+
+    #[test]
+    fn rejects_bad_specs() {
+        assert!(spec("Best game? Halo | Portal", POLL).is_err());
+        assert!(spec("\"Best game?\" Halo", POLL).is_err());
+        assert!(spec("\"Best game?\" a | b | c | d | e | f", POLL).is_err());
+        assert!(spec("\"Best game?\" a | b | c | d | e | f", PREDICTION).is_ok());
+        assert!(spec("\"Best game?\" Halo | Portal 5s", POLL).is_err());
+        assert!(spec("\"Best game?\" Halo | Portal 1h", PREDICTION).is_err());
+    }
+
+    #[test]
+    fn picks_outcomes() {
+        let outcomes = ["Yes", "No"];
+        assert_eq!(pick_outcome(&outcomes, "2"), Some(1));
+        assert_eq!(pick_outcome(&outcomes, "yes"), Some(0));
+        assert_eq!(pick_outcome(&outcomes, "3"), None);
+        assert_eq!(pick_outcome(&outcomes, "maybe"), None);
+    }
+
+    #[test]
+    fn formats_results() {
+        let choices = [("Halo".to_string(), 4), ("Portal".to_string(), 12)];
+        assert_eq!(
+            poll_results("Best game?", &choices),
+            "Poll \"Best game?\" ended: Portal 12 (75%), Halo 4 (25%)"
+        );
+        let outcomes = [("Yes".to_string(), 12, 3400), ("No".to_string(), 8, 1200)];
+        assert_eq!(
+            prediction_results("Win?", Some("Yes"), &outcomes),
+            "Prediction \"Win?\" resolved, Yes won! Yes: 12 users, 3400 points | No: 8 users, 1200 points"
+        );
+        assert_eq!(
+            prediction_results("Win?", None, &outcomes),
+            "Prediction \"Win?\" was cancelled, points have been refunded"
+        );
+    }
+}
@@ -0,0 +1,192 @@
+//!Whispers through Helix's Send Whisper endpoint, Twitch no longer delivers whispers sent over IRC.
+//!
+//![`whisper`] only queues the whisper, [`run`] sends them one after another so commands and chat
+//!never wait on Twitch's limits of 3 a second and 100 a minute, a whisper waits for a free slot
+//!rather than being dropped. There is also a limit of 40 new recipients a day. A whisper that can't
+//!be sent, because of that daily limit, Helix not being ready or the recipient blocking whispers,
+//!is sent as a mention in the bot's own channel instead.
+
+//crate
+use crate::discord::bridge::{truncate, TWITCH_MESSAGE_LIMIT};
+use crate::twitch::{helix, TwitchClient};
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, warn, debug};
+
+//governor
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
+
+use lazy_static::lazy_static;
+
+//std
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+//tokio
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+//twitch_api
+use twitch_api::helix::whispers::{SendWhisperBody, SendWhisperRequest};
+use twitch_api::types::UserId;
+
+///Distinct users that may be whispered each day.
+const MAX_RECIPIENTS: usize = 40;
+///How long a user counts towards [`MAX_RECIPIENTS`].
+const RECIPIENT_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+lazy_static! {
+    static ref PER_SECOND: DefaultDirectRateLimiter =
+        RateLimiter::direct(Quota::per_second(NonZeroU32::new(3).expect("3 is non-zero")));
+    static ref PER_MINUTE: DefaultDirectRateLimiter =
+        RateLimiter::direct(Quota::per_minute(NonZeroU32::new(100).expect("100 is non-zero")));
+    static ref RECIPIENTS: Mutex<Recipients> = Mutex::new(Recipients::default());
+    ///Whispers waiting for [`run`], the receiver is taken when it starts.
+    static ref QUEUE: (UnboundedSender<Whisper>, Mutex<Option<UnboundedReceiver<Whisper>>>) = {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (sender, Mutex::new(Some(receiver)))
+    };
+}
+
+///A whisper waiting to be sent.
+struct Whisper {
+    client: TwitchClient,
+    to_id: String,
+    to_login: String,
+    text: String,
+}
+
+///Whether a user may be whispered, see [`Recipients::allow`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Slot {
+    ///Already whispered within the last day.
+    Known,
+    ///Newly counted towards the daily limit.
+    Taken,
+    ///The daily limit is reached.
+    Full,
+}
+
+///The users whispered within the last day.
+#[derive(Debug, Default)]
+pub struct Recipients {
+    ///When each user, keyed by id, was first whispered in the current window.
+    seen: HashMap<String, Instant>,
+}
+
+impl Recipients {
+    ///Whether `user` may be whispered at `now`, counting them if they are new.
+    pub fn allow(&mut self, user: &str, now: Instant) -> Slot {
+        self.seen.retain(|_, first| now.duration_since(*first) < RECIPIENT_WINDOW);
+        if self.seen.contains_key(user) {
+            return Slot::Known;
+        }
+        if self.seen.len() >= MAX_RECIPIENTS {
+            return Slot::Full;
+        }
+        self.seen.insert(user.to_string(), now);
+        Slot::Taken
+    }
+
+    ///Gives back the slot [`allow`](Self::allow) took for `user` when the whisper wasn't sent.
+    pub fn release(&mut self, user: &str) {
+        self.seen.remove(user);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+async fn send_whisper(to_id: &str, text: &str) -> eyre::Result<()> {
+    let helix = helix::get().ok_or_else(|| eyre::eyre!("Helix client isn't initialised yet"))?;
+    let token = helix.token().await?;
+    PER_MINUTE.until_ready().await;
+    PER_SECOND.until_ready().await;
+    let request = SendWhisperRequest::new(token.uid.clone(), UserId::from(to_id.to_string()));
+    helix.client.req_post(request, SendWhisperBody::new(text), &token).await?;
+    Ok(())
+}
+
+async fn say(client: &TwitchClient, channel: String, text: String) -> eyre::Result<()> {
+    #[cfg(test)]
+    {
+        let _ = (client, channel, text);
+        Ok(())
+    }
+    #[cfg(not(test))]
+    client.say(channel, text).await.map_err(|e| eyre::eyre!("{e}"))
+}
+
+///Queues `text` for the user `to_id`, mentioning `to_login` in the bot's channel if it can't be
+///whispered.
+pub fn whisper(client: &TwitchClient, to_id: &str, to_login: &str, text: &str) -> eyre::Result<()> {
+    let whisper = Whisper {
+        client: client.clone(),
+        to_id: to_id.to_string(),
+        to_login: to_login.to_string(),
+        // whispers to users who haven't whispered back are limited to one chat message's length
+        text: truncate(text, TWITCH_MESSAGE_LIMIT),
+    };
+    QUEUE.0.send(whisper).map_err(|_| eyre::eyre!("The whisper queue has stopped"))
+}
+
+async fn deliver(whisper: Whisper) -> eyre::Result<()> {
+    let Whisper { client, to_id, to_login, text } = whisper;
+    let sent = match lock(&RECIPIENTS).allow(&to_id, Instant::now()) {
+        Slot::Full => Err(eyre::eyre!("{MAX_RECIPIENTS} users have been whispered today")),
+        slot => {
+            let sent = send_whisper(&to_id, &text).await;
+            if sent.is_err() && slot == Slot::Taken {
+                lock(&RECIPIENTS).release(&to_id);
+            }
+            sent
+        },
+    };
+    match sent {
+        Ok(()) => Ok(debug!("whispered {to_login}")),
+        Err(e) => {
+            warn!("Unable to whisper {to_login}, replying in chat instead: {e}");
+            let mention = truncate(&format!("@{to_login} {text}"), TWITCH_MESSAGE_LIMIT);
+            say(&client, super::bot_channel(), mention).await
+        },
+    }
+}
+
+///Sends queued whispers one at a time for as long as the bot runs.
+#[allow(unused)]
+pub async fn run() {
+    let Some(mut queue) = lock(&QUEUE.1).take() else {
+        return;
+    };
+    while let Some(whisper) = queue.recv().await {
+        if let Err(e) = deliver(whisper).await {
+            error!("Unable to deliver a whisper: {e:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_new_recipients() {
+        let start = Instant::now();
+        let mut recipients = Recipients::default();
+        for user in 0..MAX_RECIPIENTS {
+            assert_eq!(recipients.allow(&user.to_string(), start), Slot::Taken);
+        }
+        assert_eq!(recipients.allow("new", start), Slot::Full);
+        // users already whispered today can still be whispered
+        assert_eq!(recipients.allow("0", start + Duration::from_secs(60)), Slot::Known);
+        // a whisper that failed gives its slot back
+        recipients.release("0");
+        assert_eq!(recipients.allow("new", start + Duration::from_secs(60)), Slot::Taken);
+        assert_eq!(recipients.allow("newer", start + RECIPIENT_WINDOW), Slot::Taken);
+    }
+}
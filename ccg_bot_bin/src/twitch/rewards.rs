@@ -0,0 +1,385 @@
+//!Actions run when a viewer redeems a channel point reward.
+//!
+//!Each channel maps reward titles to an [`Action`] in the `reward_actions` table, managed with
+//!`/rewards` on Discord. Rewards without an action are left alone, rewards with `auto_settle` are
+//!marked fulfilled when their action succeeds and refunded when it fails. Twitch only lets the
+//!bot settle rewards created with its own client id.
+
+//crate
+use crate::db::{self, models::RewardAction, models::RewardRole};
+use crate::discord::bridge::{truncate, TWITCH_MESSAGE_LIMIT};
+use crate::discord::HTTP;
+use crate::twitch::commands::{custom, variables};
+use crate::twitch::{broadcasters, helix, IRC_CLIENT};
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, info, debug};
+
+//chrono
+use chrono::{TimeDelta, Utc};
+
+use lazy_static::lazy_static;
+
+//serenity
+use serenity::all::{GuildId, RoleId, UserId};
+
+//std
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+//twitch_api
+use twitch_api::eventsub::channel::ChannelPointsCustomRewardRedemptionAddV1Payload;
+use twitch_api::helix::points::{
+    CustomRewardRedemptionStatus, UpdateRedemptionStatusBody, UpdateRedemptionStatusRequest,
+};
+
+const AUDIT_REASON: &str = "Twitch channel point reward";
+///Longest reward title Twitch allows.
+pub const TITLE_LIMIT: usize = 45;
+
+lazy_static! {
+    ///Viewers waiting in each channel's queue, oldest first.
+    static ref QUEUES: Mutex<HashMap<String, VecDeque<QueueEntry>>> = Mutex::new(HashMap::new());
+}
+
+fn queues() -> std::sync::MutexGuard<'static, HashMap<String, VecDeque<QueueEntry>>> {
+    match QUEUES.lock() {
+        Ok(queues) => queues,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+///What happens when a reward is redeemed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    ///Posts the message in chat, `$(user)` and `$(input)` are replaced.
+    Say(String),
+    ///Grants the Discord role to the viewer's linked accounts for a number of minutes.
+    Role { role: RoleId, minutes: u32 },
+    ///Runs the channel's custom command as if the viewer had typed it.
+    Command(String),
+    ///Adds the viewer and their input to the channel's queue.
+    Queue,
+}
+
+impl Action {
+    ///Names accepted in the `action` column.
+    pub const KINDS: [&'static str; 4] = ["say", "role", "command", "queue"];
+
+    ///Parses an action from its stored parts.
+    pub fn new(kind: &str, argument: &str, minutes: Option<u32>) -> Result<Self, String> {
+        let argument = argument.trim();
+        match kind {
+            "say" if argument.is_empty() => Err("say needs a message".to_string()),
+            "say" => Ok(Action::Say(argument.to_string())),
+            "role" => {
+                let role = argument
+                    .trim_start_matches("<@&")
+                    .trim_end_matches('>')
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|id| *id != 0)
+                    .ok_or_else(|| format!("`{argument}` isn't a role"))?;
+                match minutes {
+                    Some(minutes) if minutes > 0 => {
+                        Ok(Action::Role { role: RoleId::new(role), minutes })
+                    },
+                    _ => Err("role needs a number of minutes".to_string()),
+                }
+            },
+            "command" if argument.is_empty() => Err("command needs a command name".to_string()),
+            "command" => Ok(Action::Command(argument.trim_start_matches('!').to_lowercase())),
+            "queue" => Ok(Action::Queue),
+            other => Err(format!("unknown action `{other}`, use {}", Action::KINDS.join(", "))),
+        }
+    }
+
+    ///The name stored in the `action` column.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Action::Say(_) => "say",
+            Action::Role { .. } => "role",
+            Action::Command(_) => "command",
+            Action::Queue => "queue",
+        }
+    }
+}
+
+impl TryFrom<&RewardAction> for Action {
+    type Error = String;
+
+    fn try_from(row: &RewardAction) -> Result<Self, Self::Error> {
+        Action::new(&row.action, &row.argument, row.duration_minutes)
+    }
+}
+
+///The viewer who redeemed a reward and what they typed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Redemption {
+    pub channel: String,
+    pub user_id: String,
+    pub user_name: String,
+    pub input: String,
+}
+
+impl From<&ChannelPointsCustomRewardRedemptionAddV1Payload> for Redemption {
+    fn from(payload: &ChannelPointsCustomRewardRedemptionAddV1Payload) -> Self {
+        Redemption {
+            channel: payload.broadcaster_user_login.to_string(),
+            user_id: payload.user_id.to_string(),
+            user_name: payload.user_name.to_string(),
+            input: payload.user_input.trim().to_string(),
+        }
+    }
+}
+
+///A viewer waiting in a channel's queue.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueueEntry {
+    pub user_name: String,
+    pub input: String,
+}
+
+///Everyone in `channel`'s queue, oldest first.
+pub fn queue(channel: &str) -> Vec<QueueEntry> {
+    queues().get(&channel.to_lowercase()).map(|q| q.iter().cloned().collect()).unwrap_or_default()
+}
+
+///Takes the oldest viewer out of `channel`'s queue.
+pub fn next(channel: &str) -> Option<QueueEntry> {
+    queues().get_mut(&channel.to_lowercase())?.pop_front()
+}
+
+///Empties `channel`'s queue, returning how many viewers were in it.
+pub fn clear(channel: &str) -> usize {
+    queues().remove(&channel.to_lowercase()).map(|q| q.len()).unwrap_or(0)
+}
+
+fn enqueue(redemption: &Redemption) -> usize {
+    let mut queues = queues();
+    let queue = queues.entry(redemption.channel.to_lowercase()).or_default();
+    queue.push_back(QueueEntry {
+        user_name: redemption.user_name.clone(),
+        input: redemption.input.clone(),
+    });
+    queue.len()
+}
+
+///Replaces `$(user)`, `$(input)` and `$(channel)` in `template`.
+pub fn expand(template: &str, redemption: &Redemption) -> String {
+    variables::expand(template, |variable| match variable {
+        "user" => Some(redemption.user_name.clone()),
+        "input" => Some(redemption.input.clone()),
+        "channel" => Some(redemption.channel.clone()),
+        _ => None,
+    })
+}
+
+async fn say(channel: &str, message: &str) -> eyre::Result<()> {
+    let client = IRC_CLIENT.get().ok_or_else(|| eyre::eyre!("chat isn't connected"))?;
+    client
+        .say(channel.to_string(), truncate(message, TWITCH_MESSAGE_LIMIT))
+        .await
+        .map_err(|e| eyre::eyre!("{e}"))
+}
+
+async fn grant_role(redemption: &Redemption, role: RoleId, minutes: u32) -> eyre::Result<()> {
+    let guild_id = GuildId::new(CONFIG.discord_guildid.parse()?);
+    let uid = redemption.user_id.parse::<u32>()?;
+    let user = db::blocking(move || db::find_twitch_user_by_id(uid))
+        .await?
+        .ok_or_else(|| eyre::eyre!("{} has no linked Discord account", redemption.user_name))?;
+    let linked = db::blocking(move || db::find_discord_user_by_twitch_id(user.tid)).await?;
+    if linked.is_empty() {
+        eyre::bail!("{} has no linked Discord account", redemption.user_name);
+    }
+    let expires_at = Utc::now() + TimeDelta::minutes(minutes.into());
+    for discord_user in linked {
+        let user_id = UserId::new(discord_user.did);
+        HTTP.add_member_role(guild_id, user_id, role, Some(AUDIT_REASON)).await?;
+        // kept in the database so a restart still takes the role away, see `expire_roles`
+        let granted = RewardRole {
+            discord_id: discord_user.did,
+            role_id: role.get(),
+            expires_at: expires_at.naive_utc(),
+        };
+        db::blocking(move || db::save_reward_role(&granted)).await?;
+        tokio::spawn(expire_role(guild_id, user_id, role));
+    }
+    Ok(())
+}
+
+///Removes `role` from `user_id` once its stored expiry passes, waiting longer if it was extended.
+async fn expire_role(guild_id: GuildId, user_id: UserId, role: RoleId) {
+    let (user, role_id) = (user_id.get(), role.get());
+    loop {
+        let granted = match db::blocking(move || db::find_reward_role(user, role_id)).await {
+            Ok(Some(granted)) => granted,
+            Ok(None) => return,
+            Err(e) => return error!("Unable to load reward role {role} of {user_id}: {e:?}"),
+        };
+        let left = (granted.expires_at.and_utc() - Utc::now()).to_std().unwrap_or_default();
+        if left.is_zero() {
+            break;
+        }
+        tokio::time::sleep(left).await;
+    }
+    if let Err(e) = HTTP.remove_member_role(guild_id, user_id, role, Some(AUDIT_REASON)).await {
+        return error!("Unable to remove reward role {role} from {user_id}: {e}");
+    }
+    if let Err(e) = db::blocking(move || db::delete_reward_role(user, role_id)).await {
+        error!("Unable to forget reward role {role} of {user_id}: {e:?}");
+    }
+}
+
+///Resumes removing reward roles after a restart, expired ones are removed straight away.
+#[allow(unused)]
+pub async fn expire_roles() {
+    let guild_id = match CONFIG.discord_guildid.parse() {
+        Ok(id) => GuildId::new(id),
+        Err(e) => return error!("Unable to expire reward roles, bad guild id: {e}"),
+    };
+    let granted = match db::blocking(db::find_reward_roles).await {
+        Ok(granted) => granted,
+        Err(e) => return error!("Unable to load reward roles: {e:?}"),
+    };
+    for role in granted {
+        let (user_id, role) = (UserId::new(role.discord_id), RoleId::new(role.role_id));
+        tokio::spawn(expire_role(guild_id, user_id, role));
+    }
+}
+
+async fn run_command(redemption: &Redemption, name: &str) -> eyre::Result<()> {
+    let (channel, lookup) = (redemption.channel.clone(), name.to_string());
+    let command = db::blocking(move || db::find_custom_command(&channel, &lookup))
+        .await?
+        .ok_or_else(|| eyre::eyre!("#{} has no command named {name}", redemption.channel))?;
+    let id = command.id;
+    let uses = db::blocking(move || db::increment_custom_command_uses(id)).await?;
+    let response = custom::response(
+        &command.response,
+        &redemption.user_name,
+        &redemption.input,
+        uses,
+        &redemption.channel,
+        None,
+    );
+    say(&redemption.channel, &response).await
+}
+
+///Runs `action` for `redemption`.
+pub async fn perform(action: &Action, redemption: &Redemption) -> eyre::Result<()> {
+    match action {
+        Action::Say(message) => say(&redemption.channel, &expand(message, redemption)).await,
+        Action::Role { role, minutes } => grant_role(redemption, *role, *minutes).await,
+        Action::Command(name) => run_command(redemption, name).await,
+        Action::Queue => {
+            let position = enqueue(redemption);
+            Ok(debug!("{} is #{position} in #{}'s queue", redemption.user_name, redemption.channel))
+        },
+    }
+}
+
+async fn settle(
+    payload: &ChannelPointsCustomRewardRedemptionAddV1Payload,
+    status: CustomRewardRedemptionStatus,
+) -> eyre::Result<()> {
+    let helix = helix::get().ok_or_else(|| eyre::eyre!("Helix client isn't initialised yet"))?;
+    let channel = payload.broadcaster_user_login.as_str();
+    let token = match channel.eq_ignore_ascii_case(&CONFIG.twitch_bot_name) {
+        true => helix.token().await?,
+        false => broadcasters::token(&helix.client, channel)
+            .await?
+            .ok_or_else(|| eyre::eyre!("{channel} didn't authorize the bot"))?,
+    };
+    let request = UpdateRedemptionStatusRequest::new(
+        &payload.broadcaster_user_id,
+        &payload.reward.id,
+        &payload.id,
+    );
+    let body = UpdateRedemptionStatusBody::status(status);
+    helix.client.req_patch(request, body, &token).await?;
+    Ok(())
+}
+
+///Runs the action mapped to the redeemed reward, settling the redemption if it asks for that.
+pub async fn redeemed(
+    payload: &ChannelPointsCustomRewardRedemptionAddV1Payload,
+) -> eyre::Result<()> {
+    let redemption = Redemption::from(payload);
+    let title = payload.reward.title.as_str();
+    let (channel, reward) = (redemption.channel.clone(), title.to_string());
+    let Some(row) = db::blocking(move || db::find_reward_action(&channel, &reward)).await? else {
+        return Ok(debug!("[#{}] no action for the reward `{title}`", redemption.channel));
+    };
+    info!("[twitch / #{}] {} redeemed {title}", redemption.channel, redemption.user_name);
+    let result = match Action::try_from(&row) {
+        Ok(action) => perform(&action, &redemption).await,
+        Err(e) => Err(eyre::eyre!("the action of `{title}` is invalid: {e}")),
+    };
+    if row.auto_settle {
+        let status = match result {
+            Ok(_) => CustomRewardRedemptionStatus::Fulfilled,
+            Err(_) => CustomRewardRedemptionStatus::Canceled,
+        };
+        if let Err(e) = settle(payload, status).await {
+            error!("[#{}] Unable to settle `{title}`: {e:?}", redemption.channel);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redemption(channel: &str, user_name: &str, input: &str) -> Redemption {
+        Redemption {
+            channel: channel.to_string(),
+            user_id: "12345678".to_string(),
+            user_name: user_name.to_string(),
+            input: input.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_actions() {
+        assert_eq!(
+            Action::new("say", " hi $(user) ", None),
+            Ok(Action::Say("hi $(user)".to_string()))
+        );
+        assert_eq!(
+            Action::new("role", "<@&1234>", Some(30)),
+            Ok(Action::Role { role: RoleId::new(1234), minutes: 30 })
+        );
+        assert!(Action::new("role", "1234", None).is_err());
+        assert_eq!(Action::new("command", "!Hug", None), Ok(Action::Command("hug".to_string())));
+        assert!(Action::new("dance", "", None).is_err());
+        for kind in Action::KINDS {
+            let action = Action::new(kind, "1234", Some(5)).unwrap();
+            assert_eq!(action.kind(), kind);
+        }
+    }
+
+    #[test]
+    fn expands_redemptions() {
+        let redemption = redemption("testchannel", "TestUser", "Celeste");
+        assert_eq!(
+            expand("$(user) picked $(input) in $(channel) $(count)", &redemption),
+            "TestUser picked Celeste in testchannel $(count)"
+        );
+    }
+
+    #[test]
+    fn queues_in_order() {
+        let channel = "testqueue";
+        clear(channel);
+        enqueue(&redemption(channel, "First", "Celeste"));
+        enqueue(&redemption(channel, "Second", ""));
+        assert_eq!(queue("TestQueue").len(), 2);
+        assert_eq!(next(channel).map(|e| e.user_name), Some("First".to_string()));
+        assert_eq!(clear(channel), 1);
+        assert!(next(channel).is_none());
+    }
+}
@@ -0,0 +1,295 @@
+//!Keeps Discord roles in step with Twitch subscriber, VIP and moderator status.
+//!
+//!Every Discord member linked through the `users` table is checked against the bot's own channel,
+//!the only broadcaster we hold a token for, and granted or stripped of the roles configured in
+//!`[role_sync]`. A full sync runs every `interval_minutes` and whenever [`request_sync`] is called
+//!by an EventSub subscription, VIP or moderator event, bursts of events only trigger one sync.
+
+//crate
+use crate::config::RoleSync;
+use crate::db;
+use crate::discord::HTTP;
+use crate::twitch::helix;
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, info, debug};
+
+use lazy_static::lazy_static;
+
+//serenity
+use serenity::all::{GuildId, RoleId, UserId};
+
+//std
+use std::collections::HashMap;
+use std::time::Duration;
+
+//tokio
+use tokio::sync::Notify;
+
+//twitch_api
+use twitch_api::{
+    helix::{
+        channels::GetVipsRequest, moderation::GetModeratorsRequest,
+        subscriptions::GetBroadcasterSubscriptionsRequest,
+    },
+    types::SubscriptionTier,
+};
+
+const AUDIT_REASON: &str = "Twitch role sync";
+
+lazy_static! {
+    static ref SYNC_REQUESTED: Notify = Notify::new();
+}
+
+///A linked user's standing in the broadcaster's channel.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Status {
+    ///Subscription tier from 1 to 3, Prime counts as tier 1.
+    pub tier: Option<u8>,
+    pub vip: bool,
+    pub moderator: bool,
+}
+
+///The Discord roles managed by the sync, parsed from [`RoleSync`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RoleMap {
+    tiers: [Option<RoleId>; 3],
+    vip: Option<RoleId>,
+    moderator: Option<RoleId>,
+}
+
+impl RoleMap {
+    ///Parses the configured role ids, ignoring any that aren't valid.
+    pub fn new(config: &RoleSync) -> Self {
+        fn role(id: &Option<String>) -> Option<RoleId> {
+            let id = id.as_ref()?;
+            match id.parse::<u64>() {
+                Ok(id) if id != 0 => Some(RoleId::new(id)),
+                _ => {
+                    error!("Ignoring invalid role id `{id}` in [role_sync]");
+                    None
+                },
+            }
+        }
+        Self {
+            tiers: [
+                role(&config.tier1_role_id),
+                role(&config.tier2_role_id),
+                role(&config.tier3_role_id),
+            ],
+            vip: role(&config.vip_role_id),
+            moderator: role(&config.moderator_role_id),
+        }
+    }
+
+    ///Every role the sync is allowed to grant or remove.
+    pub fn managed(&self) -> Vec<RoleId> {
+        self.tiers.iter().chain([&self.vip, &self.moderator]).flatten().copied().collect()
+    }
+
+    ///The roles a member with `status` should hold.
+    pub fn desired(&self, status: Status) -> Vec<RoleId> {
+        let tier = status
+            .tier
+            .and_then(|t| self.tiers.get(usize::from(t).checked_sub(1)?).copied().flatten());
+        [tier, self.vip.filter(|_| status.vip), self.moderator.filter(|_| status.moderator)]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    ///Returns the roles to add and remove to take a member from `current` to `status`.
+    pub fn changes(&self, current: &[RoleId], status: Status) -> (Vec<RoleId>, Vec<RoleId>) {
+        let desired = self.desired(status);
+        let add = desired.iter().filter(|r| !current.contains(r)).copied().collect();
+        let remove = self
+            .managed()
+            .into_iter()
+            .filter(|r| current.contains(r) && !desired.contains(r))
+            .collect();
+        (add, remove)
+    }
+}
+
+fn tier(tier: &SubscriptionTier) -> u8 {
+    match tier {
+        SubscriptionTier::Tier2 => 2,
+        SubscriptionTier::Tier3 => 3,
+        _ => 1,
+    }
+}
+
+///Asks [`run`] to sync as soon as possible.
+pub fn request_sync() {
+    SYNC_REQUESTED.notify_one();
+}
+
+///Fetches the status of everyone with a subscription, VIP or moderator badge in the bot's
+///channel, keyed by Twitch user id.
+async fn fetch_statuses() -> eyre::Result<HashMap<String, Status>> {
+    let helix = helix::get().ok_or_else(|| eyre::eyre!("Helix client isn't initialised yet"))?;
+    let token = helix.token().await?;
+    let client = &helix.client;
+    let broadcaster = client
+        .get_user_from_login(CONFIG.twitch_bot_name.as_str(), &token)
+        .await?
+        .ok_or_else(|| eyre::eyre!("no user found with name {}", CONFIG.twitch_bot_name))?
+        .id;
+    let mut statuses: HashMap<String, Status> = HashMap::new();
+
+    let mut page = client
+        .req_get(GetBroadcasterSubscriptionsRequest::broadcaster_id(&broadcaster), &token)
+        .await?;
+    loop {
+        for sub in &page.data {
+            // the broadcaster shows up as their own subscriber
+            if sub.user_id != broadcaster {
+                statuses.entry(sub.user_id.to_string()).or_default().tier = Some(tier(&sub.tier));
+            }
+        }
+        match page.get_next(client, &token).await? {
+            Some(next) => page = next,
+            None => break,
+        }
+    }
+
+    let mut page = client.req_get(GetVipsRequest::broadcaster_id(&broadcaster), &token).await?;
+    loop {
+        for vip in &page.data {
+            statuses.entry(vip.user_id.to_string()).or_default().vip = true;
+        }
+        match page.get_next(client, &token).await? {
+            Some(next) => page = next,
+            None => break,
+        }
+    }
+
+    let mut page =
+        client.req_get(GetModeratorsRequest::broadcaster_id(&broadcaster), &token).await?;
+    loop {
+        for moderator in &page.data {
+            statuses.entry(moderator.user_id.to_string()).or_default().moderator = true;
+        }
+        match page.get_next(client, &token).await? {
+            Some(next) => page = next,
+            None => break,
+        }
+    }
+    Ok(statuses)
+}
+
+///Brings the managed roles of every linked Discord member up to date.
+pub async fn sync(roles: &RoleMap) -> eyre::Result<()> {
+    let guild_id = GuildId::new(CONFIG.discord_guildid.parse()?);
+    let statuses = fetch_statuses().await?;
+    let linked = db::blocking(db::find_all_linked_users).await?;
+    let (mut added, mut removed) = (0, 0);
+    for user in linked {
+        let status = statuses.get(&user.twitch_id.to_string()).copied().unwrap_or_default();
+        let user_id = UserId::new(user.discord_id);
+        let member = match guild_id.member(&*HTTP, user_id).await {
+            Ok(member) => member,
+            Err(e) => {
+                debug!("skipping linked user {user_id} who isn't in the guild: {e}");
+                continue;
+            },
+        };
+        let (add, remove) = roles.changes(&member.roles, status);
+        for role in add {
+            match HTTP.add_member_role(guild_id, user_id, role, Some(AUDIT_REASON)).await {
+                Ok(()) => added += 1,
+                Err(e) => error!("Unable to add role {role} to {user_id}: {e}"),
+            }
+        }
+        for role in remove {
+            match HTTP.remove_member_role(guild_id, user_id, role, Some(AUDIT_REASON)).await {
+                Ok(()) => removed += 1,
+                Err(e) => error!("Unable to remove role {role} from {user_id}: {e}"),
+            }
+        }
+    }
+    info!("role sync granted {added} and removed {removed} role(s)");
+    Ok(())
+}
+
+///Time between full syncs, at least a minute so a `0` doesn't spin.
+fn sync_interval(config: &RoleSync) -> Duration {
+    Duration::from_secs(config.interval_minutes.unwrap_or(30).max(1) * 60)
+}
+
+///Syncs roles on an interval and whenever one is requested, returns immediately if `[role_sync]`
+///isn't configured.
+#[allow(unused)]
+pub async fn run() {
+    let Some(config) = CONFIG.role_sync.as_ref() else {
+        return;
+    };
+    let roles = RoleMap::new(config);
+    if roles.managed().is_empty() {
+        return;
+    }
+    let mut interval = tokio::time::interval(sync_interval(config));
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {},
+            _ = SYNC_REQUESTED.notified() => {},
+        }
+        if let Err(e) = sync(&roles).await {
+            error!("Role sync failed: {e:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roles() -> RoleMap {
+        RoleMap::new(&RoleSync {
+            interval_minutes: None,
+            tier1_role_id: Some("1".to_string()),
+            tier2_role_id: Some("2".to_string()),
+            tier3_role_id: None,
+            vip_role_id: Some("4".to_string()),
+            moderator_role_id: Some("5".to_string()),
+        })
+    }
+
+    #[test]
+    fn desired_roles() {
+        let roles = roles();
+        assert_eq!(roles.desired(Status::default()), vec![]);
+        let status = Status { tier: Some(2), vip: true, moderator: false };
+        assert_eq!(roles.desired(status), vec![RoleId::new(2), RoleId::new(4)]);
+        // tier 3 has no role configured
+        assert_eq!(roles.desired(Status { tier: Some(3), ..Default::default() }), vec![]);
+    }
+
+    #[test]
+    fn changes_only_touch_managed_roles() {
+        let roles = roles();
+        let current = [RoleId::new(1), RoleId::new(5), RoleId::new(99)];
+        let status = Status { tier: Some(2), vip: false, moderator: true };
+        let (add, remove) = roles.changes(&current, status);
+        assert_eq!(add, vec![RoleId::new(2)]);
+        assert_eq!(remove, vec![RoleId::new(1)]);
+    }
+
+    #[test]
+    fn interval_is_at_least_a_minute() {
+        assert_eq!(sync_interval(&RoleSync::default()), Duration::from_secs(30 * 60));
+        let zero = RoleSync { interval_minutes: Some(0), ..Default::default() };
+        assert_eq!(sync_interval(&zero), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn ignores_invalid_role_ids() {
+        let roles = RoleMap::new(&RoleSync {
+            tier1_role_id: Some("not a role".to_string()),
+            vip_role_id: Some("0".to_string()),
+            ..Default::default()
+        });
+        assert!(roles.managed().is_empty());
+    }
+}
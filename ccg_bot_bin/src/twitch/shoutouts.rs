@@ -0,0 +1,231 @@
+//!Native Twitch shoutouts, sent by `!so` and optionally for incoming raids.
+//!
+//!Twitch only allows a shoutout every 2 minutes per channel and the same streamer to be shouted
+//!out once an hour, so the chat message goes out straight away while the native shoutout waits in
+//!a per channel queue until both cooldowns allow it. A streamer already waiting isn't queued
+//!twice. Shoutouts only work while the channel is live and the bot is one of its moderators, a
+//!failed one is logged and dropped.
+
+//crate
+use crate::twitch::helix;
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, warn, info, debug};
+
+use lazy_static::lazy_static;
+
+//std
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+//twitch_api
+use twitch_api::helix::{chat::SendAShoutoutRequest, EmptyBody};
+use twitch_api::types::UserId;
+
+///Time between any two shoutouts in a channel.
+const CHANNEL_COOLDOWN: Duration = Duration::from_secs(2 * 60);
+///Time before the same streamer can be shouted out again in a channel.
+const TARGET_COOLDOWN: Duration = Duration::from_secs(60 * 60);
+///How often the queues are checked.
+const TICK: Duration = Duration::from_secs(5);
+
+lazy_static! {
+    static ref QUEUES: Mutex<HashMap<String, Queue>> = Mutex::new(HashMap::new());
+}
+
+fn queues() -> std::sync::MutexGuard<'static, HashMap<String, Queue>> {
+    match QUEUES.lock() {
+        Ok(queues) => queues,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+///A native shoutout waiting for the cooldowns.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Pending {
+    pub broadcaster_id: String,
+    pub target_id: String,
+    pub target_name: String,
+}
+
+///The shoutouts waiting in one channel.
+#[derive(Debug, Default)]
+pub struct Queue {
+    pending: VecDeque<Pending>,
+    ///When the channel last sent a shoutout.
+    last: Option<Instant>,
+    ///When each streamer was last shouted out, keyed by user id.
+    recent: HashMap<String, Instant>,
+}
+
+impl Queue {
+    ///Queues `shoutout` unless its target is already waiting, returning how many are ahead of it.
+    pub fn push(&mut self, shoutout: Pending) -> usize {
+        match self.pending.iter().position(|p| p.target_id == shoutout.target_id) {
+            Some(position) => position,
+            None => {
+                self.pending.push_back(shoutout);
+                self.pending.len() - 1
+            },
+        }
+    }
+
+    ///Takes the first shoutout both cooldowns allow at `now`, if any.
+    pub fn pop_ready(&mut self, now: Instant) -> Option<Pending> {
+        if self.last.is_some_and(|last| now.duration_since(last) < CHANNEL_COOLDOWN) {
+            return None;
+        }
+        self.recent.retain(|_, at| now.duration_since(*at) < TARGET_COOLDOWN);
+        let position = self.pending.iter().position(|p| !self.recent.contains_key(&p.target_id))?;
+        let shoutout = self.pending.remove(position)?;
+        self.last = Some(now);
+        self.recent.insert(shoutout.target_id.clone(), now);
+        Some(shoutout)
+    }
+}
+
+///The chat message announcing a shoutout for `name`.
+pub fn message(name: &str, login: &str, game: &str) -> String {
+    match game.is_empty() {
+        true => format!("Go check out {name} at https://twitch.tv/{login}"),
+        false => {
+            format!(
+                "Go check out {name} at https://twitch.tv/{login}, they were last playing {game}"
+            )
+        },
+    }
+}
+
+///Queues a native shoutout of `login` in `channel`, returning the chat message to go with it.
+///
+///Returns [`None`] if there is no channel called `login`.
+pub async fn shoutout(
+    channel: &str,
+    broadcaster_id: &str,
+    login: &str,
+) -> eyre::Result<Option<String>> {
+    let helix = helix::get().ok_or_else(|| eyre::eyre!("Helix client isn't initialised yet"))?;
+    let token = helix.token().await?;
+    let Some(target) = helix.client.get_channel_from_login(login, &token).await? else {
+        return Ok(None);
+    };
+    let position = queues().entry(channel.to_lowercase()).or_default().push(Pending {
+        broadcaster_id: broadcaster_id.to_string(),
+        target_id: target.broadcaster_id.to_string(),
+        target_name: target.broadcaster_name.to_string(),
+    });
+    debug!("queued a shoutout of {login} in #{channel} behind {position} others");
+    Ok(Some(message(
+        target.broadcaster_name.as_str(),
+        target.broadcaster_login.as_str(),
+        &target.game_name,
+    )))
+}
+
+///Queues a shoutout for a raid from `raider` into `channel` if `[shoutouts]` asks for one.
+pub async fn raided(channel: &str, broadcaster_id: &str, raider: &str, viewers: i64) {
+    let Some(settings) = CONFIG.shoutouts.as_ref() else {
+        return;
+    };
+    if !settings.raids.unwrap_or(false) || viewers < i64::from(settings.min_raiders.unwrap_or(1)) {
+        return;
+    }
+    info!("{raider} raided #{channel} with {viewers} viewers");
+    let announcement = match shoutout(channel, broadcaster_id, raider).await {
+        Ok(Some(announcement)) => announcement,
+        Ok(None) => return,
+        Err(e) => return error!("Unable to shout out raider {raider} in #{channel}: {e:?}"),
+    };
+    let Some(client) = super::IRC_CLIENT.get() else {
+        return;
+    };
+    if let Err(e) = client.say(channel.to_string(), announcement).await {
+        error!("Unable to announce raider {raider} in #{channel}: {e}");
+    }
+}
+
+async fn send(shoutout: &Pending) -> eyre::Result<()> {
+    let helix = helix::get().ok_or_else(|| eyre::eyre!("Helix client isn't initialised yet"))?;
+    let token = helix.token().await?;
+    let request = SendAShoutoutRequest::new(
+        UserId::from(shoutout.broadcaster_id.clone()),
+        UserId::from(shoutout.target_id.clone()),
+        token.uid.clone(),
+    );
+    helix.client.req_post(request, EmptyBody, &token).await?;
+    Ok(())
+}
+
+///Sends queued shoutouts as the cooldowns allow, forever.
+#[allow(unused)]
+pub async fn run() {
+    let mut interval = tokio::time::interval(TICK);
+    loop {
+        interval.tick().await;
+        let now = Instant::now();
+        let ready: Vec<(String, Pending)> = queues()
+            .iter_mut()
+            .filter_map(|(channel, queue)| Some((channel.clone(), queue.pop_ready(now)?)))
+            .collect();
+        for (channel, shoutout) in ready {
+            match send(&shoutout).await {
+                Ok(()) => debug!("shouted out {} in #{channel}", shoutout.target_name),
+                Err(e) => warn!("Unable to shout out {} in #{channel}: {e}", shoutout.target_name),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(target: &str) -> Pending {
+        Pending {
+            broadcaster_id: "12345678".to_string(),
+            target_id: target.to_string(),
+            target_name: format!("Streamer{target}"),
+        }
+    }
+
+    #[test]
+    fn waits_for_channel_cooldown() {
+        let start = Instant::now();
+        let mut queue = Queue::default();
+        assert_eq!(queue.push(pending("1")), 0);
+        assert_eq!(queue.push(pending("2")), 1);
+        assert_eq!(queue.push(pending("1")), 0);
+        assert_eq!(queue.pop_ready(start), Some(pending("1")));
+        assert_eq!(queue.pop_ready(start + Duration::from_secs(60)), None);
+        assert_eq!(queue.pop_ready(start + CHANNEL_COOLDOWN), Some(pending("2")));
+        assert_eq!(queue.pop_ready(start + CHANNEL_COOLDOWN * 2), None);
+    }
+
+    #[test]
+    fn skips_targets_on_cooldown() {
+        let start = Instant::now();
+        let mut queue = Queue::default();
+        queue.push(pending("1"));
+        assert_eq!(queue.pop_ready(start), Some(pending("1")));
+        queue.push(pending("1"));
+        queue.push(pending("2"));
+        let later = start + CHANNEL_COOLDOWN;
+        assert_eq!(queue.pop_ready(later), Some(pending("2")));
+        assert_eq!(queue.pop_ready(later + CHANNEL_COOLDOWN), None);
+        assert_eq!(queue.pop_ready(start + TARGET_COOLDOWN), Some(pending("1")));
+    }
+
+    #[test]
+    fn formats_messages() {
+        assert_eq!(
+            message("TestUser", "testuser", "Just Chatting"),
+            "Go check out TestUser at https://twitch.tv/testuser, they were last playing Just Chatting"
+        );
+        assert_eq!(
+            message("TestUser", "testuser", ""),
+            "Go check out TestUser at https://twitch.tv/testuser"
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//!Recurring chat messages, such as socials or schedule reminders, posted on an interval.
+//!
+//!Timers are stored in the `timers` table and managed with `!timer`. A timer posts once its
+//!interval has passed and at least `min_lines` chat lines have been seen in the channel since its
+//!last post, so a quiet chat isn't filled with the bot talking to itself. Timers marked live only
+//!wait until the channel is streaming. Nothing is posted for a timer until one interval after the
+//!bot first sees it.
+
+//crate
+use crate::db::{self, models::Timer};
+use crate::discord::bridge::{truncate, TWITCH_MESSAGE_LIMIT};
+use crate::twitch::{helix, IRC_CLIENT};
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, debug};
+
+use lazy_static::lazy_static;
+
+//std
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+//twitch_api
+use twitch_api::{helix::streams::GetStreamsRequest, types::UserName};
+
+///How often timers are checked.
+const TICK: Duration = Duration::from_secs(30);
+
+lazy_static! {
+    static ref STATE: Mutex<State> = Mutex::new(State::default());
+}
+
+#[derive(Debug, Default)]
+struct State {
+    ///Chat lines seen in each channel since the bot started.
+    lines: HashMap<String, u64>,
+    ///When each timer last posted and the channel's line count at the time, keyed by timer id.
+    posted: HashMap<u32, (Instant, u64)>,
+}
+
+fn state() -> std::sync::MutexGuard<'static, State> {
+    match STATE.lock() {
+        Ok(state) => state,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+///Counts a chat line towards the timers of `channel`.
+#[allow(unused)]
+pub fn count_line(channel: &str) {
+    *state().lines.entry(channel.to_lowercase()).or_default() += 1;
+}
+
+///Whether `timer` may post at `now`, given its last post and the channel's line count.
+pub fn due(
+    timer: &Timer,
+    (posted_at, lines_then): (Instant, u64),
+    lines: u64,
+    now: Instant,
+) -> bool {
+    let interval = Duration::from_secs(u64::from(timer.interval_minutes) * 60);
+    now.duration_since(posted_at) >= interval
+        && lines.saturating_sub(lines_then) >= u64::from(timer.min_lines)
+}
+
+async fn is_live(channel: &str) -> eyre::Result<bool> {
+    let helix = helix::get().ok_or_else(|| eyre::eyre!("helix isn't ready"))?;
+    let token = helix.token().await?;
+    let logins = [UserName::from(channel)];
+    let request = GetStreamsRequest::user_logins(&logins[..]);
+    Ok(!helix.client.req_get(request, &token).await?.data.is_empty())
+}
+
+async fn fire(timer: &Timer) -> eyre::Result<()> {
+    let now = Instant::now();
+    let lines = {
+        let mut state = state();
+        let lines = state.lines.get(&timer.channel).copied().unwrap_or_default();
+        let last = *state.posted.entry(timer.id).or_insert((now, lines));
+        if !due(timer, last, lines, now) {
+            return Ok(());
+        }
+        lines
+    };
+    if timer.live_only && !is_live(&timer.channel).await? {
+        return Ok(());
+    }
+    let client = IRC_CLIENT.get().ok_or_else(|| eyre::eyre!("chat isn't connected"))?;
+    client
+        .say(timer.channel.clone(), truncate(&timer.message, TWITCH_MESSAGE_LIMIT))
+        .await
+        .map_err(|e| eyre::eyre!("{e}"))?;
+    debug!("posted timer {} in #{}", timer.name, timer.channel);
+    state().posted.insert(timer.id, (now, lines));
+    Ok(())
+}
+
+///Posts due timers forever.
+#[allow(unused)]
+pub async fn run() {
+    let mut interval = tokio::time::interval(TICK);
+    loop {
+        interval.tick().await;
+        let timers = match db::blocking(db::find_all_timers).await {
+            Ok(timers) => timers,
+            Err(e) => {
+                error!("Unable to load timers: {e:?}");
+                continue;
+            },
+        };
+        let ids: HashSet<u32> = timers.iter().map(|t| t.id).collect();
+        state().posted.retain(|id, _| ids.contains(id));
+        for timer in &timers {
+            if let Err(e) = fire(timer).await {
+                error!("Timer {} in #{} failed: {e:?}", timer.name, timer.channel);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timer(interval_minutes: u32, min_lines: u32) -> Timer {
+        Timer {
+            id: 1,
+            channel: "testchannel".to_string(),
+            name: "socials".to_string(),
+            message: "Follow us on Discord".to_string(),
+            interval_minutes,
+            min_lines,
+            live_only: false,
+        }
+    }
+
+    #[test]
+    fn waits_for_interval_and_lines() {
+        let start = Instant::now();
+        let socials = timer(10, 5);
+        let later = start + Duration::from_secs(600);
+        assert!(!due(&socials, (start, 0), 5, start + Duration::from_secs(599)));
+        assert!(!due(&socials, (start, 3), 7, later));
+        assert!(due(&socials, (start, 3), 8, later));
+        assert!(due(&timer(10, 0), (start, 3), 3, later));
+    }
+
+    #[test]
+    fn counts_lines_per_channel() {
+        count_line("TimerChannel");
+        count_line("timerchannel");
+        assert_eq!(state().lines.get("timerchannel"), Some(&2));
+    }
+}
@@ -40,6 +40,8 @@ use crate::{debug, trace};
 #[cfg(not(test))]
 use crate::twitch::api;
 use crate::utils::approx_instant;
+#[cfg(not(test))]
+use crate::{error, warn};
 
 //std
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
@@ -215,6 +217,9 @@ pub(crate) struct Token {
     pub uid: UserId,
     pub name: UserName,
     pub(crate) client_secret: ClientSecret,
+    /// What the token was actually granted, filled in by [`Token::validate`].
+    #[serde(default)]
+    pub scopes: Vec<Scope>,
 }
 
 impl Token {
@@ -276,6 +281,19 @@ impl Token {
         self.clone()
     }
 
+    /// Asks Twitch which scopes the token holds and returns those of `wanted` it's missing.
+    #[allow(unused)]
+    pub async fn validate<C: Client>(
+        &mut self,
+        client: &C,
+        wanted: &[Scope],
+    ) -> eyre::Result<Vec<Scope>> {
+        let validated =
+            self.access_token.validate_token(client).await.map_err(|e| eyre::eyre!("{e}"))?;
+        self.scopes = validated.scopes.unwrap_or_default();
+        Ok(wanted.iter().filter(|s| !self.scopes.contains(s)).cloned().collect())
+    }
+
     pub fn block_set_uid(&mut self) {
         tokio::task::block_in_place(move || {
             tokio::runtime::Handle::current().block_on(async move {
@@ -330,7 +348,7 @@ impl TwitchToken for Token {
     }
 
     fn scopes(&self) -> &[Scope] {
-        &super::api::SCOPE
+        &self.scopes
     }
 }
 
@@ -348,6 +366,7 @@ impl std::fmt::Debug for Token {
             .field("uid", &self.uid.clone().take())
             .field("name", &self.name.clone().take())
             .field("client_secret", &self.client_secret)
+            .field("scopes", &self.scopes)
             .finish()
     }
 }
@@ -446,6 +465,34 @@ impl BotTokenStorage {
     pub async fn token(&mut self) -> Token {
         self.load_token().await.unwrap().into()
     }
+
+    /// The bot's token with the scopes it was granted, asking for authorization once more when
+    /// it lacks some of [`api::SCOPE`], as a token from before an update that needs new ones does.
+    #[cfg(not(test))]
+    pub async fn authorized_token<C: Client>(&mut self, client: &C) -> eyre::Result<Token> {
+        let mut token = self.token().await;
+        let missing = token.validate(client, &api::SCOPE).await?;
+        if missing.is_empty() {
+            return Ok(token);
+        }
+        warn!("The bot's token is missing {missing:?}, authorize it again to grant them");
+        let config = crate::CONFIG.clone();
+        let (access_token, api_handle) = api::new(
+            config.twitch_client_id,
+            config.twitch_client_secret,
+            config.twitch_redirect_url,
+        )
+        .await
+        .map_err(|e| eyre::eyre!("Unable to authorize the bot again: {e}"))?;
+        api_handle.abort();
+        self.update_token(&access_token).await?;
+        token = self.token().await;
+        let missing = token.validate(client, &api::SCOPE).await?;
+        if !missing.is_empty() {
+            error!("The bot still lacks {missing:?}, anything needing them will fail");
+        }
+        Ok(token)
+    }
 }
 
 impl From<UserAccessToken> for Token {
@@ -471,6 +518,7 @@ impl From<UserAccessToken> for Token {
             uid: UserId::from_static(""),
             name: UserName::new(config.twitch_bot_name.clone()),
             client_secret: ClientSecret::new(config.twitch_client_secret.clone()),
+            scopes: vec![],
         };
         token.block_set_uid();
         token
@@ -495,6 +543,7 @@ impl From<GetAccessTokenResponse> for Token {
             uid: UserId::from_static(""),
             name: UserName::new(config.twitch_bot_name.clone()),
             client_secret: ClientSecret::new(config.twitch_client_secret.clone()),
+            scopes: vec![],
         };
         token.block_set_uid();
         token
@@ -577,6 +626,7 @@ impl TokenStorage for BotTokenStorage {
             uid,
             name,
             client_secret: ClientSecret::new(config.twitch_client_secret.clone()),
+            scopes: vec![],
         };
         let uat = UserAccessToken {
             access_token: token.access_token.clone().take(),
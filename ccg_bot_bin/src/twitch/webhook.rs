@@ -0,0 +1,348 @@
+//!EventSub over webhooks, served by the bot's Rocket server.
+//!
+//!Twitch posts every message to `/eventsub/callback`. A message is only trusted once its
+//!`Twitch-Eventsub-Message-Signature` HMAC checks out against the configured secret and its
+//!timestamp is recent, and Twitch retries deliveries so each message id is only handled once.
+//!Notifications go to the same [handlers](super::events) as the websocket. Webhook subscriptions
+//!are created with the app token once the server is up, which isn't limited per connection, and
+//!cover the broadcaster-only events of every channel whose broadcaster authorized the bot.
+
+//crate
+use crate::config::EventSubWebhook;
+use crate::twitch::broadcasters;
+use crate::twitch::eventsub::{broadcaster_of, subscribe_to};
+use crate::twitch::tokens::AppToken;
+use crate::CONFIG;
+//skip reordering to allow easy reference to verbosity(from least to most)
+#[rustfmt::skip]
+use crate::{error, warn, info, debug};
+
+//chrono
+use chrono::{DateTime, Utc};
+
+//futures
+use futures::TryStreamExt;
+
+//hmac
+use hmac::{Hmac, Mac};
+
+use lazy_static::lazy_static;
+
+//rocket
+use rocket::data::{Data, ToByteUnit};
+use rocket::fairing::AdHoc;
+use rocket::http::Status;
+use rocket::request::{self, FromRequest, Request};
+use rocket::{post, routes, Route};
+
+//sha2
+use sha2::Sha256;
+
+//std
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+//twitch_api
+use twitch_api::eventsub::{self, Event, EventSubSubscription};
+use twitch_api::{client::ClientDefault, HelixClient};
+
+///How old a message may be before it is rejected, and how long its id is remembered.
+const MAX_AGE: Duration = Duration::from_secs(10 * 60);
+///Largest body accepted, notifications are a few KiB at most.
+const BODY_LIMIT: u64 = 1;
+
+lazy_static! {
+    static ref SEEN: Mutex<Seen> = Mutex::new(Seen::default());
+}
+
+///The `Twitch-Eventsub-*` headers every message carries.
+#[derive(Debug)]
+pub struct Headers {
+    ///Unique per message, the same across retries.
+    pub id: String,
+    ///When the message was sent, RFC 3339.
+    pub timestamp: String,
+    ///`sha256=` and the hex HMAC of the id, timestamp and body.
+    pub signature: String,
+    ///`notification`, `webhook_callback_verification` or `revocation`.
+    pub kind: String,
+    ///The subscription type, e.g. `stream.online`.
+    pub subscription_type: String,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Headers {
+    type Error = &'static str;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let header = |name| request.headers().get_one(name).map(str::to_string);
+        match (
+            header("Twitch-Eventsub-Message-Id"),
+            header("Twitch-Eventsub-Message-Timestamp"),
+            header("Twitch-Eventsub-Message-Signature"),
+            header("Twitch-Eventsub-Message-Type"),
+        ) {
+            (Some(id), Some(timestamp), Some(signature), Some(kind)) => {
+                request::Outcome::Success(Headers {
+                    id,
+                    timestamp,
+                    signature,
+                    kind,
+                    subscription_type: header("Twitch-Eventsub-Subscription-Type")
+                        .unwrap_or_default(),
+                })
+            },
+            _ => request::Outcome::Error((Status::BadRequest, "missing Twitch-Eventsub headers")),
+        }
+    }
+}
+
+///Message ids handled within [`MAX_AGE`].
+#[derive(Debug, Default)]
+pub struct Seen {
+    ids: HashMap<String, Instant>,
+}
+
+impl Seen {
+    ///Whether `id` is new at `now`, remembering it if so.
+    pub fn first(&mut self, id: &str, now: Instant) -> bool {
+        self.ids.retain(|_, seen| now.duration_since(*seen) < MAX_AGE);
+        if self.ids.contains_key(id) {
+            return false;
+        }
+        self.ids.insert(id.to_string(), now);
+        true
+    }
+}
+
+fn seen() -> std::sync::MutexGuard<'static, Seen> {
+    match SEEN.lock() {
+        Ok(seen) => seen,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()).collect()
+}
+
+///Whether `signature` is the HMAC of the message under `secret`, compared in constant time.
+pub fn verify(secret: &str, id: &str, timestamp: &str, body: &[u8], signature: &str) -> bool {
+    let Some(expected) = signature.strip_prefix("sha256=").and_then(decode_hex) else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(id.as_bytes());
+    mac.update(timestamp.as_bytes());
+    mac.update(body);
+    mac.verify_slice(&expected).is_ok()
+}
+
+///Whether `timestamp` is within [`MAX_AGE`] of `now`, older messages may be replays.
+pub fn fresh(timestamp: &str, now: DateTime<Utc>) -> bool {
+    let Ok(sent) = DateTime::parse_from_rfc3339(timestamp) else {
+        return false;
+    };
+    let age = (now - sent.with_timezone(&Utc)).abs();
+    age.to_std().is_ok_and(|age| age <= MAX_AGE)
+}
+
+///The challenge to echo back from a `webhook_callback_verification` body.
+pub fn challenge(body: &str) -> Option<String> {
+    let value: serde_json::Value = crate::utils::json::from_str(body).ok()?;
+    value.get("challenge")?.as_str().map(str::to_string)
+}
+
+#[post("/eventsub/callback", data = "<data>")]
+async fn callback(headers: Headers, data: Data<'_>) -> (Status, String) {
+    let Some(webhook) = config() else {
+        return (Status::NotFound, String::new());
+    };
+    let body = match data.open(BODY_LIMIT.mebibytes()).into_string().await {
+        Ok(body) if body.is_complete() => body.into_inner(),
+        _ => return (Status::PayloadTooLarge, String::new()),
+    };
+    let (id, timestamp) = (headers.id.as_str(), headers.timestamp.as_str());
+    if !verify(&webhook.secret, id, timestamp, body.as_bytes(), &headers.signature) {
+        warn!("rejected an eventsub message with a bad signature");
+        return (Status::Forbidden, String::new());
+    }
+    if !fresh(timestamp, Utc::now()) {
+        warn!("rejected eventsub message {id} sent at {timestamp}");
+        return (Status::Forbidden, String::new());
+    }
+    match headers.kind.as_str() {
+        "webhook_callback_verification" => match challenge(&body) {
+            Some(challenge) => {
+                info!("verified the {} webhook", headers.subscription_type);
+                (Status::Ok, challenge)
+            },
+            None => (Status::BadRequest, String::new()),
+        },
+        "notification" => match Event::parse(&body) {
+            // only a handled notification counts as seen, so Twitch's retry of a bad one isn't lost
+            Ok(_) if !seen().first(id, Instant::now()) => {
+                debug!("eventsub message {id} was already handled");
+                (Status::NoContent, String::new())
+            },
+            Ok(event) => {
+                super::events::dispatch(&headers.subscription_type, event);
+                (Status::NoContent, String::new())
+            },
+            Err(e) => {
+                error!("Unable to parse {} notification: {e}", headers.subscription_type);
+                (Status::BadRequest, String::new())
+            },
+        },
+        "revocation" => {
+            warn!("subscription revoked: {body}");
+            (Status::NoContent, String::new())
+        },
+        kind => {
+            debug!("ignoring eventsub message of type {kind}");
+            (Status::NoContent, String::new())
+        },
+    }
+}
+
+///The webhook transport, [`None`] when the websocket is used.
+pub fn config() -> Option<EventSubWebhook> {
+    CONFIG.eventsub.as_ref().and_then(|e| e.webhook.clone())
+}
+
+///The callback route, mounted by [`api::serve`](super::api::serve).
+pub fn routes() -> Vec<Route> {
+    routes![callback]
+}
+
+///Subscribes once the server is listening, so Twitch can verify the callback straight away.
+pub fn liftoff(app_token: AppToken) -> AdHoc {
+    AdHoc::on_liftoff("EventSub subscriptions", move |_| {
+        let app_token = app_token.clone();
+        Box::pin(async move {
+            if let Err(e) = subscribe(&app_token).await {
+                error!("Unable to create webhook subscriptions: {e:?}");
+            }
+        })
+    })
+}
+
+///Deletes our failed or revoked webhook subscriptions and creates the missing ones.
+async fn subscribe(app_token: &AppToken) -> eyre::Result<()> {
+    let Some(webhook) = config() else {
+        return Ok(());
+    };
+    let client: HelixClient<'static, reqwest::Client> = HelixClient::with_client(
+        <reqwest::Client>::default_client_with_name(Some("twitch-rs/eventsub".parse()?))?,
+    );
+    let subscriptions: Vec<EventSubSubscription> = client
+        .get_eventsub_subscriptions(None, None, None, app_token)
+        .map_ok(|page| futures::stream::iter(page.subscriptions.into_iter().map(Ok)))
+        .try_flatten()
+        .try_collect()
+        .await?;
+    let mut active = HashSet::new();
+    for subscription in subscriptions {
+        let eventsub::TransportResponse::Webhook(ref transport) = subscription.transport else {
+            continue;
+        };
+        if transport.callback != webhook.callback_url {
+            continue;
+        }
+        if subscription.status == eventsub::Status::Enabled {
+            if let Some(id) = broadcaster_of(&subscription.condition) {
+                active.insert((subscription.type_.to_string(), id));
+            }
+            continue;
+        }
+        match client.delete_eventsub_subscription(subscription.id.clone(), app_token).await {
+            Ok(_) => debug!("deleted {:?} subscription {}", subscription.status, subscription.id),
+            Err(e) => warn!("Unable to delete subscription {}: {e:?}", subscription.id),
+        }
+    }
+    let transport = eventsub::Transport::webhook(webhook.callback_url, webhook.secret);
+    let mut created = 0;
+    for channel in super::channels::current().await {
+        match subscribe_channel(&client, app_token, &channel, &transport, &active).await {
+            Ok(count) => created += count,
+            Err(e) => error!("[{channel}] Unable to subscribe: {e:?}"),
+        }
+    }
+    info!("{} webhook subscriptions active, {created} created", active.len() + created);
+    Ok(())
+}
+
+///Creates the webhook subscriptions `channel` wants that aren't in `active`, returning how many
+///were created.
+pub(crate) async fn subscribe_channel(
+    client: &HelixClient<'static, reqwest::Client>,
+    app_token: &AppToken,
+    channel: &str,
+    transport: &eventsub::Transport,
+    active: &HashSet<(String, String)>,
+) -> eyre::Result<usize> {
+    let user = client
+        .get_user_from_login(channel, app_token)
+        .await?
+        .ok_or_else(|| eyre::eyre!("Unable to retrieve user from: {channel}"))?;
+    let mut created = 0;
+    for kind in super::events::wanted(channel, broadcasters::is_authorized(channel).await) {
+        if active.contains(&(kind.clone(), user.id.to_string())) {
+            continue;
+        }
+        if subscribe_to(client, app_token, &kind, channel, user.id.clone(), transport).await {
+            created += 1;
+        }
+    }
+    Ok(created)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "0123456789abcdef";
+    const ID: &str = "e76c6bd4-55c9-4987-8304-da1588d8988b";
+    const TIMESTAMP: &str = "2024-09-30T05:35:13.221774018Z";
+    const BODY: &str = r#"{"challenge":"pogchamp-kappa-360noscope-vohiyo"}"#;
+    const SIGNATURE: &str =
+        "sha256=d4f0e404667224aa5fe4ef429dd60e811e7df0e5f4ce281d8dda30b36108941e";
+
+    #[test]
+    fn verifies_signatures() {
+        assert!(verify(SECRET, ID, TIMESTAMP, BODY.as_bytes(), SIGNATURE));
+        assert!(!verify("another secret", ID, TIMESTAMP, BODY.as_bytes(), SIGNATURE));
+        assert!(!verify(SECRET, ID, TIMESTAMP, b"{}", SIGNATURE));
+        assert!(!verify(SECRET, ID, TIMESTAMP, BODY.as_bytes(), &SIGNATURE[7..]));
+        assert!(!verify(SECRET, ID, TIMESTAMP, BODY.as_bytes(), "sha256=zz"));
+    }
+
+    #[test]
+    fn rejects_old_messages() {
+        let sent = DateTime::parse_from_rfc3339(TIMESTAMP).unwrap().with_timezone(&Utc);
+        assert!(fresh(TIMESTAMP, sent + chrono::Duration::minutes(5)));
+        assert!(!fresh(TIMESTAMP, sent + chrono::Duration::minutes(11)));
+        assert!(!fresh("yesterday", sent));
+    }
+
+    #[test]
+    fn deduplicates_ids() {
+        let start = Instant::now();
+        let mut seen = Seen::default();
+        assert!(seen.first(ID, start));
+        assert!(!seen.first(ID, start + Duration::from_secs(1)));
+        assert!(seen.first(ID, start + MAX_AGE));
+    }
+
+    #[test]
+    fn reads_challenges() {
+        assert_eq!(challenge(BODY).as_deref(), Some("pogchamp-kappa-360noscope-vohiyo"));
+        assert_eq!(challenge("{}"), None);
+    }
+}